@@ -1,93 +1,213 @@
-use std::io::Read;
-
 use anyhow::{Error, Result, anyhow};
-use cchain::display_control::{display_command_line, display_message};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use super::traits::AgentExecution;
+use crate::{
+    errors::YouError,
+    executors::{Executor, host::HostExecutor},
+    llm::{ResponseFormat, ToolCall, ToolDefinition},
+    parameters::{ScriptParameter, render_defaults},
+    risk::{RiskAssessment, RiskLevel, assess},
+    schema::{schema_of, validate},
+};
+
+use super::{execution::ExecutionResult, traits::AgentExecution};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// A command line tool that the LLM needs but could not find on the system.
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct CLIToInstall {
+    #[schemars(description = "The name of the cli that you want the user to install")]
     pub cli_name: String,
+    #[schemars(
+        description = "Base on the current system platform, suggest a command line for the user to install the tool"
+    )]
     pub suggested_installation_command: String,
+    #[schemars(
+        description = "Leave a notice if any to the user. Leave it null if you don't have a notice"
+    )]
     pub additional_notices: Option<String>,
 }
 
-impl Default for CLIToInstall {
-    fn default() -> Self {
-        Self { 
-            cli_name: "The name of the cli that you want the user to install".to_string(), 
-            suggested_installation_command: "Base on the current system platform, suggest a command line for the user to install the tool".to_string(), 
-            additional_notices: Some("Leave a notice if any to the user. Leave it null if you don't have a notice".to_string())
-        }
-    }
-}
-
-#[derive(Debug, Deserialize, Serialize, Clone)]
-#[serde(untagged)]
+/// Everything the LLM may answer with. The `action` field tells the variants apart.
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
 pub enum LLMActionType {
+    /// Execute a shell script on the user's machine
     Execute(ActionTypeExecute),
+    /// Ask the user for additional information
     RequestInformation(ActionTypeRequestInformation),
+    /// Ask the user to install command line tools that are not found
+    #[serde(rename = "request_clis_to_install")]
     RequestCLIsToInstall(ActionTypeRequestCLIsToInstall),
+    /// Run several commands in order. The user approves, skips or edits each step
+    Plan(ActionTypePlan),
 }
 
 impl LLMActionType {
-    /// The enum `LLMActionType` represents different types of actions that can be performed by an LLM agent.
+    /// Describes the reply format to the LLM, for backends that use the prompt rather than tools.
     ///
-    /// # Variants
-    ///
-    /// * `Execute` - Executes a shell command with an explanation.
-    /// * `RequestInformation` - Requests additional information from the user.
-    /// * `InstallDependencies` - Handles installation of CLI dependencies.
+    /// The format is the JSON schema of `LLMActionType` itself, followed by one example, so
+    /// the prompt can never drift from what `Step` accepts.
     ///
     /// # Examples
     ///
     /// ```
-    /// use crate::agents::command_json::{LLMActionType, ActionTypeExecute};
+    /// use crate::agents::command_json::LLMActionType;
     ///
-    /// // Create an execute action
-    /// let action = LLMActionType::Execute(ActionTypeExecute {
-    ///     command: "ls -la".to_string(),
-    ///     explanation: "List all files in the current directory with details".to_string(),
-    /// });
-    ///
-    /// // You can use pattern matching to handle different action types
-    /// match action {
-    ///     LLMActionType::Execute(exec) => println!("Command to execute: {}", exec.get_commands()),
-    ///     LLMActionType::RequestInformation(_) => println!("Need more information from user"),
-    ///     LLMActionType::InstallDependencies(_) => println!("Need to install dependencies"),
-    /// }
+    /// let template = LLMActionType::get_llm_action_type_prompt_template();
+    /// assert!(template.contains("\"action\""));
     /// ```
     pub fn get_llm_action_type_prompt_template() -> String {
-        // We use the default impl to fetch their corresponding prompts
-        let execution_template: ActionTypeExecute = ActionTypeExecute::default();
-        let request_information_template: ActionTypeRequestInformation =
-            ActionTypeRequestInformation::default();
-        let request_clis_template: ActionTypeRequestCLIsToInstall =
-            ActionTypeRequestCLIsToInstall::default();
-
-        // Convert them into json strings
-        let execution_json: String = serde_json::to_string(&execution_template).unwrap_or_default();
-        let request_information_json: String =
-            serde_json::to_string(&request_information_template).unwrap_or_default();
-        let request_clis_json: String =
-            serde_json::to_string(&request_clis_template).unwrap_or_default();
-
-        let mut prompt: String = String::new();
-        prompt.push_str(&format!(
-            "To execute a command, you may output: {}",
-            execution_json
-        ));
-        prompt.push_str(&format!(
-            "\n\nTo request additional information from the user, you may output: {}",
-            request_information_json
-        ));
-        prompt.push_str(&format!(
-            "\n\nIf the command is not found, you may output: {}",
-            request_clis_json
-        ));
-
-        prompt
+        let schema: String =
+            serde_json::to_string_pretty(&schema_of::<LLMActionType>()).unwrap_or_default();
+        let example: String = serde_json::to_string(&LLMActionType::Execute(ActionTypeExecute {
+            command: "ls -la".to_string(),
+            explanation: "List all files in the current directory with details".to_string(),
+            parameters: Vec::new(),
+        }))
+        .unwrap_or_default();
+
+        format!(
+            "Respond with one JSON object that follows this JSON schema. The `action` field selects what you want to do:\n{}\n\nFor example, to execute a command, you may output: {}",
+            schema, example
+        )
+    }
+
+    /// The response format to enforce on backends that support structured output.
+    pub fn get_response_format() -> ResponseFormat {
+        ResponseFormat {
+            name: "llm_action".to_string(),
+            schema: schema_of::<LLMActionType>(),
+        }
+    }
+
+    /// Describes each action as a native tool, for backends that support tool calling.
+    ///
+    /// Each tool is named after the `action` tag of its variant, and its arguments are the
+    /// fields of that variant, so a call can be mapped back with `from_tool_call`.
+    pub fn get_tool_definitions() -> Vec<ToolDefinition> {
+        let schema: serde_json::Value = schema_of::<LLMActionType>();
+        let definitions: &serde_json::Value = &schema["$defs"];
+
+        schema["oneOf"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|branch| {
+                let name: &str = branch["properties"]["action"]["const"].as_str()?;
+                let reference: &str = branch["$ref"].as_str()?;
+                let mut parameters: serde_json::Value =
+                    definitions[reference.rsplit('/').next()?].clone();
+                // Nested types refer to the shared definitions
+                parameters["$defs"] = definitions.clone();
+
+                Some(ToolDefinition {
+                    name: name.to_string(),
+                    description: branch["description"].as_str().unwrap_or(name).to_string(),
+                    parameters,
+                })
+            })
+            .collect()
+    }
+
+    /// Maps a call to one of the tools from `get_tool_definitions` back to its action.
+    pub fn from_tool_call(tool_call: &ToolCall) -> Result<Self, Error> {
+        let invalid_tool_call = |reason: String| YouError::InvalidToolCall {
+            name: tool_call.name.clone(),
+            reason,
+        };
+
+        let mut arguments: serde_json::Value = tool_call.arguments.clone();
+        let Some(arguments_object) = arguments.as_object_mut() else {
+            return Err(
+                invalid_tool_call("the arguments are not a JSON object".to_string()).into(),
+            );
+        };
+        arguments_object.insert(
+            "action".to_string(),
+            serde_json::Value::String(tool_call.name.clone()),
+        );
+
+        let violations: Vec<String> = validate(&schema_of::<LLMActionType>(), &arguments);
+        if !violations.is_empty() {
+            return Err(invalid_tool_call(violations.join("; ")).into());
+        }
+
+        Ok(serde_json::from_value(arguments)
+            .map_err(|error| invalid_tool_call(error.to_string()))?)
+    }
+
+    /// Runs the risk analyzer over every command this action would execute.
+    pub fn assess_risk(&self) -> RiskAssessment {
+        let mut risk: RiskAssessment = RiskAssessment::default();
+        match self {
+            Self::Execute(execute_action) => {
+                risk.merge(assess_in_current_directory(&execute_action.get_commands()))
+            }
+            Self::Plan(plan) => {
+                for step in &plan.steps {
+                    risk.merge(assess_in_current_directory(&step.command));
+                }
+            }
+            Self::RequestInformation(_) | Self::RequestCLIsToInstall(_) => {}
+        }
+
+        risk
+    }
+
+    /// Every command this action would execute, in order.
+    pub fn get_commands(&self) -> Vec<String> {
+        match self {
+            Self::Execute(execute_action) => vec![execute_action.get_commands()],
+            Self::Plan(plan) => plan.steps.iter().map(|step| step.command.clone()).collect(),
+            Self::RequestInformation(_) | Self::RequestCLIsToInstall(_) => Vec::new(),
+        }
+    }
+
+    /// What the commands of this action do, as the LLM explained it.
+    pub fn get_explanation(&self) -> String {
+        match self {
+            Self::Execute(execute_action) => execute_action.explanation.clone(),
+            Self::Plan(plan) => plan
+                .steps
+                .iter()
+                .map(|step| step.explanation.as_str())
+                .collect::<Vec<&str>>()
+                .join(" Then, "),
+            Self::RequestInformation(_) | Self::RequestCLIsToInstall(_) => String::new(),
+        }
+    }
+
+    /// A one line summary of the action, e.g. for the history.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Execute(execute_action) => format!(
+                "`{}` ({})",
+                execute_action.get_commands(),
+                execute_action.explanation
+            ),
+            Self::Plan(plan) => format!(
+                "a plan of {} step(s): {}",
+                plan.steps.len(),
+                self.get_commands()
+                    .iter()
+                    .map(|command| format!("`{}`", command))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::RequestInformation(request_info) => {
+                format!("to ask \"{}\"", request_info.request_additional_information)
+            }
+            Self::RequestCLIsToInstall(request_clis) => format!(
+                "to install {}",
+                request_clis
+                    .request_clis_to_install
+                    .iter()
+                    .map(|cli| cli.cli_name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+        }
     }
 
     /// Returns a formatted prompt string based on the action type for display to the user.
@@ -110,6 +230,7 @@ impl LLMActionType {
     /// let action = LLMActionType::Execute(ActionTypeExecute {
     ///     command: "ls -la".to_string(),
     ///     explanation: "List all files with details".to_string(),
+    ///     parameters: Vec::new(),
     /// });
     ///
     /// // Get the display prompt
@@ -120,14 +241,41 @@ impl LLMActionType {
     pub fn fetch_display_prompt(&self) -> String {
         match self {
             Self::Execute(execute_action) => {
+                let command: String = execute_action.get_commands();
+                let risk: RiskAssessment = assess_in_current_directory(&command);
+                let approval: &str = if risk.get_level() == RiskLevel::High {
+                    "yes for executing this high-risk command"
+                } else {
+                    "y for executing the command"
+                };
+
                 format!(
-                    "Your input: (y for executing the command, or type to hint LLM)\n    > {}\n        * {}\n",
-                    execute_action.command, execute_action.explanation
+                    "Your input: ({}, or type to hint LLM)\n    > {}\n        * {}\n{}",
+                    approval,
+                    command,
+                    execute_action.explanation,
+                    format_risk(&risk)
                 )
             }
             Self::RequestInformation(request_info) => {
                 request_info.request_additional_information.clone()
             }
+            Self::Plan(plan) => {
+                let mut prompt: String = String::from(
+                    "Your input: (y for going through the plan step by step, or type to hint LLM)\n",
+                );
+
+                for (index, step) in plan.steps.iter().enumerate() {
+                    prompt.push_str(&format!("    {}. > {}\n", index + 1, step.command));
+                    prompt.push_str(&format!("        * {}\n", step.explanation));
+                    if step.is_destructive {
+                        prompt.push_str("        ! destructive\n");
+                    }
+                    prompt.push_str(&format_risk(&assess_in_current_directory(&step.command)));
+                }
+
+                prompt
+            }
             Self::RequestCLIsToInstall(request_clis) => {
                 let mut prompt: String = String::new();
                 prompt.push_str("The following CLI tools need to be installed:\n\n");
@@ -143,7 +291,7 @@ impl LLMActionType {
                         prompt.push_str(&format!("Note: {}\n", notice));
                     }
 
-                    prompt.push_str("\n");
+                    prompt.push('\n');
                 }
 
                 prompt.push_str("Please install these tools and then continue.");
@@ -153,8 +301,22 @@ impl LLMActionType {
     }
 }
 
+/// Assesses `command` as if it ran in the current working directory.
+pub fn assess_in_current_directory(command: &str) -> RiskAssessment {
+    assess(command, &std::env::current_dir().unwrap_or_default())
+}
+
+/// The risk line shown under a command, empty for low-risk commands.
+fn format_risk(risk: &RiskAssessment) -> String {
+    if risk.get_level() == RiskLevel::Low {
+        return String::new();
+    }
+
+    format!("        ! {}\n", risk.get_summary())
+}
+
 impl AgentExecution for LLMActionType {
-    fn execute(&mut self) -> Result<String, Error> {
+    fn execute(&mut self) -> Result<ExecutionResult, Error> {
         match self {
             Self::Execute(execute_action) => {
                 // Execute the command using the Execute action type's implementation
@@ -164,149 +326,158 @@ impl AgentExecution for LLMActionType {
             Self::RequestCLIsToInstall(_) => {
                 Err(anyhow!("Cannot execute a request to install CLI tools"))
             }
+            Self::Plan(_) => Err(anyhow!("A plan is executed step by step")),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct ActionTypeExecute {
+    #[schemars(description = "a shell script, preferably in one line, to execute.")]
     command: String,
+    #[schemars(description = "explain the shell script briefly. one line maximum.")]
     explanation: String,
+    #[schemars(
+        description = "values in the script that the user may want to change when reusing it, like a directory or a count. Leave it empty if there are none."
+    )]
+    #[serde(default)]
+    parameters: Vec<ScriptParameter>,
 }
 
 impl AgentExecution for ActionTypeExecute {
-    fn execute(&mut self) -> Result<String, Error> {
-        let mut command: std::process::Command = if cfg!(target_os = "windows") {
-            let mut cmd = std::process::Command::new("cmd");
-            cmd.args(["/C", &self.command]);
-            cmd
-        } else {
-            let mut sh = std::process::Command::new("sh");
-            sh.args(["-c", &self.command]);
-            sh
-        };
-
-        // Set stdout and stderr to piped so that we can capture them
-        command.stdout(std::process::Stdio::piped());
-        command.stderr(std::process::Stdio::piped());
-
-        let command_in_text: String = format!(r#"{}"#, &self.command);
-        let command_string: &console::StyledObject<&String> =
-            &console::style(&command_in_text).bold();
-        display_message(
-            cchain::display_control::Level::Logging,
-            &format!("Start executing command: {}", command_string),
-        );
-
-        // Spawn the process
-        let mut child: std::process::Child = command
-            .spawn()
-            .map_err(|e| anyhow!("Failed to execute command: {}", e))?;
-
-        // Take the stdout and stderr handles
-        let stdout: std::process::ChildStdout = child.stdout.take().unwrap();
-        let stderr: std::process::ChildStderr = child.stderr.take().unwrap();
-
-        let (tx, rx) = std::sync::mpsc::channel();
-
-        // Spawn a thread to read stdout
-        let tx_clone: std::sync::mpsc::Sender<String> = tx.clone();
-        std::thread::spawn(move || {
-            let mut reader: std::io::BufReader<std::process::ChildStdout> =
-                std::io::BufReader::new(stdout);
-            let mut buffer: [u8; 1024] = [0; 1024];
-            loop {
-                match reader.read(&mut buffer) {
-                    Ok(0) => break, // EOF
-                    Ok(n) => {
-                        let text = String::from_utf8_lossy(&buffer[..n]).to_string();
-                        tx_clone.send(text).unwrap();
-                    }
-                    Err(_) => break,
-                }
-            }
-        });
-
-        // Spawn a thread to read stderr
-        std::thread::spawn(move || {
-            let mut reader: std::io::BufReader<std::process::ChildStderr> =
-                std::io::BufReader::new(stderr);
-            let mut buffer: [u8; 1024] = [0; 1024];
-            loop {
-                match reader.read(&mut buffer) {
-                    Ok(0) => break, // EOF
-                    Ok(n) => {
-                        let text = String::from_utf8_lossy(&buffer[..n]).to_string();
-                        tx.send(text).unwrap();
-                    }
-                    Err(_) => break,
-                }
-            }
-        });
-
-        let mut collected_output: String = String::new();
-        let terminal: console::Term = console::Term::stdout();
-        for received in rx {
-            display_command_line(&terminal, &received);
-            collected_output.push_str(&received);
-        }
-
-        // Wait for process completion
-        let status: std::process::ExitStatus = child
-            .wait()
-            .map_err(|e| anyhow!("Failed to wait on child process: {}", e))?;
-
-        if !status.success() {
-            return Err(anyhow!("Process exited with non-zero status: {}", status));
-        }
-
-        display_message(
-            cchain::display_control::Level::Logging,
-            &format!("Finished executing command: {}", command_string),
-        );
-
-        Ok(collected_output)
+    fn execute(&mut self) -> Result<ExecutionResult, Error> {
+        HostExecutor::new()?.execute(&self.get_commands())
     }
 }
 
 impl ActionTypeExecute {
-    pub fn get_commands(&self) -> &str {
+    /// The script with its parameters set to their defaults, as it runs for the current request.
+    ///
+    /// A script whose defaults do not fit its parameters is returned as is, so that
+    /// the user sees the placeholders rather than a half-filled script.
+    pub fn get_commands(&self) -> String {
+        render_defaults(&self.command, &self.parameters).unwrap_or_else(|_| self.command.clone())
+    }
+
+    /// The script with its `{{name}}` placeholders, to be filled again when it is reused.
+    pub fn get_template(&self) -> &str {
         &self.command
     }
-}
 
-impl Default for ActionTypeExecute {
-    fn default() -> Self {
-        Self {
-            explanation: "explain the shell script briefly. one line maximum. ".to_string(),
-            command: "a shell script, preferably in one line, to execute.".to_string(),
-        }
+    pub fn get_parameters(&self) -> &[ScriptParameter] {
+        &self.parameters
+    }
+
+    pub fn get_explanation(&self) -> &str {
+        &self.explanation
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct ActionTypeRequestInformation {
+    #[schemars(description = "Describe what information you want the user to add on.")]
     request_additional_information: String,
 }
 
-impl Default for ActionTypeRequestInformation {
-    fn default() -> Self {
-        Self {
-            request_additional_information:
-                "Describe what information you want the user to add on. ".to_string(),
-        }
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct ActionTypeRequestCLIsToInstall {
+    request_clis_to_install: Vec<CLIToInstall>,
+}
+
+impl ActionTypeRequestCLIsToInstall {
+    pub fn get_clis(&self) -> &[CLIToInstall] {
+        &self.request_clis_to_install
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct ActionTypeRequestCLIsToInstall {
-    request_clis_to_install: Vec<CLIToInstall>,
+/// One command of a plan.
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct PlanStep {
+    #[schemars(description = "a shell command, preferably in one line, to execute.")]
+    pub command: String,
+    #[schemars(description = "explain the command briefly. one line maximum.")]
+    pub explanation: String,
+    #[schemars(
+        description = "true if the command deletes, overwrites or otherwise irreversibly changes data."
+    )]
+    pub is_destructive: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct ActionTypePlan {
+    #[schemars(
+        description = "The steps of the plan, in the order they should run. Use one step per command instead of chaining them with `&&`."
+    )]
+    pub steps: Vec<PlanStep>,
+}
+
+/// What became of a plan step once the user went through it.
+#[derive(Debug, Clone)]
+pub enum PlanStepOutcome {
+    /// The step ran successfully
+    Succeeded(ExecutionResult),
+    /// The step could not be run, or exited with a non-zero code
+    Failed(String),
+    /// The user skipped the step
+    Skipped,
+    /// The step ran in the sandbox, but the user discarded its changes
+    Discarded(ExecutionResult),
+    /// The plan was aborted, or stopped at a failure, before reaching the step
+    NotRun,
+}
+
+/// The steps of a plan as they were run, which may differ from the proposed ones
+/// when the user edited them.
+#[derive(Debug, Clone, Default)]
+pub struct PlanReport {
+    pub steps: Vec<(PlanStep, PlanStepOutcome)>,
+    pub is_aborted: bool,
 }
 
-impl Default for ActionTypeRequestCLIsToInstall {
-    fn default() -> Self {
-        Self {
-            request_clis_to_install: vec![CLIToInstall::default()],
+impl PlanReport {
+    pub fn has_failures(&self) -> bool {
+        self.steps
+            .iter()
+            .any(|(_, outcome)| matches!(outcome, PlanStepOutcome::Failed(_)))
+    }
+
+    /// The commands that ran successfully, in order, e.g. for saving them to a script.
+    pub fn get_succeeded_commands(&self) -> Vec<&str> {
+        self.steps
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, PlanStepOutcome::Succeeded(_)))
+            .map(|(step, _)| step.command.as_str())
+            .collect()
+    }
+
+    /// Describes every step and its outcome, to be fed back to the LLM.
+    pub fn to_feedback(&self) -> String {
+        let mut feedback: String = String::from("Here is how the plan went:\n");
+
+        for (index, (step, outcome)) in self.steps.iter().enumerate() {
+            feedback.push_str(&format!("\nStep {}: ", index + 1));
+            match outcome {
+                PlanStepOutcome::Succeeded(result) => {
+                    feedback.push_str(&result.to_feedback(&step.command))
+                }
+                PlanStepOutcome::Failed(error) => feedback.push_str(error),
+                PlanStepOutcome::Skipped => {
+                    feedback.push_str(&format!("`{}` was skipped by the user.\n", step.command))
+                }
+                PlanStepOutcome::Discarded(result) => {
+                    feedback.push_str(&result.to_feedback(&step.command));
+                    feedback.push_str("It ran in a sandbox and the user discarded its changes.\n");
+                }
+                PlanStepOutcome::NotRun => {
+                    feedback.push_str(&format!("`{}` was not run.\n", step.command))
+                }
+            }
+        }
+
+        if self.is_aborted {
+            feedback.push_str("\nThe user aborted the plan.\n");
         }
+
+        feedback
     }
 }
@@ -1,49 +1,89 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 use async_openai::types::ChatCompletionRequestMessage;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    configurations::{Configurations, RetryPolicy},
     information::ContextualInformation,
-    llm::{Context, FromNaturalLanguageToJSON, LLM},
+    llm::{Context, FromNaturalLanguageToJSON, LLMBackend, ResponseFormat, create_llm_backend},
+    schema::schema_of,
 };
 
 use super::traits::Step;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct CommandExplained {
+    #[schemars(description = "explain the command and its arguments briefly. one line maximum.")]
     explanation: String,
+    #[serde(default)]
+    #[schemars(
+        description = "every token of the command in order, such as programs, subcommands, flags, arguments, pipes and redirections, with what it does."
+    )]
+    breakdown: Vec<TokenExplained>,
+}
+
+/// One token of an explained command.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct TokenExplained {
+    #[schemars(description = "the token exactly as it appears in the command")]
+    pub token: String,
+    #[schemars(description = "what the token does. one short sentence.")]
+    pub explanation: String,
+}
+
+impl CommandExplained {
+    pub fn get_breakdown(&self) -> &[TokenExplained] {
+        &self.breakdown
+    }
+
+    /// One indented line per token, empty if the LLM gave no breakdown.
+    pub fn format_breakdown(&self) -> String {
+        self.breakdown
+            .iter()
+            .map(|token| format!("    {}: {}\n", token.token, token.explanation))
+            .collect()
+    }
 }
 
 impl Display for CommandExplained {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!("{}", self.explanation))
+        f.write_str(&self.explanation)?;
+        if !self.breakdown.is_empty() {
+            f.write_str("\n")?;
+            f.write_str(self.format_breakdown().trim_end())?;
+        }
+
+        Ok(())
     }
 }
 
 pub struct CommandLineExplainAgent {
-    /// LLM client
-    llm: LLM,
+    /// LLM backend
+    llm: Arc<dyn LLMBackend>,
+    /// How failed generations are retried
+    retry_policy: RetryPolicy,
     /// LLM context
     context: Vec<ChatCompletionRequestMessage>,
 }
 
 impl CommandLineExplainAgent {
-    pub fn new(contextual_information_object: &ContextualInformation) -> anyhow::Result<Self> {
+    pub fn new(
+        contextual_information_object: &ContextualInformation,
+        configurations: &Configurations,
+    ) -> anyhow::Result<Self> {
         let mut context: Vec<ChatCompletionRequestMessage> = Vec::new();
-        let example_data_structure = CommandExplained {
-            explanation: "explain the command and its arguments briefly. one line maximum."
-                .to_string(),
-        };
+        let schema: serde_json::Value = schema_of::<CommandExplained>();
 
-        let mut system_prompt: String = format!(
-            "You are an assistant that explains shell commands in simple terms. Please provide a brief explanation for any command given to you.\n\n"
+        let mut system_prompt: String = String::from(
+            "You are an assistant that explains shell commands in simple terms. Please provide a brief explanation for any command given to you, followed by a breakdown of each of its tokens.\n\n",
         );
         system_prompt.push_str(&contextual_information_object.get_contextual_information()?);
 
         system_prompt.push_str(&format!(
-            "You need to respond json format like this: {}",
-            &serde_json::to_string_pretty(&example_data_structure)?
+            "You need to respond with one JSON object that follows this JSON schema: {}",
+            &serde_json::to_string_pretty(&schema)?
         ));
 
         context.push(
@@ -54,7 +94,14 @@ impl CommandLineExplainAgent {
         );
 
         Ok(CommandLineExplainAgent {
-            llm: LLM::new()?,
+            llm: create_llm_backend(
+                configurations,
+                Some(ResponseFormat {
+                    name: "command_explained".to_string(),
+                    schema,
+                }),
+            )?,
+            retry_policy: configurations.retry_policy.clone(),
             context,
         })
     }
@@ -71,8 +118,12 @@ impl Context for CommandLineExplainAgent {
 }
 
 impl FromNaturalLanguageToJSON for CommandLineExplainAgent {
-    fn get_llm(&self) -> &LLM {
-        &self.llm
+    fn get_llm(&self) -> &dyn LLMBackend {
+        self.llm.as_ref()
+    }
+
+    fn get_retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
     }
 }
 
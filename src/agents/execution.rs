@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How much of each output stream is sent back to the LLM. Failures tend to be
+/// explained at the end of the output, so the tail is kept.
+const MAX_FEEDBACK_OUTPUT_CHARACTERS: usize = 4000;
+
+/// What running a command produced.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionResult {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` if the process was killed by a signal
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+}
+
+impl ExecutionResult {
+    pub fn is_success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// One line summary, e.g. for error messages.
+    pub fn get_status(&self) -> String {
+        match self.exit_code {
+            Some(exit_code) => format!(
+                "exited with code {} after {:.2}s",
+                exit_code,
+                self.duration.as_secs_f64()
+            ),
+            None => format!(
+                "was terminated by a signal after {:.2}s",
+                self.duration.as_secs_f64()
+            ),
+        }
+    }
+
+    /// Describes how `command` went, with both output streams, to be fed back to the LLM.
+    pub fn to_feedback(&self, command: &str) -> String {
+        format!(
+            "The command `{}` {}.\nstdout:\n{}\nstderr:\n{}\n",
+            command,
+            self.get_status(),
+            truncate_output(&self.stdout),
+            truncate_output(&self.stderr)
+        )
+    }
+}
+
+fn truncate_output(output: &str) -> String {
+    let total_characters: usize = output.chars().count();
+    if total_characters <= MAX_FEEDBACK_OUTPUT_CHARACTERS {
+        return output.to_string();
+    }
+
+    let tail: String = output
+        .chars()
+        .skip(total_characters - MAX_FEEDBACK_OUTPUT_CHARACTERS)
+        .collect();
+    format!(
+        "[{} earlier characters omitted]\n{}",
+        total_characters - MAX_FEEDBACK_OUTPUT_CHARACTERS,
+        tail
+    )
+}
@@ -1,4 +1,5 @@
 pub mod command_json;
 pub mod command_line_explain_agent;
+pub mod execution;
 pub mod semi_autonomous_command_line_agent;
 pub mod traits;
@@ -1,13 +1,14 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, sync::Arc};
 
 use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs};
 
 use crate::{
-    information::{
-        ContextualInformation, get_current_directory_structure, get_current_time,
-        get_system_information,
+    configurations::{Configurations, RetryPolicy},
+    information::ContextualInformation,
+    llm::{
+        Context, FromNaturalLanguageToJSON, LLMBackend, ToolCall, ToolDefinition,
+        create_llm_backend,
     },
-    llm::{Context, FromNaturalLanguageToJSON, LLM},
 };
 
 use super::{command_json::LLMActionType, traits::Step};
@@ -19,14 +20,21 @@ use super::{command_json::LLMActionType, traits::Step};
 pub struct SemiAutonomousCommandLineAgent {
     /// The command lines to execute
     command_line_to_execute: Option<String>,
-    /// LLM client
-    llm: LLM,
+    /// LLM backend
+    llm: Arc<dyn LLMBackend>,
+    /// How failed generations are retried
+    retry_policy: RetryPolicy,
+    /// Actions offered as native tools, empty when tool calling is disabled
+    tools: Vec<ToolDefinition>,
     /// LLM context
     context: Vec<ChatCompletionRequestMessage>,
 }
 
 impl SemiAutonomousCommandLineAgent {
-    pub fn new(contextual_information_object: &ContextualInformation) -> anyhow::Result<Self> {
+    pub fn new(
+        contextual_information_object: &ContextualInformation,
+        configurations: &Configurations,
+    ) -> anyhow::Result<Self> {
         // Setup a command line template for prompting the LLM
         let mut example_env_var: HashMap<String, String> = HashMap::new();
         example_env_var.insert("EXAMPLE".to_string(), "this is a value".to_string());
@@ -34,7 +42,7 @@ impl SemiAutonomousCommandLineAgent {
         let command_json_template: String = LLMActionType::get_llm_action_type_prompt_template();
 
         // The system prompt for the LLM
-        let mut prompt: String = "Please translate the following command sent by the user to an executable sh command/script in a json.
+        let mut prompt: String = "Please translate the following command sent by the user to an executable command/script for the shell given below in a json.
             If you would like to have additional information to send or receive from the user, or perform other actions, please refer to the templates below.\n"
             .to_string();
 
@@ -42,27 +50,35 @@ impl SemiAutonomousCommandLineAgent {
         prompt.push_str(&contextual_information_object.get_contextual_information()?);
 
         // Inject the template to the prompt
-        prompt.push_str(&format!(
-            "This is your template, output in json: {}:\n",
-            &serde_json::to_string_pretty(&command_json_template)?
-        ));
+        prompt.push_str(&command_json_template);
+        prompt.push('\n');
 
         // Additional instructions
         prompt.push_str("\nAdditional instructions:");
-        prompt.push_str("- The `interpreter` now only supports sh. ");
+        prompt.push_str("- Commands run in the shell given above, write them in its syntax. ");
+        prompt.push_str("- When a task needs several commands, respond with a `plan` of one command per step instead of chaining them with `&&`, and mark the steps that delete or overwrite data as destructive. ");
+        prompt.push_str("- When a command works on values the user may want to change when running it again, like a directory, a file or a count, declare them as `parameters` and refer to them as {{name}} in the command, without quotes. ");
+        if configurations.llm.enable_tool_calling {
+            prompt.push_str("- Respond by calling one of the tools you are given. ");
+        }
 
         // Construct the context
-        let mut context: Vec<ChatCompletionRequestMessage> = Vec::new();
-        context.push(
+        let context: Vec<ChatCompletionRequestMessage> = vec![
             ChatCompletionRequestSystemMessageArgs::default()
                 .content(prompt)
                 .build()?
                 .into(),
-        );
+        ];
 
         Ok(SemiAutonomousCommandLineAgent {
             command_line_to_execute: None,
-            llm: LLM::new()?,
+            llm: create_llm_backend(configurations, Some(LLMActionType::get_response_format()))?,
+            retry_policy: configurations.retry_policy.clone(),
+            tools: if configurations.llm.enable_tool_calling {
+                LLMActionType::get_tool_definitions()
+            } else {
+                Vec::new()
+            },
             context,
         })
     }
@@ -72,10 +88,7 @@ impl Step<LLMActionType> for SemiAutonomousCommandLineAgent {}
 
 impl Display for SemiAutonomousCommandLineAgent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!(
-            "{}",
-            self.command_line_to_execute.as_ref().unwrap().to_string()
-        ))
+        f.write_str(self.command_line_to_execute.as_ref().unwrap())
     }
 }
 
@@ -90,7 +103,21 @@ impl Context for SemiAutonomousCommandLineAgent {
 }
 
 impl FromNaturalLanguageToJSON for SemiAutonomousCommandLineAgent {
-    fn get_llm(&self) -> &LLM {
-        &self.llm
+    fn get_llm(&self) -> &dyn LLMBackend {
+        self.llm.as_ref()
+    }
+
+    fn get_retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    fn get_tools(&self) -> Vec<ToolDefinition> {
+        self.tools.clone()
+    }
+
+    fn tool_call_to_json(&self, tool_call: &ToolCall) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(&LLMActionType::from_tool_call(
+            tool_call,
+        )?)?)
     }
 }
@@ -1,8 +1,18 @@
+use std::time::Duration;
+
 use anyhow::Error;
-use cchain::display_control::display_tree_message;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::llm::{Context, FromNaturalLanguageToJSON};
+use crate::{
+    configurations::RetryPolicy,
+    errors::YouError,
+    llm::{Context, FromNaturalLanguageToJSON, StreamHandler},
+    output::display_tree_message,
+    schema::parse_validated,
+};
+
+use super::execution::ExecutionResult;
 
 /// The `Step` trait defines a workflow step for an agent that processes user queries
 /// and converts them into actionable commands or objects of type `T`.
@@ -61,7 +71,7 @@ use crate::llm::{Context, FromNaturalLanguageToJSON};
 /// }
 pub trait Step<T>: Context + FromNaturalLanguageToJSON
 where
-    for<'de> T: Serialize + Deserialize<'de>,
+    for<'de> T: Serialize + Deserialize<'de> + JsonSchema,
 {
     /// Executes the next step of the agent's workflow.
     ///
@@ -74,6 +84,15 @@ where
     /// This function returns a result containing the deserialized object of type `T` if successful,
     /// or an `Error` if something goes wrong during the process.
     ///
+    /// The reply is checked against the JSON schema of `T` before it is deserialized.
+    ///
+    /// Failures are retried according to `get_retry_policy`. Malformed replies, and replies
+    /// that do not follow the schema, are answered with a repair message carrying the
+    /// precise errors, so the model can correct itself. Transport
+    /// errors are retried with exponential backoff. Once the attempts run out, the error is a
+    /// `YouError::LLMUnreachable` if the last one failed to reach the LLM, or a
+    /// `YouError::RetriesExhausted` otherwise.
+    ///
     /// # Example
     ///
     /// ```
@@ -119,22 +138,124 @@ where
     /// }
     /// ```
     fn next_step(&mut self, user_query: &str) -> Result<T, Error> {
+        self.next_step_with_stream_handler(user_query, None)
+    }
+
+    /// Same as `next_step`, but streams the LLM's reply to `stream_handler`
+    /// while it is being generated.
+    fn next_step_streamed(
+        &mut self,
+        user_query: &str,
+        stream_handler: &mut dyn StreamHandler,
+    ) -> Result<T, Error> {
+        self.next_step_with_stream_handler(user_query, Some(stream_handler))
+    }
+
+    /// Shared implementation of `next_step` and `next_step_streamed`.
+    fn next_step_with_stream_handler(
+        &mut self,
+        user_query: &str,
+        mut stream_handler: Option<&mut dyn StreamHandler>,
+    ) -> Result<T, Error> {
         // Update the context by adding the user query
         self.add(async_openai::types::Role::User, user_query.to_string())?;
 
-        let result: T = loop {
-            let response: String = self.from_natural_language_to_json()?;
+        let retry_policy: RetryPolicy = self.get_retry_policy().clone();
+        let mut backoff: Duration =
+            Duration::from_millis(retry_policy.initial_backoff_milliseconds);
+        let mut attempts: u32 = 0;
+
+        loop {
+            attempts += 1;
+
+            let response: Result<String, Error> = match stream_handler.as_deref_mut() {
+                Some(stream_handler) => self.from_natural_language_to_json_streamed(stream_handler),
+                None => self.from_natural_language_to_json(),
+            };
+
+            // Transport errors back off before retrying, malformed replies are repaired instead
+            let (last_error, retry_message, should_back_off): (String, &str, bool) = match response
+            {
+                Ok(response) => match parse_validated::<T>(&response) {
+                    Ok(command) => return Ok(command),
+                    Err(error) => {
+                        self.add(async_openai::types::Role::Assistant, response)?;
+                        self.add(
+                            async_openai::types::Role::User,
+                            format!(
+                                "Your previous response could not be parsed: {}. Please respond again with only a JSON object that follows the schema.",
+                                error
+                            ),
+                        )?;
+                        (
+                            error.to_string(),
+                            "LLM returned a wrong JSON, retrying...",
+                            false,
+                        )
+                    }
+                },
+                Err(error) => match error.downcast_ref::<YouError>() {
+                    Some(YouError::NoJSONInResponse { response }) => {
+                        self.add(async_openai::types::Role::Assistant, response.clone())?;
+                        self.add(
+                            async_openai::types::Role::User,
+                            "Your previous response did not contain a JSON object. Please respond again with only a JSON object that follows the schema.".to_string(),
+                        )?;
+                        (
+                            error.to_string(),
+                            "LLM returned no JSON, retrying...",
+                            false,
+                        )
+                    }
+                    Some(YouError::InvalidToolCall { .. }) => {
+                        self.add(
+                            async_openai::types::Role::User,
+                            format!(
+                                "{}. Please call one of the tools you were given, with arguments that follow its schema.",
+                                error
+                            ),
+                        )?;
+                        (
+                            error.to_string(),
+                            "LLM made an invalid tool call, retrying...",
+                            false,
+                        )
+                    }
+                    Some(YouError::LLMRequestFailed { .. }) => (
+                        error.to_string(),
+                        "Failed to reach the LLM, retrying...",
+                        true,
+                    ),
+                    // Anything else, e.g. a rejected API key, fails the same way again
+                    _ => return Err(error),
+                },
+            };
 
-            match serde_json::from_str(&response) {
-                Ok(command) => break command,
-                Err(_) => {
-                    display_tree_message(2, "LLM returned a wrong JSON, retrying...");
-                    continue;
+            if attempts >= retry_policy.max_attempts {
+                return Err(match should_back_off {
+                    true => YouError::LLMUnreachable {
+                        attempts,
+                        last_error,
+                    },
+                    false => YouError::RetriesExhausted {
+                        attempts,
+                        last_error,
+                    },
                 }
+                .into());
             }
-        };
 
-        Ok(result)
+            display_tree_message(2, retry_message);
+            if let Some(stream_handler) = stream_handler.as_deref_mut() {
+                stream_handler.on_retry();
+            }
+
+            if should_back_off {
+                std::thread::sleep(backoff);
+                backoff =
+                    (backoff * 2).min(Duration::from_millis(retry_policy.max_backoff_milliseconds));
+            }
+        }
     }
 }
 
@@ -189,8 +310,8 @@ pub trait AgentExecution {
     ///
     /// # Returns
     ///
-    /// This function returns a `Result` containing a `String` if the execution is successful,
-    /// or an `Error` if something goes wrong during the execution.
+    /// This function returns an `ExecutionResult` with the captured output and exit code once
+    /// the command has run, whether it succeeded or not, or an `Error` if it could not be run.
     ///
     /// # Example
     ///
@@ -225,5 +346,5 @@ pub trait AgentExecution {
     ///     Ok(())
     /// }
     /// ```
-    fn execute(&mut self) -> Result<String, Error>;
+    fn execute(&mut self) -> Result<ExecutionResult, Error>;
 }
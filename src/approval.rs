@@ -0,0 +1,81 @@
+use anyhow::{Error, Result};
+use cchain::display_control::Level;
+use regex::Regex;
+
+use crate::{
+    agents::command_json::assess_in_current_directory,
+    configurations::ApprovalPolicy,
+    errors::YouError,
+    output::display_message,
+    risk::{RiskAssessment, RiskLevel, is_simple_command},
+};
+
+/// Checks `command` against the approval policy, for running it without asking.
+///
+/// `allowed_commands` only approve a single simple command without a high-risk
+/// finding, so that an allowed prefix such as `^kubectl get ` cannot carry
+/// `; rm -rf ~` or a pipe into a shell along.
+///
+/// Returns why it is approved, or fails with `YouError::NotApprovedByPolicy`.
+pub fn approve_by_policy(policy: &ApprovalPolicy, command: &str) -> Result<String, Error> {
+    let risk: RiskAssessment = assess_in_current_directory(command);
+    if policy.auto_approve_read_only && risk.is_read_only() {
+        return Ok("read-only".to_string());
+    }
+
+    if risk.get_level() == RiskLevel::High || !is_simple_command(command) {
+        return Err(YouError::NotApprovedByPolicy {
+            command: command.to_string(),
+        }
+        .into());
+    }
+
+    for pattern in &policy.allowed_commands {
+        let regex: Regex = Regex::new(pattern).map_err(|error| YouError::InvalidConfiguration {
+            reason: format!(
+                "`{}` in `approval_policy.allowed_commands` is not a valid regular expression: {}",
+                pattern, error
+            ),
+        })?;
+
+        if regex.is_match(command.trim()) {
+            return Ok(format!("matches `{}`", pattern));
+        }
+    }
+
+    Err(YouError::NotApprovedByPolicy {
+        command: command.to_string(),
+    }
+    .into())
+}
+
+/// Answers the question of the agent with the `--context` given on the command line.
+///
+/// `previous_input` is what the agent was last told. If that already was the context,
+/// asking again would go in circles, so this fails with `YouError::InputRequired`.
+pub fn answer_from_context(
+    policy: &ApprovalPolicy,
+    question: &str,
+    previous_input: &str,
+) -> Result<String, Error> {
+    let answer: String = policy.context.join("\n");
+    if answer.is_empty() || previous_input == answer {
+        return Err(YouError::InputRequired {
+            question: question.to_string(),
+        }
+        .into());
+    }
+
+    Ok(answer)
+}
+
+/// Shows the answer given to a prompt on behalf of the user, as one `key="value"` line
+/// that logs can be searched for, e.g. `prompt="approve_command" answer="y" reason="read-only"`.
+pub fn log_answer(prompt: &str, answer: &str, details: &[(&str, &str)]) {
+    let mut line: String = format!("prompt={:?} answer={:?}", prompt, answer);
+    for (key, value) in details {
+        line.push_str(&format!(" {}={:?}", key, value));
+    }
+
+    display_message(Level::Logging, &line);
+}
@@ -1,11 +1,16 @@
+use std::path::PathBuf;
+
 use clap::{
-    Args, Parser, Subcommand,
+    Args, Parser, Subcommand, ValueEnum,
     builder::{
         Styles,
         styling::{AnsiColor, Effects},
     },
     crate_authors, crate_description, crate_version,
 };
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+
+use crate::{cache::Cache, configurations::Configurations, traits::GlobalResourceInitialization};
 
 // Configures Clap v3-style help menu colors
 const STYLES: Styles = Styles::styled()
@@ -14,14 +19,37 @@ const STYLES: Styles = Styles::styled()
     .literal(AnsiColor::Cyan.on_default().effects(Effects::BOLD))
     .placeholder(AnsiColor::Cyan.on_default());
 
+// Documents the codes that `you` exits with, for scripts that run it
+const EXIT_CODES: &str = "\
+Exit codes:
+  0    Success
+  1    Any other error
+  2    Invalid arguments
+  120  The user declined to run the command
+  121  The LLM cannot be reached
+  122  The configurations are invalid
+  123  The LLM kept replying with unusable responses
+A command that fails passes its own exit code on, e.g. `you exec` or a cached script.";
+
 #[derive(Debug, Parser)]
 #[command(name = "you", author = crate_authors!(), long_version = crate_version!())]
 #[command(about = crate_description!())]
 #[command(styles = STYLES)]
+#[command(after_help = EXIT_CODES)]
 pub struct Arguments {
     /// Grouped features provided by `you`
     #[clap(subcommand)]
     pub commands: Commands,
+    /// How to report what happens. `json` writes one JSON object per line, for editors
+    /// and wrappers, e.g. the generated actions, the approvals and the command output
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
 }
 
 #[derive(Debug, Subcommand)]
@@ -33,12 +61,32 @@ pub enum Commands {
     /// Explain a given command
     #[clap(short_flag = 'e')]
     Explain(ExplainArguments),
+    /// Print the command for a request without running it, for the shell to insert. See `you init`
+    Suggest(SuggestArguments),
+    /// Print the shell function that binds Ctrl-G to `you suggest`, e.g. `eval "$(you init bash)"`
+    Init(InitArguments),
     /// List all saved scripts in the cache. 
     #[clap(visible_alias = "ls")]
     List(ListArguments),
     /// Remove a specified script from the cache. 
     #[clap(visible_alias = "rm")]
     Remove(RemoveArguments),
+    /// Display a cached script, with what it was saved for
+    Show(ShowArguments),
+    /// Open a cached script in $EDITOR, then check it again
+    Edit(EditArguments),
+    /// Rename a cached script
+    Rename(RenameArguments),
+    /// Run a cached script by name, passing the arguments that follow on to it
+    Exec(ExecArguments),
+    /// Write cached scripts and their metadata to a bundle, for sharing them
+    Export(ExportArguments),
+    /// Add the scripts of a bundle to the cache, after approving each of them
+    Import(ImportArguments),
+    /// Restore the files changed by the last executed command
+    Undo(UndoArguments),
+    /// Browse past sessions, and run their commands again
+    History(HistoryArguments),
     /// Display the version of `you`
     #[clap(short_flag = 'v')]
     Version(VersionArguments),
@@ -50,6 +98,31 @@ pub struct RunArguments {
     /// Convert natural language instruction to an executable command. Leave it empty to run interactive mode.
     #[arg(group = "sources")]
     pub command_in_natural_language: Option<String>,
+    /// Go through the conversation without executing anything. Prints the proposed commands,
+    /// what each of their tokens does and their risk, followed by a JSON summary.
+    #[arg(long, requires = "command_in_natural_language")]
+    pub dry_run: bool,
+    /// Run commands in a sandbox against a copy of the working directory, then commit or
+    /// discard their changes. Linux only.
+    #[arg(long)]
+    pub sandbox: bool,
+    /// Run the cached script with this name, setting one of its parameters, e.g. `--param dir=./logs`.
+    /// Repeat it to set several parameters. The others keep their default
+    #[arg(
+        long = "param",
+        value_name = "NAME=VALUE",
+        requires = "command_in_natural_language",
+        conflicts_with = "dry_run"
+    )]
+    pub params: Vec<String>,
+    /// Never prompt, for CI and scripts. Only commands that are read-only, or that match
+    /// `approval_policy.allowed_commands` in configurations.json, are run
+    #[arg(long, short = 'y', requires = "command_in_natural_language")]
+    pub yes: bool,
+    /// With --yes, what questions of the LLM are answered with, e.g. `--context "the database is staging"`.
+    /// Repeat it to give several lines. Without it, a question stops `you`
+    #[arg(long, value_name = "TEXT", requires = "yes")]
+    pub context: Vec<String>,
 }
 
 #[derive(Debug, Args)]
@@ -61,8 +134,42 @@ pub struct ExplainArguments {
 }
 
 #[derive(Debug, Args)]
-#[command(group = clap::ArgGroup::new("sources").required(false).multiple(false))]
-pub struct ListArguments;
+pub struct SuggestArguments {
+    /// What the command should do, in natural language
+    pub request: String,
+}
+
+#[derive(Debug, Args)]
+pub struct InitArguments {
+    /// The shell to integrate with
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Debug, Args)]
+pub struct ListArguments {
+    /// Order of the scripts. Timestamps and run counts list the highest first
+    #[arg(long, value_enum, default_value_t = SortScriptsBy::Name)]
+    pub sort: SortScriptsBy,
+    /// Only list the scripts with this tag. Repeat it to require several tags
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SortScriptsBy {
+    Name,
+    Created,
+    LastUsed,
+    Runs,
+}
 
 #[derive(Debug, Args)]
 #[command(group = clap::ArgGroup::new("sources").required(true).multiple(false))]
@@ -72,6 +179,123 @@ pub struct RemoveArguments {
     pub script_name: String,
 }
 
+#[derive(Debug, Args)]
+pub struct ShowArguments {
+    /// Name of the script to display
+    pub script_name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct EditArguments {
+    /// Name of the script to edit
+    pub script_name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct RenameArguments {
+    /// Current name of the script
+    pub old_name: String,
+    /// New name of the script
+    pub new_name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ExecArguments {
+    /// Never prompt, for CI and scripts. The script only runs if it is read-only, or if
+    /// it matches `approval_policy.allowed_commands` in configurations.json
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+    /// Name of the script to run
+    #[arg(add = ArgValueCandidates::new(complete_script_names))]
+    pub script_name: String,
+    /// Arguments for the script, which it refers to as "$@", $1, $2...
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub arguments: Vec<String>,
+}
+
+/// Names of the cached scripts, for shell completion. Nothing is completed when they cannot
+/// be listed, errors would only get in the way of typing.
+fn complete_script_names() -> Vec<CompletionCandidate> {
+    let (Ok(mut cache), Ok(configurations)) = (Cache::load(), Configurations::load()) else {
+        return Vec::new();
+    };
+    for directory in configurations.script_search_paths.iter() {
+        let _ = cache.add_shared_directory(directory);
+    }
+
+    cache
+        .list_scripts()
+        .into_iter()
+        .map(|script| {
+            CompletionCandidate::new(script.name).help(Some(script.metadata.request.into()))
+        })
+        .collect()
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArguments {
+    /// Names of the scripts to export. Every script is exported without any
+    pub script_names: Vec<String>,
+    /// Only export the scripts with this tag. Repeat it to require several tags
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+    /// Where to write the bundle
+    #[arg(short = 'o', long = "output-file", default_value = "you-scripts.json")]
+    pub output_file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArguments {
+    /// The bundle written by `you export`
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Args)]
+#[command(group = clap::ArgGroup::new("sources").required(false).multiple(false))]
+pub struct UndoArguments;
+
+#[derive(Debug, Args)]
+pub struct HistoryArguments {
+    /// What to do with the history. Lists the most recent sessions by default
+    #[clap(subcommand)]
+    pub commands: Option<HistoryCommands>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommands {
+    /// List the most recent sessions
+    #[clap(visible_alias = "ls")]
+    List(HistoryListArguments),
+    /// List the sessions whose prompt, instructions or commands contain the query
+    Search(HistorySearchArguments),
+    /// Show what was proposed, answered and executed in a session
+    Show(HistorySessionArguments),
+    /// Run the commands that succeeded in a session again, in the current directory
+    Rerun(HistorySessionArguments),
+}
+
+#[derive(Debug, Args)]
+pub struct HistoryListArguments {
+    /// Maximum number of sessions to list
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub limit: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct HistorySearchArguments {
+    /// Text to look for, ignoring case
+    pub query: String,
+    /// Maximum number of sessions to list
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub limit: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct HistorySessionArguments {
+    /// Id of the session, as listed by `you history`
+    pub id: String,
+}
+
 #[derive(Debug, Args)]
 #[command(group = clap::ArgGroup::new("sources").required(false).multiple(false))]
 pub struct VersionArguments;
@@ -1,19 +1,182 @@
 use std::{
+    collections::{BTreeMap, HashSet},
     fs::{DirEntry, File, create_dir, read_dir},
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
 
 use crate::{
+    configurations::CacheLookupPolicy,
     constants::YOU_CACHE_DIRECTORY,
+    executors::get_shell_name,
+    parameters::ScriptParameter,
     traits::{GlobalResourceInitialization, acquire_you_home_directory},
 };
 
+/// Where the metadata of the scripts is kept, in the cache directory
+const CACHE_INDEX_JSON: &str = "index.json";
+/// The format of `ScriptBundle`, raised whenever older versions cannot read it
+const SCRIPT_BUNDLE_VERSION: u32 = 1;
+/// Words that say nothing about what a request is for
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "all", "at", "by", "for", "from", "i", "in", "into", "it", "me", "my", "of",
+    "on", "please", "the", "this", "that", "to", "with",
+];
+
+/// The personal scripts, saved in `~/.you/cache`, followed by the shared ones.
+///
+/// A script hides the scripts of the same name that come after it, so personal
+/// scripts come first, then shared directories in the order they are configured.
 #[derive(Debug, Clone)]
 pub struct Cache {
     scripts: Vec<PathBuf>,
+    index: CacheIndex,
+    shared_directories: Vec<SharedDirectory>,
+}
+
+/// A read-only directory of scripts, with an optional `index.json` of their metadata.
+#[derive(Debug, Clone)]
+struct SharedDirectory {
+    path: PathBuf,
+    scripts: Vec<PathBuf>,
+    index: CacheIndex,
+}
+
+/// Where a script comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptSource {
+    /// The cache, where saved scripts go
+    Personal,
+    /// A shared directory from `script_search_paths`
+    Shared(PathBuf),
+}
+
+impl std::fmt::Display for ScriptSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Personal => f.write_str("personal"),
+            Self::Shared(directory) => write!(f, "{}", directory.display()),
+        }
+    }
+}
+
+/// What is known about the cached scripts, by script name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    scripts: BTreeMap<String, ScriptMetadata>,
+}
+
+/// What a cached script was generated for, where, and how it has been used since.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptMetadata {
+    /// The request in natural language the script was generated for
+    pub request: String,
+    /// What the script does, as the LLM explained it
+    pub description: String,
+    /// The operating system the script was generated on
+    #[serde(default)]
+    pub operating_system: String,
+    /// The shell the script was generated for
+    #[serde(default)]
+    pub shell: String,
+    /// RFC 3339 timestamp
+    #[serde(default)]
+    pub created_at: Option<String>,
+    /// RFC 3339 timestamp of the last time the script was run from the cache
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+    /// How many times the script was run from the cache
+    #[serde(default)]
+    pub run_count: u32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Values the script refers to as `{{name}}`, filled in when it runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<ScriptParameter>,
+    /// Embedding of `request`, if embeddings are used for lookups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+impl ScriptMetadata {
+    /// The metadata of a script generated for `request` just now, on this system.
+    pub fn new(request: &str, description: &str, tags: Vec<String>) -> Self {
+        Self {
+            request: request.to_string(),
+            description: description.to_string(),
+            operating_system: System::long_os_version().unwrap_or_default(),
+            shell: get_shell_name().to_string(),
+            created_at: Some(Local::now().to_rfc3339()),
+            last_used_at: None,
+            run_count: 0,
+            tags,
+            parameters: Vec::new(),
+            embedding: None,
+        }
+    }
+}
+
+/// A script in the cache or a shared directory, with its metadata.
+#[derive(Debug, Clone)]
+pub struct CachedScript {
+    pub name: String,
+    pub path: PathBuf,
+    pub source: ScriptSource,
+    pub metadata: ScriptMetadata,
+}
+
+/// Cached scripts with their metadata in one file, for sharing them with `you export` and `you import`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptBundle {
+    pub version: u32,
+    /// RFC 3339 timestamp
+    pub exported_at: String,
+    pub scripts: Vec<BundledScript>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledScript {
+    pub name: String,
+    pub content: String,
+    pub metadata: ScriptMetadata,
+}
+
+impl ScriptBundle {
+    pub fn read(path: &Path) -> Result<Self> {
+        let bundle: Self = serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|error| anyhow!("{} is not a script bundle: {}", path.display(), error))?;
+        if bundle.version > SCRIPT_BUNDLE_VERSION {
+            return Err(anyhow!(
+                "{} is a bundle of version {}, only versions up to {} are supported. Please update you",
+                path.display(),
+                bundle.version,
+                SCRIPT_BUNDLE_VERSION
+            ));
+        }
+
+        Ok(bundle)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// A cached script that is similar to a request.
+#[derive(Debug, Clone)]
+pub struct CacheCandidate {
+    pub name: String,
+    pub path: PathBuf,
+    pub metadata: ScriptMetadata,
+    /// From 0 to 1
+    pub similarity: f64,
 }
 
 impl GlobalResourceInitialization for Cache {
@@ -31,79 +194,432 @@ impl GlobalResourceInitialization for Cache {
     where
         Self: Sized,
     {
-        let mut scripts: Vec<PathBuf> = Vec::new();
-        for file in read_dir(acquire_you_home_directory()?.join(YOU_CACHE_DIRECTORY))? {
-            let file: DirEntry = file?;
-            if file.metadata().unwrap().is_file() {
-                let filename: String = file.file_name().to_string_lossy().to_string();
-                if filename.ends_with(".sh") {
-                    scripts.push(file.path());
-                }
-            }
-        }
+        let you_cache_directory: PathBuf = acquire_you_home_directory()?.join(YOU_CACHE_DIRECTORY);
 
-        Ok(Self { scripts })
+        Ok(Self {
+            scripts: list_script_files(&you_cache_directory)?,
+            index: read_index(&you_cache_directory)?,
+            shared_directories: Vec::new(),
+        })
     }
 }
 
 impl Cache {
     /// Refreshes the in-memory scripts list by re-reading from the cache directory
     pub fn refresh_scripts(&mut self) -> Result<()> {
-        self.scripts.clear();
-        for file in read_dir(acquire_you_home_directory()?.join(YOU_CACHE_DIRECTORY))? {
-            let file: DirEntry = file?;
-            if file.metadata().unwrap().is_file() {
-                let filename: String = file.file_name().to_string_lossy().to_string();
-                if filename.ends_with(".sh") {
-                    self.scripts.push(file.path());
-                }
-            }
+        self.scripts = list_script_files(&acquire_you_home_directory()?.join(YOU_CACHE_DIRECTORY))?;
+        Ok(())
+    }
+
+    /// Adds the scripts of `directory` after the ones already known. `~` stands for the
+    /// home directory.
+    pub fn add_shared_directory(&mut self, directory: &str) -> Result<()> {
+        let path: PathBuf = match directory.strip_prefix("~") {
+            Some(rest) => dirs::home_dir()
+                .ok_or_else(|| anyhow!("No home directory is found"))?
+                .join(rest.trim_start_matches(['/', '\\'])),
+            None => PathBuf::from(directory),
+        };
+        if !path.is_dir() {
+            return Err(anyhow!("{} is not a directory", path.display()));
         }
+
+        self.shared_directories.push(SharedDirectory {
+            scripts: list_script_files(&path)?,
+            index: read_index(&path)?,
+            path,
+        });
+
         Ok(())
     }
 
-    pub fn search(&self, query: &str) -> Option<&PathBuf> {
-        for script in self.scripts.iter() {
-            let script_name: &str = script.file_stem().unwrap().to_str().unwrap();
-            if query == script_name {
-                return Some(&script);
+    /// The scripts that are similar enough to `query`, the most similar first.
+    ///
+    /// Scripts are compared by the request they were saved for, their name and
+    /// their description. With `query_embedding`, scripts that have an embedding
+    /// are also compared by it.
+    pub fn search(
+        &self,
+        query: &str,
+        query_embedding: Option<&[f32]>,
+        policy: &CacheLookupPolicy,
+    ) -> Vec<CacheCandidate> {
+        let mut candidates: Vec<CacheCandidate> = Vec::new();
+
+        for script in self.list_scripts() {
+            let metadata: ScriptMetadata = script.metadata;
+
+            // Names are usually the request, with dashes or underscores for spaces
+            let mut similarity: f64 = similarity(query, &script.name.replace(['-', '_'], " "))
+                .max(similarity(query, &metadata.request))
+                .max(similarity(query, &metadata.description) * 0.8);
+            if similarity < policy.min_similarity {
+                similarity = 0.0;
+            }
+
+            if let (Some(query_embedding), Some(embedding)) = (query_embedding, &metadata.embedding)
+            {
+                let embedding_similarity: f64 = cosine_similarity(query_embedding, embedding);
+                if embedding_similarity >= policy.min_embedding_similarity {
+                    similarity = similarity.max(embedding_similarity);
+                }
+            }
+
+            if similarity > 0.0 {
+                candidates.push(CacheCandidate {
+                    name: script.name,
+                    path: script.path,
+                    metadata,
+                    similarity,
+                });
             }
         }
 
-        None
+        candidates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        candidates.truncate(policy.max_candidates);
+
+        candidates
+    }
+
+    /// What `script_name` was saved for.
+    pub fn get_metadata(&self, script_name: &str) -> ScriptMetadata {
+        match self.find_script(script_name) {
+            Ok(script) => script.metadata,
+            Err(_) => get_metadata_without_index(script_name, None),
+        }
+    }
+
+    /// Counts a run of `script_name` from the cache. Shared directories are
+    /// read-only, so runs of their scripts are not counted.
+    pub fn record_run(&mut self, script_name: &str) -> Result<()> {
+        if self.find_script(script_name)?.source != ScriptSource::Personal {
+            return Ok(());
+        }
+
+        let mut metadata: ScriptMetadata = self.get_metadata(script_name);
+        metadata.run_count += 1;
+        metadata.last_used_at = Some(Local::now().to_rfc3339());
+
+        self.index.scripts.insert(script_name.to_string(), metadata);
+        self.save_index()
     }
 
-    pub fn add_new_script(&mut self, script_name: &str, script_content: &str) -> Result<()> {
+    pub fn add_new_script(
+        &mut self,
+        script_name: &str,
+        script_content: &str,
+        metadata: ScriptMetadata,
+    ) -> Result<()> {
         let you_cache_directory: PathBuf = acquire_you_home_directory()?.join(YOU_CACHE_DIRECTORY);
 
         let mut file: File =
             std::fs::File::create_new(you_cache_directory.join(format!("{}.sh", script_name)))?;
-        file.write(script_content.as_bytes())?;
-        
+        file.write_all(script_content.as_bytes())?;
+        self.index.scripts.insert(script_name.to_string(), metadata);
+        self.save_index()?;
+
         // Update in-memory scripts after successful file creation
         self.refresh_scripts()?;
 
         Ok(())
     }
 
-    pub fn list_scripts(&self) -> Vec<String> {
-        self.scripts
-            .iter()
-            .map(|script| script.file_stem().unwrap().to_str().unwrap().to_string())
-            .collect()
+    /// The script that `script_name` stands for, the first one with that name.
+    pub fn find_script(&self, script_name: &str) -> Result<CachedScript> {
+        self.list_scripts()
+            .into_iter()
+            .find(|script| script.name == script_name)
+            .ok_or_else(|| anyhow!("Script '{}' not found", script_name))
+    }
+
+    /// Where `script_name` is stored.
+    pub fn get_script_path(&self, script_name: &str) -> Result<PathBuf> {
+        Ok(self.find_script(script_name)?.path)
+    }
+
+    /// Where `script_name` is stored, as long as it is a personal script that may be changed.
+    pub fn get_personal_script_path(&self, script_name: &str) -> Result<PathBuf> {
+        let script: CachedScript = self.find_script(script_name)?;
+        match script.source {
+            ScriptSource::Personal => Ok(script.path),
+            ScriptSource::Shared(directory) => Err(anyhow!(
+                "Script '{}' is shared from {}, which is read-only",
+                script_name,
+                directory.display()
+            )),
+        }
+    }
+
+    /// The content of `script_name`, with the placeholders of its parameters.
+    pub fn read_script(&self, script_name: &str) -> Result<String> {
+        Ok(std::fs::read_to_string(self.get_script_path(script_name)?)?)
+    }
+
+    /// Every script, without the ones hidden by a script of the same name that comes first.
+    pub fn list_scripts(&self) -> Vec<CachedScript> {
+        let sources = std::iter::once((ScriptSource::Personal, &self.scripts, &self.index)).chain(
+            self.shared_directories.iter().map(|directory| {
+                (
+                    ScriptSource::Shared(directory.path.clone()),
+                    &directory.scripts,
+                    &directory.index,
+                )
+            }),
+        );
+
+        let mut names: HashSet<String> = HashSet::new();
+        let mut scripts: Vec<CachedScript> = Vec::new();
+        for (source, paths, index) in sources {
+            for path in paths {
+                let name: String = path.file_stem().unwrap().to_string_lossy().to_string();
+                if !names.insert(name.clone()) {
+                    continue;
+                }
+
+                scripts.push(CachedScript {
+                    metadata: match index.scripts.get(&name) {
+                        Some(metadata) => metadata.clone(),
+                        None => get_metadata_without_index(&name, Some(path)),
+                    },
+                    name,
+                    path: path.clone(),
+                    source: source.clone(),
+                });
+            }
+        }
+
+        scripts
     }
 
     pub fn delete_script(&mut self, script_name: &str) -> Result<()> {
-        for script in self.scripts.iter() {
-            let current_script_name: &str = script.file_stem().unwrap().to_str().unwrap();
-            if current_script_name == script_name {
-                std::fs::remove_file(script)?;
-                // Update in-memory scripts after successful file deletion
-                self.refresh_scripts()?;
-                return Ok(());
+        std::fs::remove_file(self.get_personal_script_path(script_name)?)?;
+        if self.index.scripts.remove(script_name).is_some() {
+            self.save_index()?;
+        }
+        // Update in-memory scripts after successful file deletion
+        self.refresh_scripts()?;
+
+        Ok(())
+    }
+
+    /// Renames `old_name` to `new_name`, along with its metadata.
+    pub fn rename_script(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        validate_script_name(new_name)?;
+        let old_path: PathBuf = self.get_personal_script_path(old_name)?;
+        if self.get_script_path(new_name).is_ok() {
+            return Err(anyhow!("Script '{}' already exists", new_name));
+        }
+
+        std::fs::rename(
+            &old_path,
+            old_path.with_file_name(format!("{}.sh", new_name)),
+        )?;
+        if let Some(metadata) = self.index.scripts.remove(old_name) {
+            self.index.scripts.insert(new_name.to_string(), metadata);
+            self.save_index()?;
+        }
+        // Update in-memory scripts after successful file renaming
+        self.refresh_scripts()?;
+
+        Ok(())
+    }
+
+    /// Bundles `script_names`, or every script without any, for sharing them.
+    ///
+    /// How often and when the scripts were run is left out, as are their
+    /// embeddings, which only make sense with the model that computed them.
+    pub fn export(&self, script_names: &[String]) -> Result<ScriptBundle> {
+        let mut scripts: Vec<BundledScript> = Vec::new();
+        for script in self.list_scripts() {
+            if !script_names.is_empty() && !script_names.contains(&script.name) {
+                continue;
             }
+
+            let mut metadata: ScriptMetadata = script.metadata;
+            metadata.last_used_at = None;
+            metadata.run_count = 0;
+            metadata.embedding = None;
+            scripts.push(BundledScript {
+                content: self.read_script(&script.name)?,
+                name: script.name,
+                metadata,
+            });
+        }
+
+        if let Some(missing) = script_names
+            .iter()
+            .find(|name| !scripts.iter().any(|script| &script.name == *name))
+        {
+            return Err(anyhow!("Script '{}' not found", missing));
+        }
+
+        scripts.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(ScriptBundle {
+            version: SCRIPT_BUNDLE_VERSION,
+            exported_at: Local::now().to_rfc3339(),
+            scripts,
+        })
+    }
+
+    /// Adds a script from a bundle as `script_name`, replacing the script of that name if
+    /// `is_overwriting`.
+    pub fn import_script(
+        &mut self,
+        script_name: &str,
+        script: &BundledScript,
+        is_overwriting: bool,
+    ) -> Result<()> {
+        validate_script_name(script_name)?;
+        // A shared script of that name is hidden by the imported one instead
+        if is_overwriting && self.get_personal_script_path(script_name).is_ok() {
+            self.delete_script(script_name)?;
         }
 
-        Err(anyhow!("Script '{}' not found", script_name))
+        self.add_new_script(script_name, &script.content, script.metadata.clone())
     }
+
+    fn save_index(&self) -> Result<()> {
+        std::fs::write(
+            acquire_you_home_directory()?
+                .join(YOU_CACHE_DIRECTORY)
+                .join(CACHE_INDEX_JSON),
+            serde_json::to_string_pretty(&self.index)?,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// The `.sh` files of `directory`.
+fn list_script_files(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut scripts: Vec<PathBuf> = Vec::new();
+    for file in read_dir(directory)? {
+        let file: DirEntry = file?;
+        if file.metadata()?.is_file() && file.file_name().to_string_lossy().ends_with(".sh") {
+            scripts.push(file.path());
+        }
+    }
+    // Directory listings come in no particular order
+    scripts.sort();
+
+    Ok(scripts)
+}
+
+fn read_index(directory: &Path) -> Result<CacheIndex> {
+    let index_path: PathBuf = directory.join(CACHE_INDEX_JSON);
+    if !index_path.exists() {
+        return Ok(CacheIndex::default());
+    }
+
+    serde_json::from_str(&std::fs::read_to_string(&index_path)?)
+        .map_err(|error| anyhow!("{} cannot be read: {}", index_path.display(), error))
+}
+
+/// What can be told about a script that is not in an index, like the scripts saved
+/// before the index existed: its name, and the time its file at `path` was last modified.
+fn get_metadata_without_index(script_name: &str, path: Option<&PathBuf>) -> ScriptMetadata {
+    let modified_at: Option<String> = path
+        .and_then(|path| path.metadata().ok())
+        .and_then(|metadata| metadata.modified().ok())
+        .map(|modified_at| DateTime::<Local>::from(modified_at).to_rfc3339());
+
+    ScriptMetadata {
+        request: script_name.replace(['-', '_'], " "),
+        description: String::new(),
+        operating_system: String::new(),
+        shell: String::new(),
+        created_at: modified_at,
+        last_used_at: None,
+        run_count: 0,
+        tags: Vec::new(),
+        parameters: Vec::new(),
+        embedding: None,
+    }
+}
+
+/// Script names become file names, so they cannot point elsewhere.
+pub fn validate_script_name(script_name: &str) -> Result<()> {
+    if script_name.is_empty() || script_name.contains(['/', '\\']) || script_name.starts_with('.') {
+        return Err(anyhow!(
+            "'{}' is not a valid script name, it must not be empty, start with a dot nor contain slashes",
+            script_name
+        ));
+    }
+
+    Ok(())
+}
+
+/// From 0 to 1, how alike two texts are, by their words and by their letters.
+///
+/// Comparing letters too makes up for typos and words that differ slightly,
+/// e.g. `dir` and `directory`.
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a_words, b_words): (Vec<String>, Vec<String>) = (tokenize(a), tokenize(b));
+    if a_words.is_empty() || b_words.is_empty() {
+        return 0.0;
+    }
+
+    let word_similarity: f64 = dice_coefficient(
+        &a_words.iter().cloned().collect(),
+        &b_words.iter().cloned().collect(),
+    );
+    let letter_similarity: f64 =
+        dice_coefficient(&trigrams(&a_words.join(" ")), &trigrams(&b_words.join(" ")));
+
+    0.6 * word_similarity + 0.4 * letter_similarity
+}
+
+/// The meaningful words of `text`, lowercased and without plural `s`.
+fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|character: char| !character.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !STOP_WORDS.contains(word))
+        .map(|word| match word.strip_suffix('s') {
+            Some(singular) if singular.len() > 2 && !singular.ends_with('s') => {
+                singular.to_string()
+            }
+            _ => word.to_string(),
+        })
+        .collect()
+}
+
+fn trigrams(text: &str) -> HashSet<String> {
+    let characters: Vec<char> = format!("  {} ", text).chars().collect();
+    characters
+        .windows(3)
+        .map(|window| window.iter().collect())
+        .collect()
+}
+
+fn dice_coefficient(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+
+    2.0 * a.intersection(b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let dot_product: f64 = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| (*x as f64) * (*y as f64))
+        .sum();
+    let norm = |vector: &[f32]| {
+        vector
+            .iter()
+            .map(|x| (*x as f64).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+    let norms: f64 = norm(a) * norm(b);
+    if norms == 0.0 {
+        return 0.0;
+    }
+
+    dot_product / norms
 }
@@ -5,6 +5,8 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use crate::constants::CONFIGURATIONS_JSON;
+use crate::errors::YouError;
+use crate::risk::RiskCategory;
 use crate::traits::{GlobalResourceInitialization, acquire_you_home_directory};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -19,22 +21,287 @@ impl Display for PreferredCLI {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Configurations {
+/// The LLM API flavors that `you` can talk to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LLMProvider {
+    /// Any OpenAI-compatible `/v1/chat/completions` endpoint
+    #[default]
+    OpenAI,
+    /// Ollama's native `/api/chat` endpoint
+    Ollama,
+    /// Anthropic's `/v1/messages` endpoint
+    Anthropic,
+    /// Offline replay of canned replies from `script`, for testing
+    Scripted,
+}
+
+/// Selects and configures the LLM backend.
+///
+/// Every field except `provider` is optional. Missing values fall back to
+/// the provider's environment variables, e.g. `YOU_OPENAI_API_KEY`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LLMConfigurations {
     #[serde(default)]
-    pub enable_cache: bool,
-    preferred_clis: Vec<PreferredCLI>,
+    pub provider: LLMProvider,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_base: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Model that turns text into embeddings, for comparing requests with cached scripts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    /// Offer the agent's actions as native tools instead of describing them
+    /// in the prompt. Models without tool support fall back to the prompt.
+    #[serde(default)]
+    pub enable_tool_calling: bool,
+    /// Send the JSON schema of the expected reply along with the request, so
+    /// the endpoint constrains the model's output to it
+    #[serde(default)]
+    pub enable_structured_output: bool,
+    /// JSONL file of canned replies used by the `scripted` provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<PathBuf>,
+}
+
+/// How many times, and how patiently, a failed LLM generation is retried.
+///
+/// Transport errors are retried after an exponentially growing delay.
+/// Malformed replies are retried immediately with a repair message that
+/// tells the model what was wrong.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts per step, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry of a transport error
+    pub initial_backoff_milliseconds: u64,
+    /// Upper bound for the delay between retries
+    pub max_backoff_milliseconds: u64,
 }
 
-impl Default for Configurations {
+impl Default for RetryPolicy {
     fn default() -> Self {
         Self {
-            enable_cache: false,
-            preferred_clis: vec![],
+            max_attempts: 3,
+            initial_backoff_milliseconds: 500,
+            max_backoff_milliseconds: 8000,
         }
     }
 }
 
+/// How many times the agent may try to fix a failed command on its own.
+///
+/// After a command fails, its exit code, stdout and stderr are sent to the
+/// agent, which proposes a corrected command for the user to approve. Set
+/// `max_rounds` to 0 to stop at the first failure instead.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RepairPolicy {
+    /// Consecutive failures the agent is asked to fix
+    pub max_rounds: u32,
+}
+
+impl Default for RepairPolicy {
+    fn default() -> Self {
+        Self { max_rounds: 3 }
+    }
+}
+
+/// Which risky commands are never offered for execution.
+///
+/// Commands are classified by a local static analyzer. Proposals with a
+/// finding in one of `blocked_categories` are sent back to the agent for an
+/// alternative instead of being shown to the user, up to `max_blocked_rounds`
+/// times in a row.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RiskPolicy {
+    pub blocked_categories: Vec<RiskCategory>,
+    /// Consecutive blocked proposals the agent is asked to replace
+    pub max_blocked_rounds: u32,
+}
+
+impl Default for RiskPolicy {
+    fn default() -> Self {
+        Self {
+            blocked_categories: Vec::new(),
+            max_blocked_rounds: 3,
+        }
+    }
+}
+
+/// Where approved commands run.
+///
+/// With `enabled`, commands run in a sandbox against a copy of the working
+/// directory, with the rest of the file system read-only. Their changes are
+/// listed afterwards, and only applied to the working directory if the user
+/// commits them. Linux only, using bubblewrap or user namespaces.
+///
+/// The working directory is copied before every command, so working directories
+/// with more than `max_files` files or `max_megabytes` are refused.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SandboxPolicy {
+    pub enabled: bool,
+    /// Files in the largest working directory that is copied into the sandbox
+    pub max_files: u64,
+    /// Size of the largest working directory that is copied into the sandbox
+    pub max_megabytes: u64,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_files: 10000,
+            max_megabytes: 1024,
+        }
+    }
+}
+
+/// How commands are approved with `--yes`, when nobody is there to answer, e.g. in CI or cron.
+///
+/// A command runs without asking if the risk analyzer classifies it as read-only
+/// and `auto_approve_read_only` is set, or if it matches one of the
+/// `allowed_commands` regular expressions. Any other command stops `you`. The
+/// questions of the agent are answered with the `--context` given on the command
+/// line, or stop `you` too.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ApprovalPolicy {
+    pub auto_approve_read_only: bool,
+    /// e.g. `^kubectl get ` or `^make (build|test)$`
+    pub allowed_commands: Vec<String>,
+    /// Set by `--yes`, prompts are answered by this policy instead of the user
+    #[serde(skip)]
+    pub is_non_interactive: bool,
+    /// Set by `--context`, what the questions of the agent are answered with
+    #[serde(skip)]
+    pub context: Vec<String>,
+}
+
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        Self {
+            auto_approve_read_only: true,
+            allowed_commands: Vec::new(),
+            is_non_interactive: false,
+            context: Vec::new(),
+        }
+    }
+}
+
+/// What is kept for `you undo`.
+///
+/// Before a command runs, the files the risk analyzer expects it to modify or
+/// delete are copied into the journal, up to `max_snapshot_bytes` per command.
+/// Only the last `max_entries` commands are kept.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct JournalPolicy {
+    pub enabled: bool,
+    pub max_entries: usize,
+    pub max_snapshot_bytes: u64,
+}
+
+impl Default for JournalPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 20,
+            max_snapshot_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// How requests are matched with cached scripts.
+///
+/// Requests are compared with the request each script was saved for, by their
+/// words and letters. With `use_embeddings`, they are also compared by the
+/// embeddings of `llm.embedding_model`. Scripts that are similar enough are
+/// offered, the most similar first, and only run once the user picked one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CacheLookupPolicy {
+    /// From 0 to 1, how similar the words of a request must be
+    pub min_similarity: f64,
+    /// From 0 to 1, how similar the embeddings of a request must be
+    pub min_embedding_similarity: f64,
+    pub max_candidates: usize,
+    pub use_embeddings: bool,
+}
+
+impl Default for CacheLookupPolicy {
+    fn default() -> Self {
+        Self {
+            min_similarity: 0.6,
+            min_embedding_similarity: 0.85,
+            max_candidates: 3,
+            use_embeddings: false,
+        }
+    }
+}
+
+/// What is kept for `you history`.
+///
+/// Each session records the prompt, what the LLM proposed, how the user
+/// answered and the commands that ran. Only the last `max_sessions` sessions
+/// are kept.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HistoryPolicy {
+    pub enabled: bool,
+    pub max_sessions: usize,
+}
+
+impl Default for HistoryPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_sessions: 500,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Configurations {
+    #[serde(default)]
+    pub enable_cache: bool,
+    #[serde(default)]
+    pub cache_lookup: CacheLookupPolicy,
+    /// Read-only directories of shared scripts, e.g. a checked out repository of team
+    /// scripts. They come after the cache, in order of precedence. `~` is the home directory
+    #[serde(default)]
+    pub script_search_paths: Vec<String>,
+    #[serde(default)]
+    pub llm: LLMConfigurations,
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    #[serde(default)]
+    pub repair_policy: RepairPolicy,
+    #[serde(default)]
+    pub risk_policy: RiskPolicy,
+    #[serde(default)]
+    pub sandbox: SandboxPolicy,
+    #[serde(default)]
+    pub approval_policy: ApprovalPolicy,
+    #[serde(default)]
+    pub journal: JournalPolicy,
+    #[serde(default)]
+    pub history: HistoryPolicy,
+    /// Render explanations while the LLM is still generating them
+    #[serde(default)]
+    pub enable_streaming: bool,
+    /// Shell that commands run in, e.g. `bash` or `/bin/zsh`. The login shell in `$SHELL`
+    /// is used without it, or `sh`. Commands always run in `cmd` on Windows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+    preferred_clis: Vec<PreferredCLI>,
+}
+
 impl Display for Configurations {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&serde_json::to_string_pretty(&self).unwrap())
@@ -42,6 +309,16 @@ impl Display for Configurations {
 }
 
 impl Configurations {
+    /// The configured interpreter, or the login shell in `$SHELL`, or `sh`.
+    pub fn get_interpreter(&self) -> String {
+        self.interpreter
+            .clone()
+            .or_else(|| std::env::var("SHELL").ok())
+            .map(|interpreter| interpreter.trim().to_string())
+            .filter(|interpreter| !interpreter.is_empty())
+            .unwrap_or_else(|| "sh".to_string())
+    }
+
     pub fn get_preferred_clis(&self) -> String {
         let mut prompt: String = String::new();
         for preferred_cli in self.preferred_clis.iter() {
@@ -63,7 +340,7 @@ impl GlobalResourceInitialization for Configurations {
         if !configurations_directory.exists() {
             let mut new_configuration: File = std::fs::File::create_new(&configurations_directory)?;
             new_configuration
-                .write(serde_json::to_string_pretty(&Configurations::default())?.as_bytes())?;
+                .write_all(serde_json::to_string_pretty(&Configurations::default())?.as_bytes())?;
         }
 
         Ok(())
@@ -77,6 +354,11 @@ impl GlobalResourceInitialization for Configurations {
         let configuration_string: String =
             std::fs::read_to_string(acquire_you_home_directory()?.join(CONFIGURATIONS_JSON))?;
 
-        Ok(serde_json::from_str(&configuration_string)?)
+        serde_json::from_str(&configuration_string).map_err(|error| {
+            YouError::InvalidConfiguration {
+                reason: format!("{} cannot be parsed: {}", CONFIGURATIONS_JSON, error),
+            }
+            .into()
+        })
     }
 }
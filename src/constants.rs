@@ -1,3 +1,12 @@
 pub const YOU_HOME_DIRECTORY: &str = ".you"; // The home directory of you
 pub const YOU_CACHE_DIRECTORY: &str = "cache"; // A subdirectory under you's home
+pub const YOU_JOURNAL_DIRECTORY: &str = "journal"; // Snapshots taken before commands run, for `you undo`
+pub const YOU_HISTORY_DIRECTORY: &str = "history"; // Past sessions, for `you history`
 pub const CONFIGURATIONS_JSON: &str = "configurations.json";
+
+// Exit codes of `you` itself. A failed command exits with its own code instead
+pub const EXIT_CODE_FAILURE: u8 = 1; // Any other error
+pub const EXIT_CODE_USER_DECLINED: u8 = 120;
+pub const EXIT_CODE_LLM_UNREACHABLE: u8 = 121;
+pub const EXIT_CODE_INVALID_CONFIGURATION: u8 = 122;
+pub const EXIT_CODE_RETRIES_EXHAUSTED: u8 = 123;
@@ -0,0 +1,100 @@
+use serde::Serialize;
+
+use crate::{
+    agents::command_line_explain_agent::TokenExplained,
+    risk::{RiskAssessment, RiskFinding, RiskLevel},
+};
+
+/// What `you run --dry-run` found out about a request, without running anything.
+#[derive(Debug, Serialize)]
+pub struct DryRunReport {
+    /// The request in natural language
+    pub request: String,
+    /// The commands the agent settled on, in the order they would run
+    pub commands: Vec<DryRunCommand>,
+    /// The level of the riskiest command
+    pub risk_level: RiskLevel,
+}
+
+impl DryRunReport {
+    pub fn new(request: String, commands: Vec<DryRunCommand>) -> Self {
+        let risk_level: RiskLevel = commands
+            .iter()
+            .map(|command| command.risk_level)
+            .max()
+            .unwrap_or_default();
+
+        Self {
+            request,
+            commands,
+            risk_level,
+        }
+    }
+}
+
+/// One command that would have run.
+#[derive(Debug, Serialize)]
+pub struct DryRunCommand {
+    pub command: String,
+    /// The agent's own explanation of the command
+    pub explanation: String,
+    /// What each token does, as explained by `CommandLineExplainAgent`
+    pub breakdown: Vec<TokenExplained>,
+    /// Whether the agent marked the command as destructive
+    pub is_destructive: bool,
+    pub risk_level: RiskLevel,
+    pub risk_findings: Vec<RiskFinding>,
+}
+
+impl DryRunCommand {
+    pub fn new(
+        command: String,
+        explanation: String,
+        breakdown: Vec<TokenExplained>,
+        is_destructive: bool,
+        risk: RiskAssessment,
+    ) -> Self {
+        Self {
+            command,
+            explanation,
+            breakdown,
+            is_destructive,
+            risk_level: risk.get_level(),
+            risk_findings: risk.findings,
+        }
+    }
+
+    /// The human-readable form, numbered with `position`.
+    pub fn format(&self, position: usize) -> String {
+        let mut formatted: String = format!(
+            "{}. > {}\n        * {}\n",
+            position, self.command, self.explanation
+        );
+
+        for token in &self.breakdown {
+            formatted.push_str(&format!(
+                "          {}: {}\n",
+                token.token, token.explanation
+            ));
+        }
+
+        if self.is_destructive {
+            formatted.push_str("        ! destructive\n");
+        }
+
+        if self.risk_level != RiskLevel::Low {
+            let reasons: Vec<&str> = self
+                .risk_findings
+                .iter()
+                .map(|finding| finding.reason.as_str())
+                .collect();
+            formatted.push_str(&format!(
+                "        ! {} risk: {}\n",
+                self.risk_level,
+                reasons.join("; ")
+            ));
+        }
+
+        formatted
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::Display;
+
+use crate::constants::{
+    EXIT_CODE_FAILURE, EXIT_CODE_INVALID_CONFIGURATION, EXIT_CODE_LLM_UNREACHABLE,
+    EXIT_CODE_RETRIES_EXHAUSTED, EXIT_CODE_USER_DECLINED,
+};
+
+/// Failures that callers need to tell apart, rather than just print.
+///
+/// Everything else is reported through `anyhow` as usual. These are wrapped
+/// into `anyhow::Error` too, and can be recovered with `downcast_ref`.
+#[derive(Debug)]
+pub enum YouError {
+    /// The LLM replied, but no JSON object could be found in the reply
+    NoJSONInResponse { response: String },
+    /// The LLM called a tool that does not exist, or with unusable arguments
+    InvalidToolCall { name: String, reason: String },
+    /// The LLM replied with JSON that does not follow the expected schema
+    SchemaViolation { violations: Vec<String> },
+    /// The LLM kept failing after the configured number of attempts
+    RetriesExhausted { attempts: u32, last_error: String },
+    /// A request to the LLM did not go through, e.g. the endpoint cannot be connected to
+    /// or is overloaded. Unlike other failures of a backend, it is worth retrying
+    LLMRequestFailed { message: String },
+    /// The LLM could not be reached within the configured number of attempts
+    LLMUnreachable { attempts: u32, last_error: String },
+    /// Commands were meant to run in a sandbox, but none can be set up here
+    SandboxUnavailable { reason: String },
+    /// `configurations.json` cannot be read, or lacks a required setting
+    InvalidConfiguration { reason: String },
+    /// The user did not approve running the command
+    UserDeclined,
+    /// With `--yes`, the approval policy does not allow running `command`
+    NotApprovedByPolicy { command: String },
+    /// The LLM kept proposing commands in `blocked_categories`, `rounds` times in a row
+    ProposalsBlocked { rounds: u32 },
+    /// A command that did not come from the LLM, e.g. a re-run or cached one, is in
+    /// `blocked_categories` for `reasons`
+    CommandBlocked { reasons: Vec<String> },
+    /// With `--yes`, there is nobody to answer `question`
+    InputRequired { question: String },
+    /// An executed command failed. `exit_code` is `None` if it was killed by a signal
+    CommandFailed {
+        message: String,
+        exit_code: Option<i32>,
+    },
+}
+
+impl YouError {
+    /// The code `you` exits with when it fails with this error.
+    ///
+    /// A failed command passes its own exit code on, the other errors have
+    /// reserved codes so that scripts can tell them apart.
+    pub fn get_exit_code(&self) -> u8 {
+        match self {
+            Self::RetriesExhausted { .. } => EXIT_CODE_RETRIES_EXHAUSTED,
+            Self::LLMUnreachable { .. } => EXIT_CODE_LLM_UNREACHABLE,
+            Self::InvalidConfiguration { .. } => EXIT_CODE_INVALID_CONFIGURATION,
+            Self::UserDeclined
+            | Self::NotApprovedByPolicy { .. }
+            | Self::ProposalsBlocked { .. }
+            | Self::CommandBlocked { .. } => EXIT_CODE_USER_DECLINED,
+            Self::CommandFailed {
+                exit_code: Some(exit_code),
+                ..
+            } => u8::try_from(*exit_code)
+                .ok()
+                .filter(|exit_code| *exit_code != 0)
+                .unwrap_or(EXIT_CODE_FAILURE),
+            _ => EXIT_CODE_FAILURE,
+        }
+    }
+}
+
+/// The code `you` exits with when it fails with `error`.
+pub fn get_exit_code(error: &anyhow::Error) -> u8 {
+    match error.downcast_ref::<YouError>() {
+        Some(error) => error.get_exit_code(),
+        None => EXIT_CODE_FAILURE,
+    }
+}
+
+impl Display for YouError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoJSONInResponse { response } => {
+                write!(f, "No JSON is found in the LLM's response: {}", response)
+            }
+            Self::InvalidToolCall { name, reason } => {
+                write!(f, "The LLM made an invalid call to `{}`: {}", name, reason)
+            }
+            Self::SchemaViolation { violations } => write!(
+                f,
+                "The LLM's response does not follow the schema: {}",
+                violations.join("; ")
+            ),
+            Self::RetriesExhausted {
+                attempts,
+                last_error,
+            } => write!(
+                f,
+                "Gave up after {} attempts. The last error was: {}",
+                attempts, last_error
+            ),
+            Self::LLMUnreachable {
+                attempts,
+                last_error,
+            } => write!(
+                f,
+                "Cannot reach the LLM after {} attempts. The last error was: {}",
+                attempts, last_error
+            ),
+            Self::LLMRequestFailed { message } => f.write_str(message),
+            Self::SandboxUnavailable { reason } => {
+                write!(f, "Cannot run the command in a sandbox: {}", reason)
+            }
+            Self::InvalidConfiguration { reason } => {
+                write!(f, "Invalid configurations: {}", reason)
+            }
+            Self::UserDeclined => f.write_str("Declined by the user"),
+            Self::NotApprovedByPolicy { command } => write!(
+                f,
+                "`{}` is neither read-only nor allowed by `approval_policy.allowed_commands`",
+                command
+            ),
+            Self::ProposalsBlocked { rounds } => write!(
+                f,
+                "The LLM proposed commands that are blocked by your configurations {} times in a row",
+                rounds
+            ),
+            Self::CommandBlocked { reasons } => write!(
+                f,
+                "The command is blocked by your configurations: {}",
+                reasons.join("; ")
+            ),
+            Self::InputRequired { question } => write!(
+                f,
+                "The LLM needs an answer, but --yes leaves nobody to ask: {}",
+                question
+            ),
+            Self::CommandFailed { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for YouError {}
@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use anyhow::{Error, Result};
+
+use crate::agents::execution::ExecutionResult;
+
+use super::{Executor, run_streamed, shell_command};
+
+/// Runs commands directly in the working directory, with the user's privileges.
+pub struct HostExecutor {
+    working_directory: PathBuf,
+}
+
+impl HostExecutor {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            working_directory: std::env::current_dir()?,
+        })
+    }
+}
+
+impl Executor for HostExecutor {
+    fn execute(&self, command: &str) -> Result<ExecutionResult, Error> {
+        let mut process = shell_command(command);
+        process.current_dir(&self.working_directory);
+
+        run_streamed(process, command)
+    }
+}
@@ -0,0 +1,184 @@
+pub mod host;
+pub mod sandbox;
+
+use std::{io::Read, path::Path, process::Command, sync::OnceLock, time::Instant};
+
+use anyhow::{Error, Result, anyhow};
+use cchain::display_control::{Level, display_command_line};
+
+use crate::agents::execution::ExecutionResult;
+use crate::output::{OutputEvent, display_message, emit, is_json_output};
+
+/// Runs shell commands on behalf of the user.
+pub trait Executor {
+    /// Runs `command`, showing its output while capturing it.
+    fn execute(&self, command: &str) -> Result<ExecutionResult, Error>;
+}
+
+/// Set once from the configurations, before anything runs.
+static INTERPRETER: OnceLock<String> = OnceLock::new();
+
+pub fn set_interpreter(interpreter: String) {
+    let _ = INTERPRETER.set(interpreter);
+}
+
+/// Name of the shell that commands run in: the interpreter of the configurations,
+/// `sh` until they are loaded, and always `cmd` on Windows.
+pub fn get_shell_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        return "cmd";
+    }
+
+    INTERPRETER.get().map(String::as_str).unwrap_or("sh")
+}
+
+/// The first line of saved scripts, e.g. `#!/usr/bin/env bash` for `/bin/bash`.
+pub fn get_shebang() -> String {
+    let shell_name: &str = get_shell_name();
+    let program: &str = Path::new(shell_name)
+        .file_name()
+        .and_then(|program| program.to_str())
+        .unwrap_or(shell_name);
+
+    format!("#!/usr/bin/env {}\n", program)
+}
+
+/// The interpreter named by the shebang of `script`, e.g. `bash` for `#!/usr/bin/env bash`
+/// or `/bin/zsh` for `#!/bin/zsh`. Scripts without one are run by `sh`, as a shell would.
+pub fn get_script_interpreter(script: &str) -> String {
+    let Some(shebang) = script
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("#!"))
+    else {
+        return "sh".to_string();
+    };
+
+    let mut words = shebang.split_whitespace();
+    let interpreter: Option<&str> = match words.next() {
+        // `env` looks the interpreter up in $PATH, after its own options such as `-S`
+        Some(program) if Path::new(program).file_name() == Some("env".as_ref()) => {
+            words.find(|word| !word.starts_with('-'))
+        }
+        program => program,
+    };
+
+    interpreter.unwrap_or("sh").to_string()
+}
+
+/// The platform's shell, set up to run `command`. It is not interactive, so rc files
+/// and the aliases they define are not loaded.
+fn shell_command(command: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut cmd = Command::new(get_shell_name());
+        cmd.args(["/C", command]);
+        return cmd;
+    }
+
+    let mut sh = Command::new(get_shell_name());
+    sh.args(["-c", command]);
+    sh
+}
+
+/// Spawns `process`, streams its output to the terminal and collects it.
+///
+/// `command_in_text` is what the user approved, which is shown instead of the
+/// possibly wrapped `process`.
+fn run_streamed(mut process: Command, command_in_text: &str) -> Result<ExecutionResult, Error> {
+    // Set stdout and stderr to piped so that we can capture them
+    process.stdout(std::process::Stdio::piped());
+    process.stderr(std::process::Stdio::piped());
+
+    let command_string: &console::StyledObject<&str> = &console::style(command_in_text).bold();
+    display_message(
+        Level::Logging,
+        &format!("Start executing command: {}", command_string),
+    );
+
+    // Spawn the process
+    let started_at: Instant = Instant::now();
+    let mut child: std::process::Child = process
+        .spawn()
+        .map_err(|e| anyhow!("Failed to execute command: {}", e))?;
+
+    // Take the stdout and stderr handles
+    let stdout: std::process::ChildStdout = child.stdout.take().unwrap();
+    let stderr: std::process::ChildStderr = child.stderr.take().unwrap();
+
+    // Each piece of output is tagged with whether it came from stderr
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    // Spawn a thread to read stdout
+    let tx_clone: std::sync::mpsc::Sender<(bool, String)> = tx.clone();
+    std::thread::spawn(move || {
+        let mut reader: std::io::BufReader<std::process::ChildStdout> =
+            std::io::BufReader::new(stdout);
+        let mut buffer: [u8; 1024] = [0; 1024];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break, // EOF
+                Ok(n) => {
+                    let text = String::from_utf8_lossy(&buffer[..n]).to_string();
+                    tx_clone.send((false, text)).unwrap();
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    // Spawn a thread to read stderr
+    std::thread::spawn(move || {
+        let mut reader: std::io::BufReader<std::process::ChildStderr> =
+            std::io::BufReader::new(stderr);
+        let mut buffer: [u8; 1024] = [0; 1024];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break, // EOF
+                Ok(n) => {
+                    let text = String::from_utf8_lossy(&buffer[..n]).to_string();
+                    tx.send((true, text)).unwrap();
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    let mut result: ExecutionResult = ExecutionResult::default();
+    let terminal: console::Term = console::Term::stdout();
+    for (is_stderr, received) in rx {
+        match is_json_output() {
+            true => emit(&OutputEvent::Output {
+                stream: if is_stderr { "stderr" } else { "stdout" },
+                text: &received,
+            }),
+            false => display_command_line(&terminal, &received),
+        }
+        if is_stderr {
+            result.stderr.push_str(&received);
+        } else {
+            result.stdout.push_str(&received);
+        }
+    }
+
+    // Wait for process completion
+    let status: std::process::ExitStatus = child
+        .wait()
+        .map_err(|e| anyhow!("Failed to wait on child process: {}", e))?;
+    result.exit_code = status.code();
+    result.duration = started_at.elapsed();
+
+    if !result.is_success() {
+        display_message(
+            Level::Error,
+            &format!("Command {} {}", command_string, result.get_status()),
+        );
+        return Ok(result);
+    }
+
+    display_message(
+        Level::Logging,
+        &format!("Finished executing command: {}", command_string),
+    );
+
+    Ok(result)
+}
@@ -0,0 +1,460 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs::{self, Metadata, Permissions},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Error, Result};
+
+use crate::{agents::execution::ExecutionResult, configurations::SandboxPolicy, errors::YouError};
+
+use super::{Executor, get_shell_name, run_streamed};
+
+/// Sets up the mount namespace created by `unshare`, then runs the command.
+///
+/// The copy of the working directory (`$1`) is mounted over it (`$2`), the
+/// scratch directory (`$3`) is mounted onto itself, and every other mount is
+/// made read-only, before `$4` runs in the copy with the interpreter (`$5`).
+/// If a mount cannot be made read-only, the command does not run, and the
+/// mount point is written to `$6` instead.
+const UNSHARE_SETUP_SCRIPT: &str = r#"set -e
+mount --bind "$1" "$2"
+mount --bind "$3" "$3"
+mounts=$(cat /proc/self/mounts)
+while read -r _ mount_point _ options _; do
+    mount_point=$(printf '%b' "$mount_point")
+    case "$mount_point" in
+        "$2" | "$3") continue ;;
+    esac
+    # The flags locked by the user namespace have to be kept for the remount to be allowed
+    flags=ro
+    for flag in nosuid nodev noexec; do
+        case ",$options," in
+            *",$flag,"*) flags="$flags,$flag" ;;
+        esac
+    done
+    if ! mount -o "remount,bind,$flags" "$mount_point"; then
+        printf '%s' "$mount_point" > "$6"
+        exit 1
+    fi
+done <<MOUNTS
+$mounts
+MOUNTS
+cd "$2"
+exec "$5" -c "$4"
+"#;
+
+/// How the sandbox is set up.
+enum SandboxTool {
+    /// `bwrap`, from bubblewrap
+    Bubblewrap,
+    /// `unshare`, from util-linux, with unprivileged user namespaces
+    Unshare,
+}
+
+/// Runs commands in a sandbox, against a copy of the working directory.
+///
+/// The rest of the file system is read-only inside the sandbox, and a scratch
+/// directory stands in for `TMPDIR`. Nothing the command does reaches the real
+/// working directory until `commit` applies the changes listed by
+/// `get_changes`. Dropping the executor discards them. Linux only.
+pub struct SandboxExecutor {
+    tool: SandboxTool,
+    working_directory: PathBuf,
+    /// Holds the `workspace`, which is the copy of the working directory, and `tmp`
+    root: PathBuf,
+}
+
+impl SandboxExecutor {
+    /// Copies the working directory into a new sandbox, unless it is larger than `policy`
+    /// allows.
+    pub fn new(policy: &SandboxPolicy) -> Result<Self, Error> {
+        if !cfg!(target_os = "linux") {
+            return Err(YouError::SandboxUnavailable {
+                reason: "sandboxed execution is only supported on Linux".to_string(),
+            }
+            .into());
+        }
+
+        let tool: SandboxTool = detect_tool()?;
+        let working_directory: PathBuf = std::env::current_dir()?;
+        ensure_within_limits(&working_directory, policy)?;
+        let nanoseconds: u128 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let root: PathBuf = std::env::temp_dir().join(format!(
+            "you-sandbox-{}-{}",
+            std::process::id(),
+            nanoseconds
+        ));
+
+        let sandbox: SandboxExecutor = Self {
+            tool,
+            working_directory,
+            root,
+        };
+        fs::create_dir_all(sandbox.get_scratch_directory())?;
+        copy_tree(&sandbox.working_directory, &sandbox.get_workspace())?;
+
+        Ok(sandbox)
+    }
+
+    fn get_workspace(&self) -> PathBuf {
+        self.root.join("workspace")
+    }
+
+    fn get_scratch_directory(&self) -> PathBuf {
+        self.root.join("tmp")
+    }
+
+    /// Where `UNSHARE_SETUP_SCRIPT` leaves the mount point it failed to make read-only,
+    /// in the scratch directory as it stays writable.
+    fn get_setup_failure_path(&self) -> PathBuf {
+        self.get_scratch_directory()
+            .join(".you-sandbox-setup-failure")
+    }
+
+    /// What the commands run so far changed, compared to the real working directory.
+    pub fn get_changes(&self) -> Result<Vec<FileChange>, Error> {
+        let original: BTreeMap<PathBuf, Entry> = collect_entries(&self.working_directory)?;
+        let changed: BTreeMap<PathBuf, Entry> = collect_entries(&self.get_workspace())?;
+
+        let mut changes: Vec<FileChange> = Vec::new();
+        for (path, entry) in &changed {
+            match original.get(path) {
+                None => changes.push(FileChange::Created(path.clone())),
+                Some(original_entry) => {
+                    if !is_same_entry(
+                        &self.working_directory.join(path),
+                        original_entry,
+                        &self.get_workspace().join(path),
+                        entry,
+                    )? {
+                        changes.push(FileChange::Modified(path.clone()));
+                    }
+                }
+            }
+        }
+
+        for path in original.keys() {
+            if !changed.contains_key(path) {
+                changes.push(FileChange::Deleted(path.clone()));
+            }
+        }
+
+        changes.sort_by(|a, b| a.get_path().cmp(b.get_path()));
+        Ok(changes)
+    }
+
+    /// Applies `changes` to the real working directory.
+    pub fn commit(&self, changes: &[FileChange]) -> Result<(), Error> {
+        // Deepest first, so that directories are emptied before they are removed
+        for change in changes.iter().rev() {
+            if let FileChange::Deleted(path) = change {
+                remove_entry(&self.working_directory.join(path))?;
+            }
+        }
+
+        // Parents first, so that directories exist before their contents are copied
+        for change in changes {
+            if let FileChange::Created(path) | FileChange::Modified(path) = change {
+                copy_entry(
+                    &self.get_workspace().join(path),
+                    &self.working_directory.join(path),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_process(&self, command: &str) -> Command {
+        let mut process: Command = match self.tool {
+            SandboxTool::Bubblewrap => {
+                let mut bwrap = Command::new("bwrap");
+                bwrap
+                    .args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"])
+                    .arg("--bind")
+                    .args([self.get_scratch_directory(), self.get_scratch_directory()])
+                    .arg("--bind")
+                    .args([self.get_workspace(), self.working_directory.clone()])
+                    .arg("--chdir")
+                    .arg(&self.working_directory)
+                    .args(["--unshare-pid", "--die-with-parent", "--"])
+                    .args([get_shell_name(), "-c", command]);
+                bwrap
+            }
+            SandboxTool::Unshare => {
+                let mut unshare = Command::new("unshare");
+                unshare
+                    .args(["--user", "--map-root-user", "--mount", "sh", "-c"])
+                    .args([UNSHARE_SETUP_SCRIPT, "sh"])
+                    .args([
+                        self.get_workspace(),
+                        self.working_directory.clone(),
+                        self.get_scratch_directory(),
+                    ])
+                    .args([command, get_shell_name()])
+                    .arg(self.get_setup_failure_path());
+                unshare
+            }
+        };
+        process.env("TMPDIR", self.get_scratch_directory());
+
+        process
+    }
+}
+
+impl Executor for SandboxExecutor {
+    fn execute(&self, command: &str) -> Result<ExecutionResult, Error> {
+        let setup_failure_path: PathBuf = self.get_setup_failure_path();
+        let _ = fs::remove_file(&setup_failure_path);
+
+        let result: ExecutionResult = run_streamed(self.build_process(command), command)?;
+
+        // Fail closed, rather than report a command as sandboxed that ran with a writable host
+        if let Ok(mount_point) = fs::read_to_string(&setup_failure_path) {
+            return Err(YouError::SandboxUnavailable {
+                reason: format!("{} cannot be made read-only", mount_point),
+            }
+            .into());
+        }
+
+        Ok(result)
+    }
+}
+
+impl Drop for SandboxExecutor {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Picks the first tool that can actually create a sandbox on this system.
+fn detect_tool() -> Result<SandboxTool, Error> {
+    let probes: [(SandboxTool, &str, &[&str]); 2] = [
+        (
+            SandboxTool::Bubblewrap,
+            "bwrap",
+            &["--ro-bind", "/", "/", "true"],
+        ),
+        (
+            SandboxTool::Unshare,
+            "unshare",
+            &["--user", "--map-root-user", "--mount", "true"],
+        ),
+    ];
+
+    for (tool, program, arguments) in probes {
+        let is_usable: bool = Command::new(program)
+            .args(arguments)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+
+        if is_usable {
+            return Ok(tool);
+        }
+    }
+
+    Err(YouError::SandboxUnavailable {
+        reason: "neither bubblewrap nor unprivileged user namespaces are available".to_string(),
+    }
+    .into())
+}
+
+/// A change made in the sandbox, relative to the working directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Deleted(PathBuf),
+}
+
+impl FileChange {
+    pub fn get_path(&self) -> &Path {
+        match self {
+            Self::Created(path) | Self::Modified(path) | Self::Deleted(path) => path,
+        }
+    }
+}
+
+impl Display for FileChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Created(path) => write!(f, "+ {}", path.display()),
+            Self::Modified(path) => write!(f, "~ {}", path.display()),
+            Self::Deleted(path) => write!(f, "- {}", path.display()),
+        }
+    }
+}
+
+/// What is compared between the working directory and its copy. Sockets,
+/// pipes and devices are left out of both.
+#[derive(Debug, PartialEq)]
+enum Entry {
+    Directory(Permissions),
+    File(Permissions),
+    Symlink(PathBuf),
+}
+
+/// Every entry below `root`, keyed by its path relative to `root`.
+fn collect_entries(root: &Path) -> Result<BTreeMap<PathBuf, Entry>, Error> {
+    let mut entries: BTreeMap<PathBuf, Entry> = BTreeMap::new();
+    let mut pending: Vec<PathBuf> = vec![root.to_path_buf()];
+
+    while let Some(directory) = pending.pop() {
+        for child in fs::read_dir(&directory)? {
+            let path: PathBuf = child?.path();
+            let metadata: Metadata = fs::symlink_metadata(&path)?;
+            let relative_path: PathBuf = path.strip_prefix(root)?.to_path_buf();
+
+            if metadata.is_symlink() {
+                entries.insert(relative_path, Entry::Symlink(fs::read_link(&path)?));
+            } else if metadata.is_dir() {
+                entries.insert(relative_path, Entry::Directory(metadata.permissions()));
+                pending.push(path);
+            } else if metadata.is_file() {
+                entries.insert(relative_path, Entry::File(metadata.permissions()));
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+fn is_same_entry(
+    original_path: &Path,
+    original: &Entry,
+    changed_path: &Path,
+    changed: &Entry,
+) -> Result<bool, Error> {
+    if original != changed {
+        return Ok(false);
+    }
+
+    if let Entry::File(_) = original {
+        return Ok(fs::read(original_path)? == fs::read(changed_path)?);
+    }
+
+    Ok(true)
+}
+
+/// Fails with `YouError::SandboxUnavailable` once the files below `root` exceed the
+/// limits of `policy`, before anything is copied.
+fn ensure_within_limits(root: &Path, policy: &SandboxPolicy) -> Result<(), Error> {
+    let max_bytes: u64 = policy.max_megabytes.saturating_mul(1024 * 1024);
+    let mut files: u64 = 0;
+    let mut bytes: u64 = 0;
+    let mut pending: Vec<PathBuf> = vec![root.to_path_buf()];
+
+    while let Some(directory) = pending.pop() {
+        for child in fs::read_dir(&directory)? {
+            let path: PathBuf = child?.path();
+            let metadata: Metadata = fs::symlink_metadata(&path)?;
+            if metadata.is_dir() {
+                pending.push(path);
+                continue;
+            }
+
+            files += 1;
+            bytes += metadata.len();
+            if files > policy.max_files || bytes > max_bytes {
+                return Err(YouError::SandboxUnavailable {
+                    reason: format!(
+                        "{} has more than {} files or {} MB to copy. Run `you` from a smaller directory, or raise `sandbox.max_files` and `sandbox.max_megabytes`",
+                        root.display(),
+                        policy.max_files,
+                        policy.max_megabytes
+                    ),
+                }
+                .into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies `source` to `destination` recursively, keeping symlinks and permissions.
+fn copy_tree(source: &Path, destination: &Path) -> Result<(), Error> {
+    fs::create_dir_all(destination)?;
+
+    for child in fs::read_dir(source)? {
+        let child_path: PathBuf = child?.path();
+        let metadata: Metadata = fs::symlink_metadata(&child_path)?;
+        let child_destination: PathBuf = destination.join(child_path.strip_prefix(source)?);
+
+        if metadata.is_dir() {
+            copy_tree(&child_path, &child_destination)?;
+        } else if metadata.is_symlink() || metadata.is_file() {
+            copy_entry(&child_path, &child_destination)?;
+        }
+    }
+
+    // Only now, in case the directory is read-only
+    fs::set_permissions(destination, fs::metadata(source)?.permissions())?;
+
+    Ok(())
+}
+
+/// Replaces `destination` with a copy of `source`, without descending into directories.
+fn copy_entry(source: &Path, destination: &Path) -> Result<(), Error> {
+    let metadata: Metadata = fs::symlink_metadata(source)?;
+    let existing: Option<Metadata> = fs::symlink_metadata(destination).ok();
+
+    if metadata.is_dir() {
+        if existing.as_ref().is_some_and(|existing| !existing.is_dir()) {
+            remove_entry(destination)?;
+        }
+        fs::create_dir_all(destination)?;
+        fs::set_permissions(destination, metadata.permissions())?;
+        return Ok(());
+    }
+
+    // Never write through a symlink, nor into a directory of the same name
+    if existing.is_some_and(|existing| existing.is_dir() || existing.is_symlink()) {
+        remove_entry(destination)?;
+    }
+
+    if metadata.is_symlink() {
+        if fs::symlink_metadata(destination).is_ok() {
+            fs::remove_file(destination)?;
+        }
+        return create_symlink(&fs::read_link(source)?, destination);
+    }
+
+    fs::copy(source, destination)?;
+    Ok(())
+}
+
+fn remove_entry(path: &Path) -> Result<(), Error> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path)?,
+        Ok(_) => fs::remove_file(path)?,
+        // Already gone
+        Err(_) => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> Result<(), Error> {
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_symlink(_target: &Path, link: &Path) -> Result<(), Error> {
+    Err(anyhow::anyhow!(
+        "Cannot create the symlink {} on this platform",
+        link.display()
+    ))
+}
@@ -1,51 +1,202 @@
-use anyhow::{Error, Result};
-use cchain::{
-    commons::utility::input_message,
-    display_control::{Level, display_message},
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Output},
 };
+
+use anyhow::{Error, Result, anyhow};
+use cchain::display_control::Level;
 use indicatif::ProgressBar;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     agents::{
-        command_json::{ActionTypeExecute, LLMActionType},
-        command_line_explain_agent::CommandLineExplainAgent,
+        command_json::{
+            ActionTypePlan, LLMActionType, PlanReport, PlanStep, PlanStepOutcome,
+            assess_in_current_directory,
+        },
+        command_line_explain_agent::{CommandExplained, CommandLineExplainAgent},
+        execution::ExecutionResult,
         semi_autonomous_command_line_agent::SemiAutonomousCommandLineAgent,
         traits::{AgentExecution, Step},
     },
-    cache::Cache,
-    configurations::Configurations,
+    approval::{answer_from_context, approve_by_policy, log_answer},
+    arguments::SortScriptsBy,
+    cache::{
+        Cache, CacheCandidate, CachedScript, ScriptBundle, ScriptMetadata, ScriptSource,
+        validate_script_name,
+    },
+    configurations::{ApprovalPolicy, Configurations},
+    dry_run::{DryRunCommand, DryRunReport},
+    errors::YouError,
+    executors::{
+        Executor, get_script_interpreter, get_shebang,
+        host::HostExecutor,
+        sandbox::{FileChange, SandboxExecutor},
+    },
+    history::{History, Session, SessionEventKind, SessionRecorder},
     information::ContextualInformation,
-    llm::Context,
-    styles::start_spinner,
+    journal::{Journal, JournalEntry},
+    llm::{Context, LLMBackend, create_llm_backend},
+    output::{
+        OutputEvent, ScriptEntry, display_message, emit, input_message, is_json_output,
+        is_stdout_reserved,
+    },
+    parameters::{
+        ScriptParameter, describe_parameters, parse_assignments, quote, render, render_defaults,
+    },
+    risk::{RiskAssessment, RiskFinding, RiskLevel},
+    styles::{ExplanationStreamRenderer, highlight_shell, start_spinner},
+    traits::GlobalResourceInitialization,
 };
 
 /// Prepares and displays a command prompt to the user, asking for confirmation or additional input
 ///
 /// # Arguments
 /// * `command_json` - The command JSON containing the command and its explanation
+/// * `configurations` - With `--yes`, the approval policy answers instead of the user
+/// * `previous_input` - What the agent was last told
 ///
 /// # Returns
 /// * `Result<String>` - The user's input response
-pub fn prompt_user_for_command_execution(command_json: &LLMActionType) -> Result<String> {
+pub fn prompt_user_for_command_execution(
+    command_json: &LLMActionType,
+    configurations: &Configurations,
+    previous_input: &str,
+) -> Result<String> {
     // Get the display prompt from the LLMActionType
     let prompt: String = command_json.fetch_display_prompt();
 
+    if configurations.approval_policy.is_non_interactive {
+        display_message(Level::Logging, &prompt);
+        return answer_by_policy(
+            command_json,
+            &configurations.approval_policy,
+            previous_input,
+        );
+    }
+
+    // Plans are confirmed step by step instead
+    let is_high_risk: bool = matches!(command_json, LLMActionType::Execute(_))
+        && command_json.assess_risk().get_level() == RiskLevel::High;
+
     // Prompt the user for input
-    input_message(&prompt)
+    read_approval(&prompt, is_high_risk)
+}
+
+/// Answers the proposal of the agent with the approval policy, for `--yes`.
+///
+/// Commands and plans are approved if every command is, questions are answered
+/// with the `--context` given on the command line.
+fn answer_by_policy(
+    command_json: &LLMActionType,
+    policy: &ApprovalPolicy,
+    previous_input: &str,
+) -> Result<String> {
+    match command_json {
+        LLMActionType::Execute(_) | LLMActionType::Plan(_) => {
+            let mut reasons: Vec<String> = Vec::new();
+            for command in command_json.get_commands() {
+                match approve_by_policy(policy, &command) {
+                    Ok(reason) => reasons.push(reason),
+                    Err(error) => {
+                        log_answer(
+                            "approve_command",
+                            "n",
+                            &[("command", &command), ("reason", "not approved by policy")],
+                        );
+                        return Err(error);
+                    }
+                }
+            }
+
+            log_answer("approve_command", "y", &[("reason", &reasons.join("; "))]);
+            Ok("y".to_string())
+        }
+        LLMActionType::RequestInformation(_) => {
+            let question: String = command_json.fetch_display_prompt();
+            let answer: String = answer_from_context(policy, &question, previous_input)?;
+            log_answer("answer_question", &answer, &[("reason", "--context")]);
+
+            Ok(answer)
+        }
+        LLMActionType::RequestCLIsToInstall(_) => Err(YouError::InputRequired {
+            question: command_json.fetch_display_prompt(),
+        }
+        .into()),
+    }
+}
+
+/// Reads the user's answer to `prompt`.
+///
+/// High-risk commands are only approved with the full word `yes`, which is
+/// then reported as `y`, so that a reflexive `y` cannot run them.
+fn read_approval(prompt: &str, is_high_risk: bool) -> Result<String> {
+    loop {
+        let user_input: String = input_message(prompt)?;
+        if !is_high_risk {
+            return Ok(user_input);
+        }
+
+        match user_input.trim() {
+            "yes" => return Ok("y".to_string()),
+            "y" => display_message(
+                Level::Warn,
+                "This command is high risk. Type the full word `yes` to execute it.",
+            ),
+            _ => return Ok(user_input),
+        }
+    }
+}
+
+/// Ask the agent for its next step behind a spinner.
+///
+/// With streaming enabled, the explanation is rendered while the LLM is still
+/// generating it. The returned flag tells whether that happened.
+fn fetch_next_step<T>(
+    agent: &mut impl Step<T>,
+    configurations: &Configurations,
+    user_query: &str,
+) -> Result<(T, bool), Error>
+where
+    for<'de> T: Serialize + Deserialize<'de> + JsonSchema,
+{
+    let spinner: ProgressBar = start_spinner("LLM is thinking...".to_string());
+
+    // JSON lines report the whole action once it is generated instead, and the
+    // stdout of `you suggest` is only for the command
+    if !configurations.enable_streaming || is_json_output() || is_stdout_reserved() {
+        let next_step: Result<T, Error> = agent.next_step(user_query);
+        // Clear the spinner
+        spinner.finish_and_clear();
+        return Ok((next_step?, false));
+    }
+
+    let mut renderer = ExplanationStreamRenderer::new(spinner);
+    let next_step: Result<T, Error> = agent.next_step_streamed(user_query, &mut renderer);
+    renderer.finish();
+
+    Ok((next_step?, renderer.has_rendered()))
 }
 
 fn process_command_interaction(
-    agent: &mut (impl Context + Step<LLMActionType>),
+    agent: &mut impl Step<LLMActionType>,
+    configurations: &Configurations,
+    session: &mut SessionRecorder,
     user_prompt: &mut String,
 ) -> Result<LLMActionType, Error> {
-    // Use the user query provided in the `run` argument for the first round
-    let spinner: ProgressBar = start_spinner("LLM is thinking...".to_string());
-    let command_json: LLMActionType = agent.next_step(&user_prompt)?;
-    // Clear the spinner
-    spinner.finish_and_clear();
+    let command_json: LLMActionType =
+        fetch_allowed_step(agent, configurations, session, user_prompt)?;
 
     // Update the user prompt based on command type
-    *user_prompt = prompt_user_for_command_execution(&command_json)?;
+    *user_prompt = prompt_user_for_command_execution(&command_json, configurations, user_prompt)?;
+    record_in_history(
+        session,
+        SessionEventKind::Decision {
+            input: user_prompt.clone(),
+        },
+    );
 
     // we add the `LLMActionType` to the agent's memory
     agent.add(
@@ -56,6 +207,540 @@ fn process_command_interaction(
     Ok(command_json)
 }
 
+/// Ask the agent for its next step until it proposes one that no blocked risk category forbids.
+/// Fails with `YouError::CommandBlocked` if `risk` has findings in `blocked_categories`.
+///
+/// For commands that do not come from the agent, e.g. re-run or cached ones, so there
+/// is nobody to ask for another way.
+fn ensure_not_blocked(configurations: &Configurations, risk: &RiskAssessment) -> Result<(), Error> {
+    let reasons: Vec<String> = risk
+        .get_findings_in(&configurations.risk_policy.blocked_categories)
+        .iter()
+        .map(|finding| format!("{} ({})", finding.reason, finding.category))
+        .collect();
+    if reasons.is_empty() {
+        return Ok(());
+    }
+
+    Err(YouError::CommandBlocked { reasons }.into())
+}
+
+fn fetch_allowed_step(
+    agent: &mut impl Step<LLMActionType>,
+    configurations: &Configurations,
+    session: &mut SessionRecorder,
+    user_prompt: &mut String,
+) -> Result<LLMActionType, Error> {
+    let mut blocked_rounds: u32 = 0;
+
+    loop {
+        // Use the user query provided in the `run` argument for the first round
+        let (command_json, _): (LLMActionType, bool) =
+            fetch_next_step(agent, configurations, user_prompt)?;
+        record_in_history(
+            session,
+            SessionEventKind::Proposal {
+                action: command_json.clone(),
+            },
+        );
+
+        let risk: RiskAssessment = command_json.assess_risk();
+        let blocked_findings: Vec<&RiskFinding> =
+            risk.get_findings_in(&configurations.risk_policy.blocked_categories);
+
+        if blocked_findings.is_empty() {
+            return Ok(command_json);
+        }
+
+        // Blocked proposals never reach the user, the agent has to find another way
+        let reasons: Vec<String> = blocked_findings
+            .iter()
+            .map(|finding| format!("{} ({})", finding.reason, finding.category))
+            .collect();
+        record_in_history(
+            session,
+            SessionEventKind::Blocked {
+                reasons: reasons.clone(),
+            },
+        );
+        display_message(
+            Level::Error,
+            &format!(
+                "The proposed command is blocked by your configurations: {}",
+                reasons.join("; ")
+            ),
+        );
+        agent.add(
+            async_openai::types::Role::Assistant,
+            format!("{:#?}", command_json),
+        )?;
+
+        blocked_rounds += 1;
+        if blocked_rounds > configurations.risk_policy.max_blocked_rounds {
+            return Err(YouError::ProposalsBlocked {
+                rounds: blocked_rounds,
+            }
+            .into());
+        }
+
+        *user_prompt = format!(
+            "Your proposal was blocked and will not be run, because it {}. Propose a different way to do it that avoids these operations.",
+            reasons.join("; ")
+        );
+    }
+}
+
+/// Walks the user through a plan, one step at a time.
+///
+/// Each step can be run, skipped, edited before it runs, or the whole plan
+/// aborted. A failing step stops the plan, so that the LLM can react to it.
+fn process_plan_step_by_step(
+    plan: &ActionTypePlan,
+    configurations: &Configurations,
+    session: &mut SessionRecorder,
+) -> Result<PlanReport, Error> {
+    let mut report: PlanReport = PlanReport::default();
+    let total_steps: usize = plan.steps.len();
+
+    for (index, step) in plan.steps.iter().enumerate() {
+        let mut step: PlanStep = step.clone();
+
+        if report.is_aborted || report.has_failures() {
+            report.steps.push((step, PlanStepOutcome::NotRun));
+            continue;
+        }
+
+        loop {
+            if step.is_destructive {
+                display_message(
+                    Level::Warn,
+                    &format!(
+                        "Step {} is destructive, please review it carefully.",
+                        index + 1
+                    ),
+                );
+            }
+
+            let risk: RiskAssessment = assess_in_current_directory(&step.command);
+            let is_high_risk: bool = step.is_destructive || risk.get_level() == RiskLevel::High;
+            if risk.get_level() != RiskLevel::Low {
+                display_message(Level::Warn, &risk.get_summary());
+            }
+
+            let prompt: String = format!(
+                "Step {}/{}: ({} to run, s to skip, e to edit, a to abort the plan)\n    > {}\n        * {}\n",
+                index + 1,
+                total_steps,
+                if is_high_risk { "yes" } else { "y" },
+                step.command,
+                step.explanation
+            );
+            let user_input: String = match configurations.approval_policy.is_non_interactive {
+                true => {
+                    display_message(Level::Logging, &prompt);
+                    let reason: String =
+                        approve_by_policy(&configurations.approval_policy, &step.command)?;
+                    log_answer("approve_step", "y", &[("reason", &reason)]);
+                    "y".to_string()
+                }
+                false => read_approval(&prompt, is_high_risk)?,
+            };
+            record_in_history(
+                session,
+                SessionEventKind::Decision {
+                    input: user_input.clone(),
+                },
+            );
+
+            match user_input.trim() {
+                "y" => {
+                    let outcome: PlanStepOutcome =
+                        match execute_command(configurations, session, &step.command) {
+                            Ok(ExecutedCommand::Finished(result)) if result.is_success() => {
+                                PlanStepOutcome::Succeeded(result)
+                            }
+                            Ok(ExecutedCommand::Finished(result)) => {
+                                PlanStepOutcome::Failed(result.to_feedback(&step.command))
+                            }
+                            Ok(ExecutedCommand::Discarded(result)) => {
+                                PlanStepOutcome::Discarded(result)
+                            }
+                            Err(error) => {
+                                display_message(Level::Error, &error.to_string());
+                                PlanStepOutcome::Failed(format!(
+                                    "The command `{}` could not be run: {}\n",
+                                    step.command, error
+                                ))
+                            }
+                        };
+                    report.steps.push((step, outcome));
+                    break;
+                }
+                "s" => {
+                    report.steps.push((step, PlanStepOutcome::Skipped));
+                    break;
+                }
+                "e" => {
+                    let edited_command: String = input_message("New command:")?;
+                    record_in_history(
+                        session,
+                        SessionEventKind::Decision {
+                            input: edited_command.clone(),
+                        },
+                    );
+                    if !edited_command.trim().is_empty() {
+                        step.command = edited_command.trim().to_string();
+                    }
+                }
+                // An empty input means stdin is closed, there is nobody left to ask
+                _ if user_input.is_empty() || user_input.trim() == "a" => {
+                    report.is_aborted = true;
+                    report.steps.push((step, PlanStepOutcome::NotRun));
+                    break;
+                }
+                _ => display_message(Level::Warn, "Please answer with y, s, e or a."),
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// What became of the agent's proposal once the user approved it.
+enum ExecutionOutcome {
+    /// Everything ran. `script` holds the commands that ran, for saving them, with
+    /// `{{name}}` placeholders for its `parameters`
+    Succeeded {
+        script: String,
+        parameters: Vec<ScriptParameter>,
+        feedback: String,
+    },
+    /// A command could not be run or exited with a non-zero code. `exit_code` is the
+    /// code of the failed command, if it is known
+    Failed {
+        feedback: String,
+        exit_code: Option<i32>,
+    },
+    /// The user aborted a plan, or discarded the changes of a sandboxed command
+    Aborted { feedback: String },
+    /// Nothing had to run, e.g. the user installed the CLIs the agent asked for, and the
+    /// agent can go on with `feedback`
+    Continued { feedback: String },
+}
+
+/// Whether `program` is an executable file in one of the directories of `PATH`.
+fn is_on_path(program: &str) -> bool {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+
+    std::env::split_paths(&paths).any(|directory| {
+        directory.join(program).is_file() || directory.join(format!("{}.exe", program)).is_file()
+    })
+}
+
+/// Runs an approved proposal. `feedback` describes what happened, for the agent.
+fn execute_proposal(
+    command_json: &mut LLMActionType,
+    configurations: &Configurations,
+    session: &mut SessionRecorder,
+) -> Result<ExecutionOutcome, Error> {
+    match command_json {
+        LLMActionType::Plan(plan) => {
+            let report: PlanReport = process_plan_step_by_step(plan, configurations, session)?;
+            let feedback: String = report.to_feedback();
+
+            if report.is_aborted {
+                display_message(Level::Logging, "The plan has been aborted.");
+                return Ok(ExecutionOutcome::Aborted { feedback });
+            }
+
+            if report.has_failures() {
+                return Ok(ExecutionOutcome::Failed {
+                    feedback,
+                    exit_code: None,
+                });
+            }
+
+            Ok(ExecutionOutcome::Succeeded {
+                script: report.get_succeeded_commands().join("\n"),
+                parameters: Vec::new(),
+                feedback,
+            })
+        }
+        LLMActionType::Execute(execute_action) => {
+            let command: String = execute_action.get_commands();
+
+            match execute_command(configurations, session, &command) {
+                Ok(ExecutedCommand::Finished(result)) if result.is_success() => {
+                    Ok(ExecutionOutcome::Succeeded {
+                        feedback: result.to_feedback(&command),
+                        script: execute_action.get_template().to_string(),
+                        parameters: execute_action.get_parameters().to_vec(),
+                    })
+                }
+                Ok(ExecutedCommand::Finished(result)) => Ok(ExecutionOutcome::Failed {
+                    feedback: result.to_feedback(&command),
+                    exit_code: result.exit_code,
+                }),
+                Ok(ExecutedCommand::Discarded(result)) => Ok(ExecutionOutcome::Aborted {
+                    feedback: result.to_feedback(&command)
+                        + "It ran in a sandbox and the user discarded its changes.\n",
+                }),
+                Err(error) => {
+                    display_message(Level::Error, &error.to_string());
+                    Ok(ExecutionOutcome::Failed {
+                        feedback: format!(
+                            "The command `{}` could not be run: {}\n",
+                            command, error
+                        ),
+                        exit_code: None,
+                    })
+                }
+            }
+        }
+        LLMActionType::RequestCLIsToInstall(request_clis) => {
+            let mut feedback: String = String::new();
+            for cli in request_clis.get_clis() {
+                match is_on_path(&cli.cli_name) {
+                    true => feedback.push_str(&format!("`{}` is installed now.\n", cli.cli_name)),
+                    false => feedback.push_str(&format!(
+                        "`{}` is still not found in PATH.\n",
+                        cli.cli_name
+                    )),
+                }
+            }
+            feedback.push_str("Please go on with the request.");
+
+            Ok(ExecutionOutcome::Continued { feedback })
+        }
+        other => {
+            let error: Error = match other.execute() {
+                Ok(_) => anyhow!("Nothing was executed"),
+                Err(error) => error,
+            };
+            display_message(Level::Error, &error.to_string());
+
+            Ok(ExecutionOutcome::Failed {
+                feedback: format!("{}\n", error),
+                exit_code: None,
+            })
+        }
+    }
+}
+
+/// What became of a command once it was run.
+pub enum ExecutedCommand {
+    /// The command ran, and its changes, if any, are in the working directory
+    Finished(ExecutionResult),
+    /// The command ran in the sandbox, and the user discarded its changes
+    Discarded(ExecutionResult),
+}
+
+/// Runs `command` directly, or in the sandbox when it is enabled.
+///
+/// Sandboxed commands run against a copy of the working directory. Their
+/// changes are listed afterwards, and the user chooses to commit them to the
+/// working directory or to discard them.
+pub fn execute_command(
+    configurations: &Configurations,
+    session: &mut SessionRecorder,
+    command: &str,
+) -> Result<ExecutedCommand, Error> {
+    if !configurations.sandbox.enabled {
+        let affected_paths: Vec<PathBuf> = assess_in_current_directory(command).affected_paths;
+        let journal_entry: Option<(Journal, JournalEntry)> =
+            record_in_journal(configurations, command, &affected_paths);
+
+        let result: ExecutionResult = HostExecutor::new()?.execute(command)?;
+        finish_in_journal(journal_entry, &result);
+        record_in_history(
+            session,
+            SessionEventKind::execution(command, &result, false),
+        );
+
+        return Ok(ExecutedCommand::Finished(result));
+    }
+
+    let sandbox: SandboxExecutor = SandboxExecutor::new(&configurations.sandbox)?;
+    display_message(
+        Level::Logging,
+        "Running in a sandbox. Your files stay untouched until you commit the changes.",
+    );
+    let result: ExecutionResult = sandbox.execute(command)?;
+
+    let changes: Vec<FileChange> = sandbox.get_changes()?;
+    if changes.is_empty() {
+        display_message(Level::Logging, "The command did not change any files.");
+        record_in_history(
+            session,
+            SessionEventKind::execution(command, &result, false),
+        );
+        return Ok(ExecutedCommand::Finished(result));
+    }
+
+    let listed_changes: Vec<String> = changes
+        .iter()
+        .map(|change| format!("    {}", change))
+        .collect();
+    display_message(
+        Level::Logging,
+        &format!(
+            "The command changed {} file(s):\n{}",
+            changes.len(),
+            listed_changes.join("\n")
+        ),
+    );
+
+    loop {
+        // With `--yes`, the command is approved already, and so are its changes
+        let user_input: String = match configurations.approval_policy.is_non_interactive {
+            true => {
+                log_answer("apply_sandbox_changes", "c", &[("reason", "--yes")]);
+                "c".to_string()
+            }
+            false => input_message(
+                "Apply these changes to the working directory? (c to commit, d to discard)",
+            )?,
+        };
+
+        match user_input.trim() {
+            "c" => {
+                // The sandbox knows exactly what changes, no need to rely on the analyzer
+                let working_directory: PathBuf = std::env::current_dir()?;
+                let changed_paths: Vec<PathBuf> = changes
+                    .iter()
+                    .map(|change| working_directory.join(change.get_path()))
+                    .collect();
+                let journal_entry: Option<(Journal, JournalEntry)> =
+                    record_in_journal(configurations, command, &changed_paths);
+
+                sandbox.commit(&changes)?;
+                finish_in_journal(journal_entry, &result);
+                record_in_history(
+                    session,
+                    SessionEventKind::execution(command, &result, false),
+                );
+                display_message(Level::Logging, "The changes have been committed.");
+                return Ok(ExecutedCommand::Finished(result));
+            }
+            // An empty input means stdin is closed, nothing is applied without consent
+            "d" | "" => {
+                display_message(Level::Logging, "The changes have been discarded.");
+                record_in_history(session, SessionEventKind::execution(command, &result, true));
+                return Ok(ExecutedCommand::Discarded(result));
+            }
+            _ => display_message(Level::Warn, "Please answer with c or d."),
+        }
+    }
+}
+
+/// Snapshots `paths` before `command` changes them, if the journal is enabled.
+///
+/// A command that cannot be journaled still runs, the user is only warned that
+/// it cannot be undone.
+fn record_in_journal(
+    configurations: &Configurations,
+    command: &str,
+    paths: &[PathBuf],
+) -> Option<(Journal, JournalEntry)> {
+    if !configurations.journal.enabled {
+        return None;
+    }
+
+    let recorded: Result<(Journal, JournalEntry), Error> = Journal::load().and_then(|journal| {
+        let entry: JournalEntry = journal.record(command, paths, &configurations.journal)?;
+        Ok((journal, entry))
+    });
+
+    match recorded {
+        Ok(recorded) => Some(recorded),
+        Err(error) => {
+            display_message(
+                Level::Warn,
+                &format!(
+                    "Failed to record the command in the journal, it cannot be undone: {}",
+                    error
+                ),
+            );
+            None
+        }
+    }
+}
+
+fn finish_in_journal(journal_entry: Option<(Journal, JournalEntry)>, result: &ExecutionResult) {
+    if let Some((journal, mut entry)) = journal_entry
+        && let Err(error) = journal.finish(&mut entry, result)
+    {
+        display_message(
+            Level::Warn,
+            &format!("Failed to update the journal: {}", error),
+        );
+    }
+}
+
+/// Starts recording a session for `prompt`, if the history is enabled.
+pub fn start_session(configurations: &Configurations, prompt: &str) -> SessionRecorder {
+    if !configurations.history.enabled || configurations.history.max_sessions == 0 {
+        return SessionRecorder::disabled();
+    }
+
+    match History::load() {
+        Ok(history) => {
+            SessionRecorder::new(Some((history, configurations.history.max_sessions)), prompt)
+        }
+        Err(error) => {
+            display_message(
+                Level::Warn,
+                &format!("Failed to open the history: {}", error),
+            );
+            SessionRecorder::disabled()
+        }
+    }
+}
+
+/// Records `event` in the session. The first failure is reported, and ends the recording.
+///
+/// With `--output json`, the event is reported as well.
+fn record_in_history(session: &mut SessionRecorder, event: SessionEventKind) {
+    if is_json_output() {
+        emit(&OutputEvent::Session(&event));
+    }
+
+    if let Err(error) = session.record(event) {
+        display_message(
+            Level::Warn,
+            &format!("Failed to record the session in the history: {}", error),
+        );
+        session.stop();
+    }
+}
+
+/// Asks the agent to fix a failed command, as long as the repair policy allows it.
+///
+/// Returns the message for the agent, or `None` once `repair_rounds` reached the cap.
+fn request_repair(
+    configurations: &Configurations,
+    repair_rounds: &mut u32,
+    feedback: &str,
+) -> Option<String> {
+    let max_rounds: u32 = configurations.repair_policy.max_rounds;
+    if *repair_rounds >= max_rounds {
+        return None;
+    }
+
+    *repair_rounds += 1;
+    display_message(
+        Level::Warn,
+        &format!(
+            "Asking the LLM to fix the command ({}/{})...",
+            repair_rounds, max_rounds
+        ),
+    );
+
+    Some(format!("{}\nPlease propose a corrected command.", feedback))
+}
+
 pub fn process_run_with_one_single_instruction(
     cache: &mut Cache,
     configurations: &Configurations,
@@ -63,19 +748,38 @@ pub fn process_run_with_one_single_instruction(
     command_in_natural_language: &str,
 ) -> Result<(), Error> {
     let mut agent: SemiAutonomousCommandLineAgent =
-        SemiAutonomousCommandLineAgent::new(contextual_information_object)?;
+        SemiAutonomousCommandLineAgent::new(contextual_information_object, configurations)?;
     let mut user_prompt: String = String::from(command_in_natural_language);
+    let mut repair_rounds: u32 = 0;
+    let mut session: SessionRecorder = start_session(configurations, command_in_natural_language);
 
     loop {
         // Process the command interaction
-        let mut command_json: LLMActionType =
-            process_command_interaction(&mut agent, &mut user_prompt)?;
+        let mut command_json: LLMActionType = process_command_interaction(
+            &mut agent,
+            configurations,
+            &mut session,
+            &mut user_prompt,
+        )?;
+
+        // `n`, or an empty answer once stdin is closed, ends the session instead of hinting the LLM
+        if matches!(user_prompt.trim(), "n" | "") {
+            return Err(YouError::UserDeclined.into());
+        }
 
         if user_prompt.trim() == "y" {
-            match command_json.execute() {
-                Ok(_) => {
+            match execute_proposal(&mut command_json, configurations, &mut session)? {
+                ExecutionOutcome::Succeeded {
+                    script, parameters, ..
+                } => {
                     display_message(Level::Logging, "Commands had been executed successfully.");
 
+                    // Nobody names the script with `--yes`, so it is not saved
+                    if configurations.approval_policy.is_non_interactive {
+                        log_answer("save_script", "n", &[("reason", "--yes")]);
+                        break;
+                    }
+
                     // Prompt the user for saving the command
                     let save_shell_input: String = input_message(
                         "Would you like to save the command to a shell script? (n for no, type anything to name the chain)",
@@ -84,27 +788,41 @@ pub fn process_run_with_one_single_instruction(
                         break;
                     }
 
-                    // Extract command if it's an Execute action type
-                    if let LLMActionType::Execute(ref mut execute_action) = command_json {
-                        if configurations.enable_cache {
-                            save_to_shell_in_cache(
-                                cache,
-                                save_shell_input.trim(),
-                                execute_action,
-                            )?;
-                            break;
-                        }
-
-                        save_to_shell(save_shell_input.trim(), execute_action)?;
+                    if configurations.enable_cache {
+                        save_to_shell_in_cache(
+                            cache,
+                            configurations,
+                            save_shell_input.trim(),
+                            &script,
+                            parameters,
+                            command_in_natural_language,
+                            &command_json.get_explanation(),
+                        )?;
+                        break;
                     }
 
+                    save_to_shell(save_shell_input.trim(), &script, &parameters)?;
+
                     break;
                 }
-                Err(error) => {
-                    let error_string: String = error.to_string();
-                    display_message(Level::Error, &error_string);
-                    user_prompt.push_str(&error_string);
-                }
+                ExecutionOutcome::Aborted { .. } => return Err(YouError::UserDeclined.into()),
+                ExecutionOutcome::Continued { feedback } => user_prompt = feedback,
+                ExecutionOutcome::Failed {
+                    feedback,
+                    exit_code,
+                } => match request_repair(configurations, &mut repair_rounds, &feedback) {
+                    Some(repair_prompt) => user_prompt = repair_prompt,
+                    None => {
+                        return Err(YouError::CommandFailed {
+                            message: format!(
+                                "The command still fails after {} repair round(s)",
+                                repair_rounds
+                            ),
+                            exit_code,
+                        }
+                        .into());
+                    }
+                },
             };
         }
     }
@@ -112,31 +830,180 @@ pub fn process_run_with_one_single_instruction(
     Ok(())
 }
 
+/// Goes through the whole conversation for `command_in_natural_language` without executing anything.
+///
+/// Questions the agent asks are still answered by the user. Once it proposes commands, each
+/// of them is broken down by `CommandLineExplainAgent` and assessed for risk, then shown along
+/// with a JSON summary on the last line, for reviewing commands before they ever run.
+pub fn process_dry_run_with_one_single_instruction(
+    configurations: &Configurations,
+    contextual_information_object: &ContextualInformation,
+    command_in_natural_language: &str,
+) -> Result<(), Error> {
+    let mut agent: SemiAutonomousCommandLineAgent =
+        SemiAutonomousCommandLineAgent::new(contextual_information_object, configurations)?;
+    let mut user_prompt: String = String::from(command_in_natural_language);
+    let mut session: SessionRecorder = start_session(configurations, command_in_natural_language);
+
+    let proposed_steps: Vec<PlanStep> = loop {
+        let command_json: LLMActionType =
+            fetch_allowed_step(&mut agent, configurations, &mut session, &mut user_prompt)?;
+
+        match command_json {
+            LLMActionType::Execute(execute_action) => {
+                break vec![PlanStep {
+                    command: execute_action.get_commands(),
+                    explanation: execute_action.get_explanation().to_string(),
+                    is_destructive: false,
+                }];
+            }
+            LLMActionType::Plan(plan) => break plan.steps,
+            other => {
+                user_prompt =
+                    prompt_user_for_command_execution(&other, configurations, &user_prompt)?;
+                record_in_history(
+                    &mut session,
+                    SessionEventKind::Decision {
+                        input: user_prompt.clone(),
+                    },
+                );
+                agent.add(
+                    async_openai::types::Role::Assistant,
+                    format!("{:#?}", other),
+                )?;
+            }
+        }
+    };
+
+    let mut commands: Vec<DryRunCommand> = Vec::new();
+    for step in proposed_steps {
+        // A fresh agent for each command, so that earlier commands do not leak into the breakdown
+        let mut explain_agent: CommandLineExplainAgent =
+            CommandLineExplainAgent::new(contextual_information_object, configurations)?;
+        let spinner: ProgressBar = start_spinner(format!("Explaining `{}`...", step.command));
+        let command_explained: Result<CommandExplained, Error> =
+            explain_agent.next_step(&step.command);
+        spinner.finish_and_clear();
+
+        let risk: RiskAssessment = assess_in_current_directory(&step.command);
+        commands.push(DryRunCommand::new(
+            step.command,
+            step.explanation,
+            command_explained?.get_breakdown().to_vec(),
+            step.is_destructive,
+            risk,
+        ));
+    }
+
+    let report: DryRunReport = DryRunReport::new(command_in_natural_language.to_string(), commands);
+
+    display_message(Level::Logging, "Dry run, nothing has been executed:");
+    for (index, command) in report.commands.iter().enumerate() {
+        display_message(Level::Logging, &command.format(index + 1));
+    }
+    match is_json_output() {
+        true => emit(&OutputEvent::DryRun { report: &report }),
+        false => println!("{}", serde_json::to_string(&report)?),
+    }
+
+    Ok(())
+}
+
+/// Asks the agent for a command for `request`, and prints it on stdout without running it.
+///
+/// This is what the shell function of `you init` calls. Questions the agent asks are still
+/// answered by the user, on stderr like every other message, so that stdout only ever
+/// holds the command. The steps of a plan are chained with `&&`, as the plan would stop
+/// at the first failing step.
+pub fn process_suggestion_with_one_single_instruction(
+    configurations: &Configurations,
+    contextual_information_object: &ContextualInformation,
+    request: &str,
+) -> Result<(), Error> {
+    let mut agent: SemiAutonomousCommandLineAgent =
+        SemiAutonomousCommandLineAgent::new(contextual_information_object, configurations)?;
+    let mut user_prompt: String = String::from(request);
+    let mut session: SessionRecorder = start_session(configurations, request);
+
+    let command_json: LLMActionType = loop {
+        let command_json: LLMActionType =
+            fetch_allowed_step(&mut agent, configurations, &mut session, &mut user_prompt)?;
+
+        match command_json {
+            LLMActionType::Execute(_) | LLMActionType::Plan(_) => break command_json,
+            other => {
+                user_prompt =
+                    prompt_user_for_command_execution(&other, configurations, &user_prompt)?;
+                record_in_history(
+                    &mut session,
+                    SessionEventKind::Decision {
+                        input: user_prompt.clone(),
+                    },
+                );
+                agent.add(
+                    async_openai::types::Role::Assistant,
+                    format!("{:#?}", other),
+                )?;
+            }
+        }
+    };
+
+    let command: String = command_json.get_commands().join(" && ");
+    let risk: RiskAssessment = command_json.assess_risk();
+    display_message(Level::Logging, &command_json.get_explanation());
+    if risk.get_level() != RiskLevel::Low {
+        display_message(Level::Warn, &risk.get_summary());
+    }
+
+    match is_json_output() {
+        true => emit(&OutputEvent::Suggestion { command: &command }),
+        false => println!("{}", command),
+    }
+
+    Ok(())
+}
+
 pub fn process_interactive_mode(
     cache: &mut Cache,
     configurations: &Configurations,
     contextual_information_object: &ContextualInformation,
 ) -> Result<(), Error> {
     let mut agent: SemiAutonomousCommandLineAgent =
-        SemiAutonomousCommandLineAgent::new(contextual_information_object)?;
-    let mut command_store: LLMActionType;
+        SemiAutonomousCommandLineAgent::new(contextual_information_object, configurations)?;
+    let mut script_store: String;
+    let mut script_parameters: Vec<ScriptParameter>;
+    let mut script_description: String;
+    let mut repair_rounds: u32 = 0;
     let mut user_query: String = input_message("Yes, boss. What can I do for you:")?;
+    let mut session: SessionRecorder = start_session(configurations, "");
+    // What scripts saved from this session are cached for
+    let request: String = user_query.trim().to_string();
+    record_in_history(
+        &mut session,
+        SessionEventKind::Instruction {
+            text: user_query.clone(),
+        },
+    );
 
     loop {
         let mut command_json: LLMActionType =
-            process_command_interaction(&mut agent, &mut user_query)?;
+            process_command_interaction(&mut agent, configurations, &mut session, &mut user_query)?;
 
         if user_query.trim() == "y" {
-            match command_json.execute() {
-                Ok(result) => {
-                    // Store the command
-                    command_store = command_json;
+            match execute_proposal(&mut command_json, configurations, &mut session)? {
+                ExecutionOutcome::Succeeded {
+                    script,
+                    parameters,
+                    feedback,
+                } => {
+                    repair_rounds = 0;
+                    // Store the commands
+                    script_store = script;
+                    script_parameters = parameters;
+                    script_description = command_json.get_explanation();
                     // Store the output to the user_query
                     user_query.clear();
-                    user_query.push_str(&format!(
-                        "Here is the previous output of the command/script:\n{}\n\n",
-                        result
-                    ));
+                    user_query.push_str(&feedback);
 
                     display_message(Level::Logging, "Commands had been executed successfully.");
 
@@ -151,16 +1018,21 @@ pub fn process_interactive_mode(
                     if user_input.trim() == "w" {
                         let name: String = input_message("Name of the chain:")?;
 
-                        // Extract command if it's an Execute action type
-                        if let LLMActionType::Execute(ref mut execute_action) = command_store {
-                            if configurations.enable_cache {
-                                save_to_shell_in_cache(cache, name.trim(), execute_action)?;
-                                break;
-                            }
-
-                            save_to_shell(name.trim(), execute_action)?;
+                        if configurations.enable_cache {
+                            save_to_shell_in_cache(
+                                cache,
+                                configurations,
+                                name.trim(),
+                                &script_store,
+                                script_parameters,
+                                &request,
+                                &script_description,
+                            )?;
+                            break;
                         }
 
+                        save_to_shell(name.trim(), &script_store, &script_parameters)?;
+
                         let user_feedback: String =
                             input_message("Continue? (y for yes, e for exit):")?;
 
@@ -169,21 +1041,78 @@ pub fn process_interactive_mode(
                         }
 
                         if user_feedback.trim() == "y" {
-                            user_query.push_str(&input_message(
+                            let instruction: String = input_message(
                                 "Boss, what else can I do for you (type to instruct):",
-                            )?);
+                            )?;
+                            record_in_history(
+                                &mut session,
+                                SessionEventKind::Instruction {
+                                    text: instruction.clone(),
+                                },
+                            );
+                            user_query.push_str(&instruction);
                         }
+                    } else {
+                        record_in_history(
+                            &mut session,
+                            SessionEventKind::Instruction {
+                                text: user_input.clone(),
+                            },
+                        );
                     }
 
                     user_query.push_str(&user_input);
                 }
-                Err(error) => {
-                    display_message(Level::Error, &error.to_string());
+                ExecutionOutcome::Aborted { feedback } => {
+                    repair_rounds = 0;
+
+                    let user_input: String = input_message(
+                        "Boss, what else can I do for you (type to instruct, or e to exit):",
+                    )?;
+                    if user_input.trim() == "e" {
+                        break;
+                    }
+                    record_in_history(
+                        &mut session,
+                        SessionEventKind::Instruction {
+                            text: user_input.clone(),
+                        },
+                    );
+
+                    user_query = feedback + &user_input;
                 }
-            };
-        }
+                ExecutionOutcome::Continued { feedback } => user_query = feedback,
+                ExecutionOutcome::Failed { feedback, .. } => {
+                    match request_repair(configurations, &mut repair_rounds, &feedback) {
+                        Some(repair_prompt) => user_query = repair_prompt,
+                        None => {
+                            repair_rounds = 0;
+                            display_message(
+                                Level::Error,
+                                "The command still fails, the LLM could not fix it on its own.",
+                            );
 
-        if user_query.trim() == "e" {
+                            let user_input: String = input_message(
+                                "Boss, what should I do (type to instruct, or e to exit):",
+                            )?;
+                            if user_input.trim() == "e" {
+                                break;
+                            }
+                            record_in_history(
+                                &mut session,
+                                SessionEventKind::Instruction {
+                                    text: user_input.clone(),
+                                },
+                            );
+
+                            user_query = feedback + &user_input;
+                        }
+                    }
+                }
+            };
+        }
+
+        if user_query.trim() == "e" {
             display_message(Level::Logging, "See you boss.");
             break;
         }
@@ -193,17 +1122,32 @@ pub fn process_interactive_mode(
 }
 
 pub fn process_explanation_with_one_single_instruction(
-    command: &str,
+    configurations: &Configurations,
     contextual_information_object: &ContextualInformation,
+    command: &str,
 ) -> Result<(), Error> {
-    let mut agent = CommandLineExplainAgent::new(contextual_information_object)?;
+    let mut agent = CommandLineExplainAgent::new(contextual_information_object, configurations)?;
 
     // Use the user query provided in the `run` argument for the first round
-    let spinner: ProgressBar = start_spinner("LLM is thinking...".to_string());
-    let command_line_explained = agent.next_step(&command)?;
+    let (command_line_explained, is_streamed): (CommandExplained, bool) =
+        fetch_next_step(&mut agent, configurations, command)?;
+
+    if is_json_output() {
+        emit(&OutputEvent::Explanation {
+            command,
+            explanation: &command_line_explained,
+        });
+        return Ok(());
+    }
 
-    // Clear the spinner
-    spinner.finish_and_clear();
+    // The explanation has already been shown while streaming, only the breakdown is left
+    if is_streamed {
+        let breakdown: String = command_line_explained.format_breakdown();
+        if !breakdown.is_empty() {
+            display_message(Level::Logging, &breakdown);
+        }
+        return Ok(());
+    }
 
     // For prompting the LLM and the user
     let command_lines_explanation: String = command_line_explained.to_string() + "\n";
@@ -213,9 +1157,14 @@ pub fn process_explanation_with_one_single_instruction(
     Ok(())
 }
 
-fn save_to_shell(shell_name: &str, execute_action: &mut ActionTypeExecute) -> Result<(), Error> {
-    let mut file_content: String = String::from("#!/usr/bin/env sh\n");
-    file_content.push_str(execute_action.get_commands());
+/// Saves `commands` to a script in the working directory, with its parameters set to their defaults.
+fn save_to_shell(
+    shell_name: &str,
+    commands: &str,
+    parameters: &[ScriptParameter],
+) -> Result<(), Error> {
+    let mut file_content: String = get_shebang();
+    file_content.push_str(&render_defaults(commands, parameters)?);
 
     let filepath: &str = &format!("./{}.sh", shell_name);
     std::fs::write(filepath, file_content)?;
@@ -227,21 +1176,650 @@ fn save_to_shell(shell_name: &str, execute_action: &mut ActionTypeExecute) -> Re
     Ok(())
 }
 
-pub fn process_list_cached_scripts(cache: &Cache) -> Result<(), Error> {
-    let scripts: Vec<String> = cache.list_scripts();
-    
-    if scripts.is_empty() {
+/// Restores what the last command in the journal changed, after the user confirmed it.
+pub fn process_undo() -> Result<(), Error> {
+    let journal: Journal = Journal::load()?;
+    let Some(mut entry) = journal.get_last_entry()? else {
+        display_message(Level::Logging, "There is nothing to undo.");
+        return Ok(());
+    };
+
+    display_message(
+        Level::Logging,
+        &format!(
+            "The last command was `{}`, run in {} at {}.",
+            entry.command,
+            entry.working_directory.display(),
+            entry.recorded_at
+        ),
+    );
+
+    if entry.snapshots.is_empty() {
+        display_message(
+            Level::Warn,
+            "No files were expected to change, so nothing was snapshotted and nothing can be restored.",
+        );
+        journal.undo(&mut entry)?;
+        return Ok(());
+    }
+
+    let actions: Vec<String> = entry
+        .snapshots
+        .iter()
+        .map(|snapshot| format!("    {}", snapshot.describe()))
+        .collect();
+    display_message(
+        Level::Logging,
+        &format!("Undoing it will:\n{}", actions.join("\n")),
+    );
+
+    let user_input: String = input_message("Undo the command? (y to undo, n to keep everything)")?;
+    if user_input.trim() != "y" {
+        display_message(Level::Logging, "Nothing has been changed.");
+        return Ok(());
+    }
+
+    let failures: Vec<String> = journal.undo(&mut entry)?;
+    if failures.is_empty() {
+        display_message(
+            Level::Logging,
+            &format!("`{}` has been undone.", entry.command),
+        );
+        return Ok(());
+    }
+
+    display_message(
+        Level::Error,
+        &format!(
+            "`{}` has been undone, except for:\n{}",
+            entry.command,
+            failures.join("\n")
+        ),
+    );
+
+    Ok(())
+}
+
+/// Lists the most recent sessions, or the ones matching `query`, up to `limit` of them.
+pub fn process_history_list(query: Option<&str>, limit: usize) -> Result<(), Error> {
+    let sessions: Vec<Session> = History::load()?
+        .list()?
+        .into_iter()
+        .filter(|session| query.is_none_or(|query| session.matches(query)))
+        .take(limit)
+        .collect();
+
+    if sessions.is_empty() {
+        match query {
+            Some(query) => {
+                display_message(Level::Logging, &format!("No sessions match `{}`.", query))
+            }
+            None => display_message(Level::Logging, "No sessions found."),
+        }
+        return Ok(());
+    }
+
+    display_message(Level::Logging, "Sessions, the most recent first:");
+    for session in sessions {
+        display_message(
+            Level::Logging,
+            &format!(
+                "  - {} [{}] {}\n      {}, in {}",
+                session.id,
+                session.get_start_time(),
+                session.prompt.trim(),
+                session.get_status(),
+                session.working_directory.display()
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+/// Shows everything that happened in the session `id`.
+pub fn process_history_show(id: &str) -> Result<(), Error> {
+    let session: Session = History::load()?.get(id)?;
+
+    let mut details: String = format!(
+        "Session {}\nStarted at: {}\nWorking directory: {}\nPrompt: {}\n",
+        session.id,
+        session.get_start_time(),
+        session.working_directory.display(),
+        session.prompt.trim()
+    );
+    for event in &session.events {
+        details.push_str(&format!(
+            "    [{}] {}\n",
+            event.recorded_at,
+            event.describe()
+        ));
+    }
+    display_message(Level::Logging, &details);
+
+    Ok(())
+}
+
+/// Runs the commands that succeeded in the session `id` again, in the current directory.
+///
+/// The re-run is recorded as a session of its own, with the same prompt.
+pub fn process_history_rerun(configurations: &Configurations, id: &str) -> Result<(), Error> {
+    let session: Session = History::load()?.get(id)?;
+    let commands: Vec<&str> = session.get_succeeded_commands();
+    if commands.is_empty() {
+        display_message(
+            Level::Logging,
+            "No command ran successfully in this session, there is nothing to re-run.",
+        );
+        return Ok(());
+    }
+
+    let mut risk: RiskAssessment = RiskAssessment::default();
+    let mut prompt: String = format!(
+        "Re-run the commands of `{}`? (y to run them, n to cancel)\n",
+        session.prompt.trim()
+    );
+    for command in &commands {
+        prompt.push_str(&format!("    > {}\n", command));
+        risk.merge(assess_in_current_directory(command));
+    }
+    if risk.get_level() != RiskLevel::Low {
+        prompt.push_str(&format!("        ! {}\n", risk.get_summary()));
+    }
+    ensure_not_blocked(configurations, &risk)?;
+
+    if std::env::current_dir()? != session.working_directory {
+        display_message(
+            Level::Warn,
+            &format!(
+                "The session ran in {}, the commands will now run in the current directory.",
+                session.working_directory.display()
+            ),
+        );
+    }
+
+    let is_high_risk: bool = risk.get_level() == RiskLevel::High;
+    if read_approval(&prompt, is_high_risk)?.trim() != "y" {
+        display_message(Level::Logging, "Nothing has been run.");
+        return Err(YouError::UserDeclined.into());
+    }
+
+    let mut rerun_session: SessionRecorder = start_session(configurations, &session.prompt);
+    for command in commands {
+        if let ExecutedCommand::Finished(result) =
+            execute_command(configurations, &mut rerun_session, command)?
+            && !result.is_success()
+        {
+            return Err(YouError::CommandFailed {
+                message: format!("The command `{}` {}", command, result.get_status()),
+                exit_code: result.exit_code,
+            }
+            .into());
+        }
+    }
+    display_message(Level::Logging, "Commands had been executed successfully.");
+
+    Ok(())
+}
+
+/// Lists the cached scripts that have all of `tags`, in a table sorted by `sort_by`.
+pub fn process_list_cached_scripts(
+    cache: &Cache,
+    sort_by: SortScriptsBy,
+    tags: &[String],
+) -> Result<(), Error> {
+    let mut scripts: Vec<CachedScript> = cache
+        .list_scripts()
+        .into_iter()
+        .filter(|script| tags.iter().all(|tag| script.metadata.tags.contains(tag)))
+        .collect();
+
+    if scripts.is_empty() && !is_json_output() {
+        match tags.is_empty() {
+            true => display_message(Level::Logging, "No cached scripts found."),
+            false => display_message(
+                Level::Logging,
+                &format!("No cached scripts are tagged {}.", tags.join(", ")),
+            ),
+        }
+        return Ok(());
+    }
+
+    // Names first, so that ties are in a stable order. RFC 3339 timestamps sort as text
+    scripts.sort_by(|a, b| a.name.cmp(&b.name));
+    match sort_by {
+        SortScriptsBy::Name => {}
+        SortScriptsBy::Created => {
+            scripts.sort_by(|a, b| b.metadata.created_at.cmp(&a.metadata.created_at))
+        }
+        SortScriptsBy::LastUsed => {
+            scripts.sort_by(|a, b| b.metadata.last_used_at.cmp(&a.metadata.last_used_at))
+        }
+        SortScriptsBy::Runs => {
+            scripts.sort_by_key(|script| std::cmp::Reverse(script.metadata.run_count))
+        }
+    }
+
+    if is_json_output() {
+        let scripts: Vec<ScriptEntry> = scripts
+            .iter()
+            .map(|script| {
+                ScriptEntry::new(
+                    &script.name,
+                    script.source.to_string(),
+                    None,
+                    &script.metadata,
+                )
+            })
+            .collect();
+        emit(&OutputEvent::Scripts { scripts });
+        return Ok(());
+    }
+
+    let mut rows: Vec<[String; 9]> = vec![
+        [
+            "NAME",
+            "RUNS",
+            "LAST USED",
+            "CREATED",
+            "TAGS",
+            "OS",
+            "SHELL",
+            "SOURCE",
+            "REQUEST",
+        ]
+        .map(String::from),
+    ];
+    for script in scripts {
+        let metadata: ScriptMetadata = script.metadata;
+        rows.push([
+            script.name,
+            metadata.run_count.to_string(),
+            format_timestamp(metadata.last_used_at.as_deref(), "never"),
+            format_timestamp(metadata.created_at.as_deref(), "-"),
+            or_dash(metadata.tags.join(",")),
+            or_dash(metadata.operating_system),
+            or_dash(metadata.shell),
+            script.source.to_string(),
+            metadata.request,
+        ]);
+    }
+
+    let mut widths: [usize; 9] = [0; 9];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table: String = String::from("Cached scripts:\n");
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        table.push_str(&format!("  {}\n", cells.join("  ").trim_end()));
+    }
+    display_message(Level::Logging, &table);
+
+    Ok(())
+}
+
+/// An RFC 3339 `timestamp` in local time, or `missing` without one.
+fn format_timestamp(timestamp: Option<&str>, missing: &str) -> String {
+    match timestamp.map(chrono::DateTime::parse_from_rfc3339) {
+        Some(Ok(timestamp)) => timestamp
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        Some(Err(_)) | None => missing.to_string(),
+    }
+}
+
+fn or_dash(value: String) -> String {
+    if value.is_empty() {
+        return "-".to_string();
+    }
+
+    value
+}
+
+/// Displays `script_name` with what it was saved for, its parameters and its tags.
+pub fn process_show_cached_script(cache: &Cache, script_name: &str) -> Result<(), Error> {
+    let script: CachedScript = cache.find_script(script_name)?;
+
+    if let ScriptSource::Shared(directory) = &script.source {
+        display_message(
+            Level::Logging,
+            &format!("Shared from {}, read-only.", directory.display()),
+        );
+    }
+    display_script(
+        script_name,
+        &script.source.to_string(),
+        &std::fs::read_to_string(&script.path)?,
+        &script.metadata,
+    );
+
+    Ok(())
+}
+
+/// Displays `script` with what it was saved for, its parameters and its tags.
+///
+/// `source` is where the script comes from, only reported with `--output json`.
+fn display_script(script_name: &str, source: &str, script: &str, metadata: &ScriptMetadata) {
+    if is_json_output() {
+        let script: ScriptEntry = ScriptEntry::new(
+            script_name,
+            source.to_string(),
+            Some(script.to_string()),
+            metadata,
+        );
+        return emit(&OutputEvent::Script { script: &script });
+    }
+
+    let mut details: String = format!("Script {}, saved for: {}\n", script_name, metadata.request);
+    if !metadata.description.is_empty() {
+        details.push_str(&format!("    * {}\n", metadata.description));
+    }
+    if !metadata.parameters.is_empty() {
+        details.push_str(&format!(
+            "    $ {}\n",
+            describe_parameters(&metadata.parameters)
+        ));
+    }
+    if !metadata.tags.is_empty() {
+        details.push_str(&format!("    Tags: {}\n", metadata.tags.join(", ")));
+    }
+    display_message(Level::Logging, &details);
+    println!("{}", highlight_shell(script));
+}
+
+/// Opens `script_name` in `$EDITOR`, then checks the edited script.
+///
+/// A script the shell cannot parse is edited again, or restored as it was. A
+/// risky script is kept, but its risk is reported.
+pub fn process_edit_cached_script(cache: &Cache, script_name: &str) -> Result<(), Error> {
+    let script_path: PathBuf = cache.get_personal_script_path(script_name)?;
+    let original_script: String = std::fs::read_to_string(&script_path)?;
+
+    loop {
+        open_in_editor(&script_path)?;
+        let edited_script: String = std::fs::read_to_string(&script_path)?;
+        if edited_script == original_script {
+            display_message(Level::Logging, "The script has not changed.");
+            return Ok(());
+        }
+
+        let Some(syntax_error) = check_shell_syntax(&script_path)? else {
+            let risk: RiskAssessment = assess_in_current_directory(&edited_script);
+            if risk.get_level() != RiskLevel::Low {
+                display_message(Level::Warn, &risk.get_summary());
+            }
+            display_message(
+                Level::Logging,
+                &format!("Script '{}' has been updated.", script_name),
+            );
+            return Ok(());
+        };
+
+        display_message(
+            Level::Error,
+            &format!("The shell cannot parse the script: {}", syntax_error),
+        );
+        let user_input: String = input_message(
+            "Edit it again? (y to edit it again, n to restore the previous version)",
+        )?;
+        // An empty input means stdin is closed, the previous version is kept
+        if user_input.trim() != "y" {
+            std::fs::write(&script_path, &original_script)?;
+            display_message(Level::Logging, "The previous version has been restored.");
+            return Ok(());
+        }
+    }
+}
+
+/// Opens `path` in `$EDITOR`, which may come with arguments, and waits for it to be closed.
+fn open_in_editor(path: &Path) -> Result<(), Error> {
+    let editor: String = std::env::var("EDITOR").unwrap_or_else(|_| {
+        match cfg!(target_os = "windows") {
+            true => "notepad",
+            false => "vi",
+        }
+        .to_string()
+    });
+
+    let status: ExitStatus = match cfg!(target_os = "windows") {
+        true => Command::new("cmd")
+            .arg("/C")
+            .arg(format!("{} \"{}\"", editor, path.display()))
+            .status(),
+        false => Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", editor))
+            .arg("sh")
+            .arg(path)
+            .status(),
+    }
+    .map_err(|error| anyhow!("Failed to open the editor `{}`: {}", editor, error))?;
+    if !status.success() {
+        return Err(anyhow!("The editor `{}` exited with {}", editor, status));
+    }
+
+    Ok(())
+}
+
+/// Interpreters that can check a script without running it, with `-n`.
+const SYNTAX_CHECKING_SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh", "fish"];
+
+/// What the interpreter of its shebang reports about the script at `path`, if it cannot
+/// parse it.
+///
+/// Only shells have a syntax check, so other scripts are always accepted, as are all
+/// scripts on Windows.
+fn check_shell_syntax(path: &Path) -> Result<Option<String>, Error> {
+    if cfg!(target_os = "windows") {
+        return Ok(None);
+    }
+
+    let interpreter: String = get_script_interpreter(&std::fs::read_to_string(path)?);
+    let program: &str = Path::new(&interpreter)
+        .file_name()
+        .and_then(|program| program.to_str())
+        .unwrap_or(&interpreter);
+    if !SYNTAX_CHECKING_SHELLS.contains(&program) {
+        return Ok(None);
+    }
+
+    let output: Output = Command::new(&interpreter).arg("-n").arg(path).output()?;
+    if output.status.success() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ))
+}
+
+pub fn process_rename_cached_script(
+    cache: &mut Cache,
+    old_name: &str,
+    new_name: &str,
+) -> Result<(), Error> {
+    cache.rename_script(old_name, new_name)?;
+    display_message(
+        Level::Logging,
+        &format!("Script '{}' has been renamed to '{}'.", old_name, new_name),
+    );
+
+    Ok(())
+}
+
+/// Writes the cached scripts named `script_names`, or tagged with all of `tags`, to a
+/// bundle at `output`. Every script is exported without any of them.
+pub fn process_export_cached_scripts(
+    cache: &Cache,
+    script_names: &[String],
+    tags: &[String],
+    output: &Path,
+) -> Result<(), Error> {
+    let mut script_names: Vec<String> = script_names.to_vec();
+    if !tags.is_empty() {
+        let tagged_script_names: Vec<String> = cache
+            .list_scripts()
+            .into_iter()
+            .filter(|script| tags.iter().all(|tag| script.metadata.tags.contains(tag)))
+            .filter(|script| script_names.is_empty() || script_names.contains(&script.name))
+            .map(|script| script.name)
+            .collect();
+        if tagged_script_names.is_empty() {
+            display_message(
+                Level::Logging,
+                &format!("No cached scripts are tagged {}.", tags.join(", ")),
+            );
+            return Ok(());
+        }
+        script_names = tagged_script_names;
+    }
+
+    let bundle: ScriptBundle = cache.export(&script_names)?;
+    if bundle.scripts.is_empty() {
         display_message(Level::Logging, "No cached scripts found.");
-    } else {
-        display_message(Level::Logging, "Cached scripts:");
-        for script in scripts {
-            display_message(Level::Logging, &format!("  - {}", script));
+        return Ok(());
+    }
+
+    bundle.write(output)?;
+    display_message(
+        Level::Logging,
+        &format!(
+            "{} script(s) have been exported to {}.",
+            bundle.scripts.len(),
+            output.display()
+        ),
+    );
+
+    Ok(())
+}
+
+/// Adds the scripts of the bundle at `path` to the cache, once the user approved each of
+/// them.
+///
+/// A script whose name is taken is skipped, renamed or overwrites the cached one, as
+/// the user chooses.
+pub fn process_import_cached_scripts(
+    cache: &mut Cache,
+    configurations: &Configurations,
+    path: &Path,
+) -> Result<(), Error> {
+    let bundle: ScriptBundle = ScriptBundle::read(path)?;
+    if bundle.scripts.is_empty() {
+        display_message(Level::Logging, "The bundle has no scripts.");
+        return Ok(());
+    }
+
+    let mut imported_count: usize = 0;
+    for mut script in bundle.scripts.iter().cloned() {
+        display_script(
+            &script.name,
+            &path.display().to_string(),
+            &script.content,
+            &script.metadata,
+        );
+
+        let risk: RiskAssessment = assess_in_current_directory(&script.content);
+        if risk.get_level() != RiskLevel::Low {
+            display_message(Level::Warn, &risk.get_summary());
         }
+        let is_high_risk: bool = risk.get_level() == RiskLevel::High;
+        let prompt: String = match is_high_risk {
+            true => format!(
+                "Import {}? (yes to import this high-risk script, n to skip it)",
+                script.name
+            ),
+            false => format!("Import {}? (y to import it, n to skip it)", script.name),
+        };
+        if read_approval(&prompt, is_high_risk)?.trim() != "y" {
+            display_message(
+                Level::Logging,
+                &format!("{} has been skipped.", script.name),
+            );
+            continue;
+        }
+
+        let Some((script_name, is_overwriting)) = resolve_name_collision(cache, &script.name)?
+        else {
+            display_message(
+                Level::Logging,
+                &format!("{} has been skipped.", script.name),
+            );
+            continue;
+        };
+
+        if configurations.cache_lookup.use_embeddings {
+            script.metadata.embedding = embed_request(configurations, &script.metadata.request);
+        }
+        cache.import_script(&script_name, &script, is_overwriting)?;
+        imported_count += 1;
+        display_message(
+            Level::Logging,
+            &format!("{} has been imported.", script_name),
+        );
     }
-    
+
+    display_message(
+        Level::Logging,
+        &format!(
+            "{} of {} script(s) have been imported.",
+            imported_count,
+            bundle.scripts.len()
+        ),
+    );
+
     Ok(())
 }
 
+/// The name to import `script_name` as, and whether it overwrites the cached script of
+/// that name, or `None` if the user skips it.
+fn resolve_name_collision(
+    cache: &Cache,
+    script_name: &str,
+) -> Result<Option<(String, bool)>, Error> {
+    if cache.get_script_path(script_name).is_err() {
+        return Ok(Some((script_name.to_string(), false)));
+    }
+
+    loop {
+        let user_input: String = input_message(&format!(
+            "A script named {} is already cached. (s to skip it, r to rename it, o to overwrite the cached one)",
+            script_name
+        ))?;
+
+        match user_input.trim() {
+            // An empty input means stdin is closed, nothing is replaced without consent
+            "s" | "" => return Ok(None),
+            "o" => return Ok(Some((script_name.to_string(), true))),
+            "r" => loop {
+                let new_name: String = input_message("New name for the script:")?;
+                let new_name: &str = new_name.trim();
+                if new_name.is_empty() {
+                    return Ok(None);
+                }
+
+                match validate_script_name(new_name) {
+                    Err(error) => display_message(Level::Warn, &error.to_string()),
+                    Ok(()) if cache.get_script_path(new_name).is_ok() => display_message(
+                        Level::Warn,
+                        &format!("A script named {} is already cached too.", new_name),
+                    ),
+                    Ok(()) => return Ok(Some((new_name.to_string(), false))),
+                }
+            },
+            _ => display_message(Level::Warn, "Please answer with s, r or o."),
+        }
+    }
+}
+
 pub fn process_remove_cached_script(cache: &mut Cache, script_name: &str) -> Result<(), Error> {
     match cache.delete_script(script_name) {
         Ok(_) => {
@@ -255,15 +1833,332 @@ pub fn process_remove_cached_script(cache: &mut Cache, script_name: &str) -> Res
     Ok(())
 }
 
+/// Offers the cached scripts that are similar to `command_in_natural_language`, and runs
+/// the one the user picks.
+///
+/// Returns whether a cached script was run. Otherwise, the request is for the LLM.
+pub fn process_cached_script_lookup(
+    cache: &mut Cache,
+    configurations: &Configurations,
+    command_in_natural_language: &str,
+) -> Result<bool, Error> {
+    let query_embedding: Option<Vec<f32>> = match configurations.cache_lookup.use_embeddings {
+        true => embed_request(configurations, command_in_natural_language),
+        false => None,
+    };
+    let candidates: Vec<CacheCandidate> = cache.search(
+        command_in_natural_language,
+        query_embedding.as_deref(),
+        &configurations.cache_lookup,
+    );
+    if candidates.is_empty() {
+        return Ok(false);
+    }
+
+    let mut scripts: Vec<String> = Vec::new();
+    let mut listed_candidates: String = String::from("Cached scripts similar to your request:\n");
+    for (index, candidate) in candidates.iter().enumerate() {
+        let script: String = std::fs::read_to_string(&candidate.path)?;
+        listed_candidates.push_str(&format!(
+            "    {}. {} ({:.0}% similar), saved for: {}\n",
+            index + 1,
+            candidate.name,
+            candidate.similarity * 100.0,
+            candidate.metadata.request
+        ));
+        if !candidate.metadata.description.is_empty() {
+            listed_candidates.push_str(&format!("        * {}\n", candidate.metadata.description));
+        }
+        for line in script.lines().filter(|line| !line.starts_with("#!")) {
+            listed_candidates.push_str(&format!("        > {}\n", line));
+        }
+        if !candidate.metadata.parameters.is_empty() {
+            listed_candidates.push_str(&format!(
+                "        $ {}\n",
+                describe_parameters(&candidate.metadata.parameters)
+            ));
+        }
+        scripts.push(script);
+    }
+    display_message(Level::Logging, &listed_candidates);
+
+    // Picking a script is left to the user, with `--yes` the LLM is asked instead
+    if configurations.approval_policy.is_non_interactive {
+        log_answer("pick_cached_script", "n", &[("reason", "--yes")]);
+        return Ok(false);
+    }
+
+    let index: usize = loop {
+        let user_input: String = input_message(&format!(
+            "Run a cached script? (1 to {} to run it, n to ask the LLM instead)",
+            candidates.len()
+        ))?;
+
+        match user_input.trim().parse::<usize>() {
+            Ok(number) if (1..=candidates.len()).contains(&number) => break number - 1,
+            // An empty input means stdin is closed, nothing runs without consent
+            _ if user_input.is_empty() || user_input.trim() == "n" => return Ok(false),
+            _ => display_message(
+                Level::Warn,
+                &format!("Please answer with 1 to {} or n.", candidates.len()),
+            ),
+        }
+    };
+
+    let values: HashMap<String, String> =
+        read_parameter_values(&candidates[index].metadata.parameters)?;
+    let command: String = render(
+        &scripts[index],
+        &candidates[index].metadata.parameters,
+        &values,
+    )?;
+    let executed_command: ExecutedCommand = run_cached_script(
+        cache,
+        configurations,
+        &candidates[index].name,
+        &command,
+        &build_script_command(&candidates[index].name, &command, &[])?,
+        command_in_natural_language,
+    )?;
+    ensure_cached_script_succeeded(executed_command)?;
+
+    Ok(true)
+}
+
+/// Runs the cached script `script_name`, with its parameters set by `name=value` assignments.
+///
+/// Parameters that are not assigned get their default.
+pub fn process_run_cached_script(
+    cache: &mut Cache,
+    configurations: &Configurations,
+    script_name: &str,
+    assignments: &[String],
+) -> Result<(), Error> {
+    let script: String = cache.read_script(script_name)?;
+    let metadata: ScriptMetadata = cache.get_metadata(script_name);
+    let command: String = render(
+        &script,
+        &metadata.parameters,
+        &parse_assignments(assignments)?,
+    )?;
+
+    let executed_command: ExecutedCommand = run_cached_script(
+        cache,
+        configurations,
+        script_name,
+        &command,
+        &build_script_command(script_name, &command, &[])?,
+        &metadata.request,
+    )?;
+
+    ensure_cached_script_succeeded(executed_command)
+}
+
+/// Fails with the exit code of the cached script, if it ran and failed.
+fn ensure_cached_script_succeeded(executed_command: ExecutedCommand) -> Result<(), Error> {
+    match executed_command {
+        ExecutedCommand::Finished(result) if !result.is_success() => Err(YouError::CommandFailed {
+            message: format!("The cached script {}", result.get_status()),
+            exit_code: result.exit_code,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Runs the cached script `script_name` with `arguments`, which it gets as "$@", $1, $2...
+/// Its parameters, if any, keep their default.
+///
+/// Returns the exit code of the script, for `you exec` to exit with. A script whose
+/// sandboxed changes are discarded counts as declined, whatever its exit code.
+pub fn process_exec_cached_script(
+    cache: &mut Cache,
+    configurations: &Configurations,
+    script_name: &str,
+    arguments: &[String],
+) -> Result<i32, Error> {
+    let script: CachedScript = cache.find_script(script_name)?;
+    let content: String = render_defaults(
+        &std::fs::read_to_string(&script.path)?,
+        &script.metadata.parameters,
+    )?;
+
+    let executed_command: ExecutedCommand = run_cached_script(
+        cache,
+        configurations,
+        script_name,
+        &content,
+        &build_script_command(script_name, &content, arguments)?,
+        &script.metadata.request,
+    )?;
+    match executed_command {
+        // A script killed by a signal has no exit code of its own
+        ExecutedCommand::Finished(result) => Ok(result.exit_code.unwrap_or(1)),
+        ExecutedCommand::Discarded(_) => Err(YouError::UserDeclined.into()),
+    }
+}
+
+/// The command that runs `script` with the interpreter of its shebang, passing it as
+/// is so that `arguments` come after it. `script_name` is its `$0`.
+///
+/// Cached scripts are shell scripts, so this fails on Windows, where commands run in `cmd`.
+fn build_script_command(
+    script_name: &str,
+    script: &str,
+    arguments: &[String],
+) -> Result<String, Error> {
+    if cfg!(target_os = "windows") {
+        return Err(anyhow!(
+            "Cached scripts are shell scripts, which cannot run with `cmd` on Windows"
+        ));
+    }
+
+    let mut command: String = format!(
+        "{} -c {} {}",
+        quote(&get_script_interpreter(script)),
+        quote(script),
+        quote(script_name)
+    );
+    for argument in arguments {
+        command.push(' ');
+        command.push_str(&quote(argument));
+    }
+
+    Ok(command)
+}
+
+/// Asks the user for the value of each parameter. An empty answer keeps the default.
+fn read_parameter_values(parameters: &[ScriptParameter]) -> Result<HashMap<String, String>, Error> {
+    let mut values: HashMap<String, String> = HashMap::new();
+
+    for parameter in parameters {
+        loop {
+            let user_input: String = input_message(&format!(
+                "{} ({}, {}) [{}]:",
+                parameter.name, parameter.kind, parameter.description, parameter.default
+            ))?;
+            let value: &str = user_input.trim();
+            if value.is_empty() {
+                break;
+            }
+
+            match parameter.validate(value) {
+                Ok(()) => {
+                    values.insert(parameter.name.clone(), value.to_string());
+                    break;
+                }
+                Err(error) => display_message(Level::Warn, &error.to_string()),
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+/// Runs `command`, which runs the filled-in `script` of `script_name`, once the user
+/// approved it if it is high risk.
+///
+/// Fails with `YouError::UserDeclined` if the user did not approve it.
+fn run_cached_script(
+    cache: &mut Cache,
+    configurations: &Configurations,
+    script_name: &str,
+    script: &str,
+    command: &str,
+    request: &str,
+) -> Result<ExecutedCommand, Error> {
+    let risk: RiskAssessment = assess_in_current_directory(script);
+    if risk.get_level() != RiskLevel::Low {
+        display_message(Level::Warn, &risk.get_summary());
+    }
+    ensure_not_blocked(configurations, &risk)?;
+    if configurations.approval_policy.is_non_interactive {
+        let reason: String = approve_by_policy(&configurations.approval_policy, script)?;
+        log_answer(
+            "approve_command",
+            "y",
+            &[("script", script_name), ("reason", &reason)],
+        );
+    } else if risk.get_level() == RiskLevel::High
+        && read_approval(
+            "This script is high risk. Run it anyway? (yes to run it)",
+            true,
+        )?
+        .trim()
+            != "y"
+    {
+        display_message(Level::Logging, "Nothing has been run.");
+        return Err(YouError::UserDeclined.into());
+    }
+
+    display_message(
+        Level::Logging,
+        &format!("Using the cached script {}...", script_name),
+    );
+    let mut session: SessionRecorder = start_session(configurations, request);
+    let executed_command: ExecutedCommand = execute_command(configurations, &mut session, command)?;
+    if let Err(error) = cache.record_run(script_name) {
+        display_message(
+            Level::Warn,
+            &format!("Failed to record the run in the cache: {}", error),
+        );
+    }
+
+    Ok(executed_command)
+}
+
+/// Embedding of `request` from the configured backend, or `None` with a warning if it
+/// cannot be had, in which case requests are only compared by their words.
+fn embed_request(configurations: &Configurations, request: &str) -> Option<Vec<f32>> {
+    let embedding: Result<Vec<f32>, Error> = create_llm_backend(configurations, None)
+        .and_then(|backend: std::sync::Arc<dyn LLMBackend>| backend.embed(&[request.to_string()]))
+        .and_then(|embeddings| {
+            embeddings
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("No embedding is returned"))
+        });
+
+    match embedding {
+        Ok(embedding) => Some(embedding),
+        Err(error) => {
+            display_message(
+                Level::Warn,
+                &format!(
+                    "Embeddings are not available, requests are compared by their words only: {}",
+                    error
+                ),
+            );
+            None
+        }
+    }
+}
+
 fn save_to_shell_in_cache(
     cache: &mut Cache,
+    configurations: &Configurations,
     shell_name: &str,
-    execute_action: &mut ActionTypeExecute,
+    commands: &str,
+    parameters: Vec<ScriptParameter>,
+    request: &str,
+    description: &str,
 ) -> Result<(), Error> {
-    let mut file_content: String = String::from("#!/usr/bin/env sh\n");
-    file_content.push_str(execute_action.get_commands());
+    let mut file_content: String = get_shebang();
+    file_content.push_str(commands);
 
-    cache.add_new_script(shell_name, &file_content)?;
+    let tags: Vec<String> =
+        input_message("Tags for the script (comma separated, or leave it empty):")?
+            .split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+    let mut metadata: ScriptMetadata = ScriptMetadata::new(request, description, tags);
+    metadata.parameters = parameters;
+    if configurations.cache_lookup.use_embeddings {
+        metadata.embedding = embed_request(configurations, request);
+    }
+    cache.add_new_script(shell_name, &file_content, metadata)?;
     display_message(Level::Logging, "Shell had been saved to the cache.");
 
     Ok(())
@@ -0,0 +1,331 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Error, Result, anyhow};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agents::{command_json::LLMActionType, execution::ExecutionResult},
+    constants::YOU_HISTORY_DIRECTORY,
+    traits::{GlobalResourceInitialization, acquire_you_home_directory},
+};
+
+/// Every session of `you`, from the prompt to the commands that ran.
+///
+/// Each session is a JSON file under `~/.you/history`, named so that sessions
+/// sort by the time they started.
+#[derive(Debug, Clone)]
+pub struct History {
+    directory: PathBuf,
+}
+
+impl GlobalResourceInitialization for History {
+    fn initialize() -> Result<()> {
+        let you_history_directory: PathBuf =
+            acquire_you_home_directory()?.join(YOU_HISTORY_DIRECTORY);
+
+        if !you_history_directory.exists() {
+            fs::create_dir(&you_history_directory)?;
+        }
+
+        Ok(())
+    }
+
+    fn load() -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            directory: acquire_you_home_directory()?.join(YOU_HISTORY_DIRECTORY),
+        })
+    }
+}
+
+/// One run of `you`, or one conversation in interactive mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    /// RFC 3339 timestamp of when the session started
+    pub started_at: String,
+    pub working_directory: PathBuf,
+    /// The request in natural language. Empty until the user typed it in interactive mode
+    pub prompt: String,
+    /// In the order they happened
+    pub events: Vec<SessionEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEvent {
+    /// RFC 3339 timestamp
+    pub recorded_at: String,
+    #[serde(flatten)]
+    pub kind: SessionEventKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SessionEventKind {
+    /// A follow-up instruction, after the prompt
+    Instruction { text: String },
+    /// What the LLM answered with
+    Proposal { action: LLMActionType },
+    /// A proposal that never reached the user, because of the risk policy
+    Blocked { reasons: Vec<String> },
+    /// How the user answered a proposal, or a step of a plan
+    Decision { input: String },
+    Execution {
+        command: String,
+        /// `None` if the process was killed by a signal
+        exit_code: Option<i32>,
+        duration_milliseconds: u64,
+        /// The command ran in the sandbox and its changes were discarded
+        is_discarded: bool,
+    },
+}
+
+impl SessionEventKind {
+    pub fn execution(command: &str, result: &ExecutionResult, is_discarded: bool) -> Self {
+        Self::Execution {
+            command: command.to_string(),
+            exit_code: result.exit_code,
+            duration_milliseconds: result.duration.as_millis() as u64,
+            is_discarded,
+        }
+    }
+}
+
+impl SessionEvent {
+    /// One line for `you history show`.
+    pub fn describe(&self) -> String {
+        match &self.kind {
+            SessionEventKind::Instruction { text } => format!("instructed: {}", text.trim()),
+            SessionEventKind::Proposal { action } => format!("proposed: {}", action.describe()),
+            SessionEventKind::Blocked { reasons } => {
+                format!("blocked the proposal: {}", reasons.join("; "))
+            }
+            SessionEventKind::Decision { input } if input.is_empty() => {
+                "answered nothing, the input was closed".to_string()
+            }
+            SessionEventKind::Decision { input } => format!("answered: {}", input.trim()),
+            SessionEventKind::Execution {
+                command,
+                exit_code,
+                duration_milliseconds,
+                is_discarded,
+            } => {
+                let status: String = match exit_code {
+                    Some(exit_code) => format!("exited with code {}", exit_code),
+                    None => "was terminated by a signal".to_string(),
+                };
+
+                format!(
+                    "ran `{}`, which {} after {:.2}s{}",
+                    command,
+                    status,
+                    *duration_milliseconds as f64 / 1000.0,
+                    if *is_discarded {
+                        ", its changes were discarded"
+                    } else {
+                        ""
+                    }
+                )
+            }
+        }
+    }
+}
+
+impl Session {
+    /// The commands that ran successfully and were kept, in order, for re-running the session.
+    pub fn get_succeeded_commands(&self) -> Vec<&str> {
+        self.events
+            .iter()
+            .filter_map(|event| match &event.kind {
+                SessionEventKind::Execution {
+                    command,
+                    exit_code: Some(0),
+                    is_discarded: false,
+                    ..
+                } => Some(command.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A short summary of what was executed, for listing sessions.
+    pub fn get_status(&self) -> String {
+        let executions: Vec<&Option<i32>> = self
+            .events
+            .iter()
+            .filter_map(|event| match &event.kind {
+                SessionEventKind::Execution { exit_code, .. } => Some(exit_code),
+                _ => None,
+            })
+            .collect();
+
+        match executions.last() {
+            None => "nothing executed".to_string(),
+            Some(Some(exit_code)) => format!(
+                "{} command(s), the last exited with code {}",
+                executions.len(),
+                exit_code
+            ),
+            Some(None) => format!(
+                "{} command(s), the last was terminated by a signal",
+                executions.len()
+            ),
+        }
+    }
+
+    /// `started_at` in local time, for display.
+    pub fn get_start_time(&self) -> String {
+        DateTime::parse_from_rfc3339(&self.started_at)
+            .map(|started_at| {
+                started_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| self.started_at.clone())
+    }
+
+    /// Whether `query` appears, ignoring case, in the prompt, an instruction or a command.
+    pub fn matches(&self, query: &str) -> bool {
+        let query: String = query.to_lowercase();
+        let contains = |text: &str| text.to_lowercase().contains(&query);
+
+        contains(&self.prompt)
+            || self.events.iter().any(|event| match &event.kind {
+                SessionEventKind::Instruction { text } => contains(text),
+                SessionEventKind::Proposal { action } => action
+                    .get_commands()
+                    .iter()
+                    .any(|command| contains(command)),
+                SessionEventKind::Execution { command, .. } => contains(command),
+                _ => false,
+            })
+    }
+}
+
+impl History {
+    /// Every session, the most recent first.
+    pub fn list(&self) -> Result<Vec<Session>, Error> {
+        let mut sessions: Vec<Session> = Vec::new();
+        for id in self.list_ids()?.iter().rev() {
+            sessions.push(self.get(id)?);
+        }
+
+        Ok(sessions)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Session, Error> {
+        let path: PathBuf = self.directory.join(format!("{}.json", id));
+        if !path.is_file() {
+            return Err(anyhow!("No session {} is found in the history", id));
+        }
+
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    fn save(&self, session: &Session) -> Result<(), Error> {
+        fs::write(
+            self.directory.join(format!("{}.json", session.id)),
+            serde_json::to_string_pretty(session)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Session ids, oldest first.
+    fn list_ids(&self) -> Result<Vec<String>, Error> {
+        let mut ids: Vec<String> = Vec::new();
+        for file in fs::read_dir(&self.directory)? {
+            let path: PathBuf = file?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+                && let Some(id) = path.file_stem()
+            {
+                ids.push(id.to_string_lossy().to_string());
+            }
+        }
+        ids.sort();
+
+        Ok(ids)
+    }
+
+    /// Removes the oldest sessions beyond `max_sessions`.
+    fn prune(&self, max_sessions: usize) -> Result<(), Error> {
+        let ids: Vec<String> = self.list_ids()?;
+        for id in ids.iter().take(ids.len().saturating_sub(max_sessions)) {
+            fs::remove_file(self.directory.join(format!("{}.json", id)))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Records the session in progress.
+///
+/// Nothing is written until the first event, so that sessions in which nothing
+/// happened do not clutter the history. Every event is saved right away, so
+/// that an interrupted session is still recorded.
+pub struct SessionRecorder {
+    /// `None` if the history is disabled
+    history: Option<(History, usize)>,
+    session: Session,
+    is_saved: bool,
+}
+
+impl SessionRecorder {
+    /// Starts a session that is kept in `history`, along with the last `max_sessions` others.
+    pub fn new(history: Option<(History, usize)>, prompt: &str) -> Self {
+        let now: DateTime<Local> = Local::now();
+
+        Self {
+            history,
+            session: Session {
+                id: now.format("%Y%m%d%H%M%S%6f").to_string(),
+                started_at: now.to_rfc3339(),
+                working_directory: std::env::current_dir().unwrap_or_default(),
+                prompt: prompt.to_string(),
+                events: Vec::new(),
+            },
+            is_saved: false,
+        }
+    }
+
+    /// A session that is not recorded anywhere.
+    pub fn disabled() -> Self {
+        Self::new(None, "")
+    }
+
+    /// Records `kind`. In interactive mode, the first instruction becomes the prompt.
+    pub fn record(&mut self, kind: SessionEventKind) -> Result<(), Error> {
+        let Some((history, max_sessions)) = &self.history else {
+            return Ok(());
+        };
+
+        match kind {
+            SessionEventKind::Instruction { text } if self.session.prompt.is_empty() => {
+                self.session.prompt = text
+            }
+            kind => self.session.events.push(SessionEvent {
+                recorded_at: Local::now().to_rfc3339(),
+                kind,
+            }),
+        }
+
+        history.save(&self.session)?;
+        if !self.is_saved {
+            self.is_saved = true;
+            history.prune(*max_sessions)?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops recording, e.g. after the history could not be written.
+    pub fn stop(&mut self) {
+        self.history = None;
+    }
+}
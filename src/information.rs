@@ -2,7 +2,9 @@ use anyhow::Result;
 use chrono::Local;
 use sysinfo::System;
 
-use crate::{configurations::Configurations, traits::GlobalResourceInitialization};
+use crate::{
+    configurations::Configurations, executors::get_shell_name, traits::GlobalResourceInitialization,
+};
 
 pub struct ContextualInformation {
     system_information: String,
@@ -26,6 +28,7 @@ impl ContextualInformation {
     /// - System specs
     /// - Current time
     /// - Current directory structure
+    /// - Shell that commands run in
     /// - User configurations
     pub fn get_contextual_information(&self) -> Result<String> {
         let mut contextual_information: String = String::new();
@@ -35,16 +38,19 @@ impl ContextualInformation {
         contextual_information.push_str(&self.system_information);
         contextual_information.push_str("Current Working Directory: ");
         contextual_information.push_str(std::env::current_dir()?.to_str().unwrap());
-        contextual_information.push_str("\n");
+        contextual_information.push('\n');
         contextual_information.push_str("Current Working Directory Structure: ");
         contextual_information.push_str(&self.current_directory_structure);
-        contextual_information.push_str("\n");
+        contextual_information.push('\n');
         contextual_information.push_str("Current Date and Time: ");
         contextual_information.push_str(&self.current_time);
-        contextual_information.push_str("\n");
+        contextual_information.push('\n');
+        contextual_information.push_str("Shell: ");
+        contextual_information.push_str(get_shell_name());
+        contextual_information.push('\n');
         contextual_information.push_str("User preferred CLIs: ");
-        contextual_information.push_str(&format!("{}", &self.configurations.get_preferred_clis()));
-        contextual_information.push_str("\n");
+        contextual_information.push_str(&self.configurations.get_preferred_clis());
+        contextual_information.push('\n');
 
         Ok(contextual_information)
     }
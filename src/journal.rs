@@ -0,0 +1,485 @@
+use std::{
+    collections::HashSet,
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Error, Result, anyhow};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agents::execution::ExecutionResult,
+    configurations::JournalPolicy,
+    constants::YOU_JOURNAL_DIRECTORY,
+    traits::{GlobalResourceInitialization, acquire_you_home_directory},
+};
+
+const ENTRY_JSON: &str = "entry.json";
+/// Where the snapshotted file contents of an entry are kept
+const FILES_DIRECTORY: &str = "files";
+/// Directories with more entries than this are not snapshotted
+const MAX_SNAPSHOTS_PER_ENTRY: usize = 10_000;
+
+/// Commands that ran, with snapshots of what they were expected to change.
+///
+/// Each entry is a directory under `~/.you/journal`, named so that entries sort
+/// by the time they were recorded.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    directory: PathBuf,
+}
+
+impl GlobalResourceInitialization for Journal {
+    fn initialize() -> Result<()> {
+        let you_journal_directory: PathBuf =
+            acquire_you_home_directory()?.join(YOU_JOURNAL_DIRECTORY);
+
+        if !you_journal_directory.exists() {
+            fs::create_dir(&you_journal_directory)?;
+        }
+
+        Ok(())
+    }
+
+    fn load() -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            directory: acquire_you_home_directory()?.join(YOU_JOURNAL_DIRECTORY),
+        })
+    }
+}
+
+/// One command in the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: String,
+    pub command: String,
+    pub working_directory: PathBuf,
+    /// RFC 3339 timestamp of when the snapshots were taken
+    pub recorded_at: String,
+    /// `None` until the command finished, or if it was killed by a signal
+    pub exit_code: Option<i32>,
+    /// In the order they were taken, directories before their contents
+    pub snapshots: Vec<Snapshot>,
+    pub is_undone: bool,
+}
+
+/// The state of a path before the command ran.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub state: SnapshotState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SnapshotState {
+    /// Nothing was there, so undoing removes whatever the command created
+    Absent,
+    Directory {
+        mode: Option<u32>,
+    },
+    File {
+        /// Name of the copy in the entry's `files` directory
+        blob: String,
+        mode: Option<u32>,
+    },
+    Symlink {
+        target: PathBuf,
+    },
+    /// The path could not be snapshotted, e.g. because it is too large
+    Skipped {
+        reason: String,
+    },
+}
+
+impl Snapshot {
+    /// What undoing does to the path, for showing it to the user.
+    pub fn describe(&self) -> String {
+        match &self.state {
+            SnapshotState::Absent => format!("remove {}", self.path.display()),
+            SnapshotState::Skipped { reason } => {
+                format!("cannot restore {} ({})", self.path.display(), reason)
+            }
+            _ => format!("restore {}", self.path.display()),
+        }
+    }
+}
+
+impl Journal {
+    /// Snapshots `paths` before `command` runs, and records the command.
+    ///
+    /// Wildcards in the last component of a path, like `*.log`, are expanded.
+    /// Directories are snapshotted with their contents.
+    pub fn record(
+        &self,
+        command: &str,
+        paths: &[PathBuf],
+        policy: &JournalPolicy,
+    ) -> Result<JournalEntry, Error> {
+        let now: chrono::DateTime<Local> = Local::now();
+        let mut entry: JournalEntry = JournalEntry {
+            id: now.format("%Y%m%d%H%M%S%6f").to_string(),
+            command: command.to_string(),
+            working_directory: std::env::current_dir()?,
+            recorded_at: now.to_rfc3339(),
+            exit_code: None,
+            snapshots: Vec::new(),
+            is_undone: false,
+        };
+
+        let entry_directory: PathBuf = self.directory.join(&entry.id);
+        fs::create_dir_all(entry_directory.join(FILES_DIRECTORY))?;
+
+        let mut snapshotter: Snapshotter = Snapshotter {
+            files_directory: entry_directory.join(FILES_DIRECTORY),
+            remaining_bytes: policy.max_snapshot_bytes,
+            snapshots: Vec::new(),
+            snapshotted_paths: HashSet::new(),
+        };
+        for path in paths.iter().flat_map(|path| expand_wildcards(path)) {
+            snapshotter.take(&path)?;
+        }
+        entry.snapshots = snapshotter.snapshots;
+
+        self.save(&entry)?;
+        self.prune(policy.max_entries)?;
+
+        Ok(entry)
+    }
+
+    /// Records how the command of `entry` went.
+    pub fn finish(&self, entry: &mut JournalEntry, result: &ExecutionResult) -> Result<(), Error> {
+        entry.exit_code = result.exit_code;
+        self.save(entry)
+    }
+
+    /// The most recent entry that has not been undone yet.
+    pub fn get_last_entry(&self) -> Result<Option<JournalEntry>, Error> {
+        for id in self.list_ids()?.iter().rev() {
+            let entry: JournalEntry = serde_json::from_str(&fs::read_to_string(
+                self.directory.join(id).join(ENTRY_JSON),
+            )?)?;
+
+            if !entry.is_undone {
+                return Ok(Some(entry));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Puts every path of `entry` back the way it was before the command ran.
+    ///
+    /// Paths that cannot be restored do not stop the others from being
+    /// restored. They are returned, with the reason.
+    pub fn undo(&self, entry: &mut JournalEntry) -> Result<Vec<String>, Error> {
+        let files_directory: PathBuf = self.directory.join(&entry.id).join(FILES_DIRECTORY);
+        let recorded_paths: HashSet<&Path> = entry
+            .snapshots
+            .iter()
+            .map(|snapshot| snapshot.path.as_path())
+            .collect();
+
+        let mut failures: Vec<String> = Vec::new();
+        for snapshot in &entry.snapshots {
+            if let Err(error) = restore(snapshot, &files_directory, &recorded_paths) {
+                failures.push(format!("{}: {}", snapshot.path.display(), error));
+            }
+        }
+
+        // Only now, in case a directory was read-only
+        for snapshot in entry.snapshots.iter().rev() {
+            if let SnapshotState::Directory { mode: Some(mode) } = snapshot.state
+                && let Err(error) = set_mode(&snapshot.path, mode)
+            {
+                failures.push(format!("{}: {}", snapshot.path.display(), error));
+            }
+        }
+
+        entry.is_undone = true;
+        self.save(entry)?;
+
+        Ok(failures)
+    }
+
+    fn save(&self, entry: &JournalEntry) -> Result<(), Error> {
+        fs::write(
+            self.directory.join(&entry.id).join(ENTRY_JSON),
+            serde_json::to_string_pretty(entry)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Entry ids, oldest first.
+    fn list_ids(&self) -> Result<Vec<String>, Error> {
+        let mut ids: Vec<String> = Vec::new();
+        for directory in fs::read_dir(&self.directory)? {
+            let directory: fs::DirEntry = directory?;
+            if directory.path().join(ENTRY_JSON).is_file() {
+                ids.push(directory.file_name().to_string_lossy().to_string());
+            }
+        }
+        ids.sort();
+
+        Ok(ids)
+    }
+
+    /// Removes the oldest entries beyond `max_entries`.
+    fn prune(&self, max_entries: usize) -> Result<(), Error> {
+        let ids: Vec<String> = self.list_ids()?;
+        for id in ids.iter().take(ids.len().saturating_sub(max_entries)) {
+            fs::remove_dir_all(self.directory.join(id))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Takes the snapshots of one entry, within its size budget.
+struct Snapshotter {
+    files_directory: PathBuf,
+    remaining_bytes: u64,
+    snapshots: Vec<Snapshot>,
+    /// Paths already snapshotted, directly or as part of a directory
+    snapshotted_paths: HashSet<PathBuf>,
+}
+
+impl Snapshotter {
+    fn take(&mut self, path: &Path) -> Result<(), Error> {
+        if !self.snapshotted_paths.insert(path.to_path_buf()) {
+            return Ok(());
+        }
+
+        if self.snapshots.len() >= MAX_SNAPSHOTS_PER_ENTRY {
+            return self.skip(path, "too many files to snapshot");
+        }
+
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            self.push(path, SnapshotState::Absent);
+            return Ok(());
+        };
+
+        if metadata.is_symlink() {
+            let target: PathBuf = fs::read_link(path)?;
+            self.push(path, SnapshotState::Symlink { target });
+        } else if metadata.is_dir() {
+            let Ok(children) = fs::read_dir(path) else {
+                return self.skip(path, "the directory cannot be read");
+            };
+
+            self.push(
+                path,
+                SnapshotState::Directory {
+                    mode: get_mode(&metadata),
+                },
+            );
+            for child in children {
+                self.take(&child?.path())?;
+            }
+        } else if metadata.is_file() {
+            if metadata.len() > self.remaining_bytes {
+                return self.skip(path, "larger than the snapshot size limit");
+            }
+
+            let blob: String = self.snapshots.len().to_string();
+            if fs::copy(path, self.files_directory.join(&blob)).is_err() {
+                return self.skip(path, "the file cannot be read");
+            }
+            self.remaining_bytes -= metadata.len();
+
+            self.push(
+                path,
+                SnapshotState::File {
+                    blob,
+                    mode: get_mode(&metadata),
+                },
+            );
+        } else {
+            return self.skip(path, "not a regular file");
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, path: &Path, state: SnapshotState) {
+        self.snapshots.push(Snapshot {
+            path: path.to_path_buf(),
+            state,
+        });
+    }
+
+    fn skip(&mut self, path: &Path, reason: &str) -> Result<(), Error> {
+        self.push(
+            path,
+            SnapshotState::Skipped {
+                reason: reason.to_string(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn restore(
+    snapshot: &Snapshot,
+    files_directory: &Path,
+    recorded_paths: &HashSet<&Path>,
+) -> Result<(), Error> {
+    let path: &Path = &snapshot.path;
+    let existing: Option<Metadata> = fs::symlink_metadata(path).ok();
+
+    match &snapshot.state {
+        SnapshotState::Absent => remove_entry(path)?,
+        SnapshotState::Directory { .. } => {
+            if existing.is_some_and(|existing| !existing.is_dir()) {
+                remove_entry(path)?;
+            }
+            fs::create_dir_all(path)?;
+
+            // Whatever the command added to the directory goes away
+            for child in fs::read_dir(path)? {
+                let child_path: PathBuf = child?.path();
+                if !recorded_paths.contains(child_path.as_path()) {
+                    remove_entry(&child_path)?;
+                }
+            }
+        }
+        SnapshotState::File { blob, mode } => {
+            // Never write through a symlink, nor into a directory or a read-only file
+            if existing.is_some() {
+                remove_entry(path)?;
+            }
+            fs::copy(files_directory.join(blob), path)?;
+
+            if let Some(mode) = mode {
+                set_mode(path, *mode)?;
+            }
+        }
+        SnapshotState::Symlink { target } => {
+            remove_entry(path)?;
+            create_symlink(target, path)?;
+        }
+        SnapshotState::Skipped { reason } => return Err(anyhow!("{}", reason)),
+    }
+
+    Ok(())
+}
+
+/// The paths matching `*` and `?` in the last component of `path`, or `path`
+/// itself if it has no wildcards.
+fn expand_wildcards(path: &Path) -> Vec<PathBuf> {
+    let pattern: String = match path.file_name() {
+        Some(file_name) => file_name.to_string_lossy().to_string(),
+        None => return vec![path.to_path_buf()],
+    };
+
+    if !pattern.contains(['*', '?']) {
+        return vec![path.to_path_buf()];
+    }
+
+    let Some(Ok(children)) = path.parent().map(fs::read_dir) else {
+        return Vec::new();
+    };
+
+    let mut matches: Vec<PathBuf> = children
+        .filter_map(|child| child.ok())
+        .filter(|child| {
+            let name: String = child.file_name().to_string_lossy().to_string();
+            // Like the shell, wildcards do not match hidden files
+            !name.starts_with('.') && matches_wildcards(&pattern, &name)
+        })
+        .map(|child| child.path())
+        .collect();
+    matches.sort();
+
+    matches
+}
+
+fn matches_wildcards(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Where the last `*` was, and the position in `name` it has matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut pattern_index, mut name_index): (usize, usize) = (0, 0);
+
+    while name_index < name.len() {
+        match pattern.get(pattern_index) {
+            Some('*') => {
+                backtrack = Some((pattern_index, name_index));
+                pattern_index += 1;
+            }
+            Some(character) if *character == '?' || *character == name[name_index] => {
+                pattern_index += 1;
+                name_index += 1;
+            }
+            _ => match backtrack {
+                Some((star_index, matched_until)) => {
+                    backtrack = Some((star_index, matched_until + 1));
+                    pattern_index = star_index + 1;
+                    name_index = matched_until + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[pattern_index..]
+        .iter()
+        .all(|character| *character == '*')
+}
+
+fn remove_entry(path: &Path) -> Result<(), Error> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path)?,
+        Ok(_) => fs::remove_file(path)?,
+        // Already gone
+        Err(_) => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn get_mode(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Some(metadata.permissions().mode())
+}
+
+#[cfg(not(unix))]
+fn get_mode(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> Result<(), Error> {
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_symlink(_target: &Path, link: &Path) -> Result<(), Error> {
+    Err(anyhow!(
+        "Cannot create the symlink {} on this platform",
+        link.display()
+    ))
+}
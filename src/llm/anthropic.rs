@@ -0,0 +1,323 @@
+use anyhow::{Error, Result, anyhow};
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Runtime;
+
+use crate::configurations::LLMConfigurations;
+
+use super::{
+    LLMBackend, StreamHandler, ToolCall, ToolCallingReply, ToolDefinition, drain_lines,
+    flatten_message, is_transient_status, request_failed, require_setting, resolve_setting,
+};
+
+const DEFAULT_ANTHROPIC_API_BASE: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+#[derive(Debug, Serialize)]
+struct AnthropicMessage {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicMessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicContentBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    #[serde(default)]
+    text: String,
+    /// Set on `tool_use` blocks
+    #[serde(default)]
+    name: String,
+    /// Set on `tool_use` blocks
+    #[serde(default)]
+    input: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicError {
+    message: String,
+}
+
+/// One server-sent event of a streamed reply. Only text deltas and errors
+/// are of interest.
+#[derive(Debug, Deserialize)]
+struct AnthropicStreamEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    delta: Option<AnthropicStreamDelta>,
+    error: Option<AnthropicError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicStreamDelta {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicMessagesResponse {
+    #[serde(default)]
+    content: Vec<AnthropicContentBlock>,
+    error: Option<AnthropicError>,
+}
+
+/// A backend for Anthropic's `/v1/messages` endpoint.
+#[derive(Debug, Clone)]
+pub struct AnthropicBackend {
+    api_base: String,
+    api_key: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+impl AnthropicBackend {
+    pub fn new(llm_configurations: &LLMConfigurations) -> Result<Self, Error> {
+        let api_base: String =
+            resolve_setting(&llm_configurations.api_base, &["YOU_ANTHROPIC_API_BASE"])
+                .unwrap_or_else(|| DEFAULT_ANTHROPIC_API_BASE.to_string());
+        let api_key: String = require_setting(
+            &llm_configurations.api_key,
+            "api_key",
+            &["YOU_ANTHROPIC_API_KEY", "ANTHROPIC_API_KEY"],
+        )?;
+        let model: String =
+            require_setting(&llm_configurations.model, "model", &["YOU_ANTHROPIC_MODEL"])?;
+
+        Ok(Self {
+            api_base: api_base.trim_end_matches('/').to_string(),
+            api_key,
+            model,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    fn build_request(
+        &self,
+        context: &[ChatCompletionRequestMessage],
+        stream: bool,
+    ) -> AnthropicMessagesRequest<'_> {
+        // Anthropic takes the system prompt separately from the conversation
+        let mut system_prompts: Vec<String> = Vec::new();
+        let mut messages: Vec<AnthropicMessage> = Vec::new();
+        for (role, content) in context.iter().filter_map(flatten_message) {
+            if role == "system" {
+                system_prompts.push(content);
+                continue;
+            }
+
+            messages.push(AnthropicMessage { role, content });
+        }
+
+        AnthropicMessagesRequest {
+            model: &self.model,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            system: if system_prompts.is_empty() {
+                None
+            } else {
+                Some(system_prompts.join("\n\n"))
+            },
+            messages,
+            stream,
+            tools: Vec::new(),
+            tool_choice: None,
+        }
+    }
+
+    /// Sends `request`, and fails with the error in the body if Anthropic rejects it.
+    ///
+    /// The body of a rejected request is JSON even when a stream was asked for, so it is
+    /// read here, before anything tries to read the events of a stream.
+    async fn send(
+        &self,
+        request: &AnthropicMessagesRequest<'_>,
+    ) -> Result<reqwest::Response, Error> {
+        let response: reqwest::Response = self
+            .client
+            .post(format!("{}/v1/messages", self.api_base))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| request_failed(format!("Failed to reach Anthropic: {}", e)))?;
+
+        let status: reqwest::StatusCode = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body: String = response
+            .text()
+            .await
+            .map_err(|e| anyhow!("Failed to read Anthropic's response: {}", e))?;
+        let message: String = serde_json::from_str::<AnthropicMessagesResponse>(&body)
+            .ok()
+            .and_then(|response| response.error)
+            .map(|error| error.message)
+            .unwrap_or(body);
+
+        let message: String = format!("Anthropic returned an error ({}): {}", status, message);
+        if is_transient_status(status) {
+            return Err(request_failed(message));
+        }
+
+        Err(anyhow!(message))
+    }
+}
+
+impl LLMBackend for AnthropicBackend {
+    fn generate_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<String, Error> {
+        let request: AnthropicMessagesRequest = self.build_request(&context, false);
+
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        runtime.block_on(async {
+            let response: AnthropicMessagesResponse = self
+                .send(&request)
+                .await?
+                .json()
+                .await
+                .map_err(|e| anyhow!("Failed to read Anthropic's response: {}", e))?;
+
+            if let Some(error) = response.error {
+                return Err(anyhow!("Anthropic returned an error: {}", error.message));
+            }
+
+            let content: String = response
+                .content
+                .into_iter()
+                .filter(|block| block.block_type == "text")
+                .map(|block| block.text)
+                .collect();
+
+            if content.is_empty() {
+                return Err(anyhow!("No response is retrieved from the LLM"));
+            }
+
+            Ok(content)
+        })
+    }
+
+    fn generate_with_tools(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<ToolCallingReply, Error> {
+        let mut request: AnthropicMessagesRequest = self.build_request(&context, false);
+        request.tools = tools
+            .iter()
+            .map(|tool| AnthropicTool {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                input_schema: tool.parameters.clone(),
+            })
+            .collect();
+        // Require a tool call rather than prose
+        request.tool_choice = Some(serde_json::json!({ "type": "any" }));
+
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        let response: AnthropicMessagesResponse = runtime.block_on(async {
+            self.send(&request)
+                .await?
+                .json()
+                .await
+                .map_err(|e| anyhow!("Failed to read Anthropic's response: {}", e))
+        })?;
+
+        if let Some(error) = response.error {
+            return Err(anyhow!("Anthropic returned an error: {}", error.message));
+        }
+
+        let mut text: String = String::new();
+        for block in response.content {
+            match block.block_type.as_str() {
+                "tool_use" => {
+                    return Ok(ToolCallingReply::ToolCall(ToolCall {
+                        name: block.name,
+                        arguments: block.input.unwrap_or_else(|| serde_json::json!({})),
+                    }));
+                }
+                "text" => text.push_str(&block.text),
+                _ => {}
+            }
+        }
+
+        if text.is_empty() {
+            return Err(anyhow!("No response is retrieved from the LLM"));
+        }
+
+        Ok(ToolCallingReply::Text(text))
+    }
+
+    fn generate_stream_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        stream_handler: &mut dyn StreamHandler,
+    ) -> Result<String, Error> {
+        let request: AnthropicMessagesRequest = self.build_request(&context, true);
+
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        runtime.block_on(async {
+            let mut response: reqwest::Response = self.send(&request).await?;
+
+            // The reply arrives as server-sent events, each carried by a `data:` line
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut content: String = String::new();
+            while let Some(bytes) = response.chunk().await.map_err(|e| {
+                request_failed(format!("Failed to read Anthropic's response: {}", e))
+            })? {
+                buffer.extend_from_slice(&bytes);
+
+                for line in drain_lines(&mut buffer) {
+                    let Some(data) = line.strip_prefix("data:") else {
+                        continue;
+                    };
+
+                    let event: AnthropicStreamEvent = serde_json::from_str(data.trim())?;
+                    if let Some(error) = event.error {
+                        return Err(anyhow!("Anthropic returned an error: {}", error.message));
+                    }
+
+                    if event.event_type != "content_block_delta" {
+                        continue;
+                    }
+
+                    if let Some(chunk) = event.delta.and_then(|delta| delta.text) {
+                        stream_handler.on_chunk(&chunk);
+                        content.push_str(&chunk);
+                    }
+                }
+            }
+
+            if content.is_empty() {
+                return Err(anyhow!("No response is retrieved from the LLM"));
+            }
+
+            Ok(content)
+        })
+    }
+}
@@ -0,0 +1,440 @@
+mod anthropic;
+mod ollama;
+mod openai;
+mod scripted;
+
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::{Error, Result};
+use async_openai::types::{
+    ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestAssistantMessageContent,
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestSystemMessageContent, ChatCompletionRequestSystemMessageContentPart,
+    ChatCompletionRequestUserMessageArgs, ChatCompletionRequestUserMessageContent,
+};
+use surfing::extract_json_to_string;
+
+use crate::configurations::{Configurations, LLMProvider, RetryPolicy};
+use crate::errors::YouError;
+
+pub use anthropic::AnthropicBackend;
+pub use ollama::OllamaBackend;
+pub use openai::OpenAIBackend;
+pub use scripted::ScriptedBackend;
+
+/// Points to a JSONL script of canned replies. When set, it takes precedence
+/// over the configured provider so that tests never reach a live endpoint.
+pub const LLM_SCRIPT_ENVIRONMENT_VARIABLE: &str = "YOU_LLM_SCRIPT";
+
+/// Receives a reply while the model is still generating it.
+pub trait StreamHandler {
+    /// Called with each new piece of the reply, in order.
+    fn on_chunk(&mut self, chunk: &str);
+
+    /// Called before the reply is generated again, e.g. after a malformed
+    /// reply. Everything received so far should be discarded.
+    fn on_retry(&mut self) {}
+}
+
+/// A function the model may call instead of replying with JSON in prose.
+#[derive(Debug, Clone)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments
+    pub parameters: serde_json::Value,
+}
+
+/// A call the model made to one of the offered tools.
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub name: String,
+    /// The arguments, as a JSON object
+    pub arguments: serde_json::Value,
+}
+
+/// What the model answered when it was offered tools.
+#[derive(Debug, Clone)]
+pub enum ToolCallingReply {
+    ToolCall(ToolCall),
+    /// The model replied in prose, or the backend does not support tools
+    Text(String),
+}
+
+/// A JSON schema that the model's replies must follow.
+///
+/// Sent along with every request by backends that support structured output.
+#[derive(Debug, Clone)]
+pub struct ResponseFormat {
+    /// Identifies the schema to the endpoint
+    pub name: String,
+    pub schema: serde_json::Value,
+}
+
+/// A chat model that `you` can send its conversation context to.
+///
+/// Implementations only need to turn the context into a reply. Extracting
+/// JSON from the reply is shared by all backends.
+pub trait LLMBackend: Debug + Send + Sync {
+    /// Sends the context to the model and returns the raw text of its reply.
+    fn generate_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<String, Error>;
+
+    /// Same as `generate_with_context`, but hands the reply to `stream_handler`
+    /// piece by piece as it arrives.
+    ///
+    /// Backends without a streaming API deliver the whole reply as one piece.
+    fn generate_stream_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        stream_handler: &mut dyn StreamHandler,
+    ) -> Result<String, Error> {
+        let content: String = self.generate_with_context(context)?;
+        stream_handler.on_chunk(&content);
+
+        Ok(content)
+    }
+
+    /// Sends the context to the model along with tools it should call.
+    ///
+    /// Backends without native tool calling, or with it turned off, reply in
+    /// prose. So do backends that found out the model does not support tools.
+    fn generate_with_tools(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        _tools: &[ToolDefinition],
+    ) -> Result<ToolCallingReply, Error> {
+        Ok(ToolCallingReply::Text(self.generate_with_context(context)?))
+    }
+
+    /// Turns each of `texts` into an embedding, for comparing them by meaning.
+    ///
+    /// Backends without an embeddings API fail.
+    fn embed(&self, _texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        Err(anyhow!(
+            "The configured LLM backend does not support embeddings"
+        ))
+    }
+
+    /// Sends the context to the model and returns the JSON found in its reply.
+    ///
+    /// Fails with `YouError::NoJSONInResponse` if the reply contains no JSON.
+    fn generate_json_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<String, Error> {
+        extract_json(self.generate_with_context(context)?)
+    }
+
+    /// Streaming counterpart of `generate_json_with_context`.
+    fn generate_json_stream_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        stream_handler: &mut dyn StreamHandler,
+    ) -> Result<String, Error> {
+        extract_json(self.generate_stream_with_context(context, stream_handler)?)
+    }
+}
+
+fn extract_json(content: String) -> Result<String, Error> {
+    match extract_json_to_string(&content) {
+        Ok(json) if !json.trim().is_empty() => Ok(json),
+        _ => Err(YouError::NoJSONInResponse { response: content }.into()),
+    }
+}
+
+/// Whether a request answered with `status` is worth retrying, e.g. when the
+/// endpoint is rate limited or overloaded.
+fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The error of a request that did not go through, which is retried with backoff.
+fn request_failed(message: String) -> Error {
+    YouError::LLMRequestFailed { message }.into()
+}
+
+/// Split the complete lines off the front of `buffer`, leaving any partial
+/// line behind. Used to parse line-delimited streaming responses.
+fn drain_lines(buffer: &mut Vec<u8>) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    while let Some(position) = buffer.iter().position(|byte| *byte == b'\n') {
+        let line: Vec<u8> = buffer.drain(..=position).collect();
+        lines.push(String::from_utf8_lossy(&line).trim_end().to_string());
+    }
+
+    lines
+}
+
+/// Build the backend selected by the `llm` section of `configurations.json`.
+///
+/// `response_format` is the schema of the replies the caller expects. It is
+/// only sent when `llm.enable_structured_output` is set, and only by backends
+/// that support it; the others rely on the prompt alone.
+pub fn create_llm_backend(
+    configurations: &Configurations,
+    response_format: Option<ResponseFormat>,
+) -> Result<Arc<dyn LLMBackend>, Error> {
+    let llm_configurations = &configurations.llm;
+    let response_format: Option<ResponseFormat> =
+        response_format.filter(|_| llm_configurations.enable_structured_output);
+
+    if let Ok(script_path) = std::env::var(LLM_SCRIPT_ENVIRONMENT_VARIABLE) {
+        return Ok(Arc::new(ScriptedBackend::new(&PathBuf::from(script_path))?));
+    }
+
+    Ok(match llm_configurations.provider {
+        LLMProvider::OpenAI => Arc::new(OpenAIBackend::new(llm_configurations, response_format)?),
+        LLMProvider::Ollama => Arc::new(OllamaBackend::new(llm_configurations, response_format)?),
+        LLMProvider::Anthropic => Arc::new(AnthropicBackend::new(llm_configurations)?),
+        LLMProvider::Scripted => {
+            let script_path: &PathBuf = llm_configurations.script.as_ref().ok_or_else(|| {
+                YouError::InvalidConfiguration {
+                    reason: "`llm.script` must point to a JSONL file for the scripted provider"
+                        .to_string(),
+                }
+            })?;
+            Arc::new(ScriptedBackend::new(script_path)?)
+        }
+    })
+}
+
+/// Resolve a setting from the configuration file first, then from the
+/// environment variables in the given order.
+fn resolve_setting(configured: &Option<String>, environment_variables: &[&str]) -> Option<String> {
+    if let Some(value) = configured {
+        return Some(value.clone());
+    }
+
+    environment_variables
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+}
+
+/// Same as `resolve_setting`, but fails with a hint when nothing is set.
+fn require_setting(
+    configured: &Option<String>,
+    setting_name: &str,
+    environment_variables: &[&str],
+) -> Result<String, Error> {
+    resolve_setting(configured, environment_variables).ok_or_else(|| {
+        YouError::InvalidConfiguration {
+            reason: format!(
+                "`llm.{}` is not set in configurations.json and none of {} is set",
+                setting_name,
+                environment_variables.join(", ")
+            ),
+        }
+        .into()
+    })
+}
+
+/// Flatten a message into its role and plain text content, which is all the
+/// non-OpenAI backends understand. Messages that are not system, user or
+/// assistant messages are skipped.
+fn flatten_message(message: &ChatCompletionRequestMessage) -> Option<(&'static str, String)> {
+    match message {
+        ChatCompletionRequestMessage::System(message) => match &message.content {
+            ChatCompletionRequestSystemMessageContent::Text(text) => Some(("system", text.clone())),
+            ChatCompletionRequestSystemMessageContent::Array(parts) => Some((
+                "system",
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        ChatCompletionRequestSystemMessageContentPart::Text(text) => {
+                            text.text.clone()
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            )),
+        },
+        ChatCompletionRequestMessage::User(message) => match &message.content {
+            ChatCompletionRequestUserMessageContent::Text(text) => Some(("user", text.clone())),
+            ChatCompletionRequestUserMessageContent::Array(_) => None,
+        },
+        ChatCompletionRequestMessage::Assistant(message) => match &message.content {
+            Some(ChatCompletionRequestAssistantMessageContent::Text(text)) => {
+                Some(("assistant", text.clone()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A context for storing messages.
+pub trait Context {
+    fn add(&mut self, role: async_openai::types::Role, content: String) -> Result<(), Error> {
+        let context: &mut Vec<ChatCompletionRequestMessage> = self.access_context();
+
+        match role {
+            async_openai::types::Role::User => context.push(
+                ChatCompletionRequestUserMessageArgs::default()
+                    .content(content)
+                    .build()?
+                    .into(),
+            ),
+            async_openai::types::Role::System => context.push(
+                ChatCompletionRequestSystemMessageArgs::default()
+                    .content(content)
+                    .build()?
+                    .into(),
+            ),
+            async_openai::types::Role::Assistant => context.push(
+                ChatCompletionRequestAssistantMessageArgs::default()
+                    .content(content)
+                    .build()?
+                    .into(),
+            ),
+            _ => return Err(anyhow!("Invalid role")),
+        }
+
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    fn clear(&mut self) -> Result<(), Error> {
+        let context: &mut Vec<ChatCompletionRequestMessage> = self.access_context();
+        context.clear();
+
+        Ok(())
+    }
+
+    /// Acquire a mutable reference to the context
+    fn get_context(&self) -> &Vec<ChatCompletionRequestMessage>;
+
+    /// Acquire an immutable reference to the context
+    fn access_context(&mut self) -> &mut Vec<ChatCompletionRequestMessage>;
+}
+
+/// A trait for converting natural language to JSON.
+///
+/// This trait provides a method to convert natural language input into a JSON representation
+/// using a large language model (LLM) backend. It requires the implementor to also implement the `Context`
+/// trait, which manages the context of messages exchanged with the LLM.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use anyhow::Result;
+/// use async_openai::types::{ChatCompletionRequestMessage, Role};
+/// use crate::llm::{LLMBackend, Context, FromNaturalLanguageToJSON, create_llm_backend};
+///
+/// struct MyAgent {
+///     llm: Arc<dyn LLMBackend>,
+///     retry_policy: RetryPolicy,
+///     context: Vec<ChatCompletionRequestMessage>,
+/// }
+///
+/// impl Context for MyAgent {
+///     fn get_context(&self) -> &Vec<ChatCompletionRequestMessage> {
+///         &self.context
+///     }
+///
+///     fn access_context(&mut self) -> &mut Vec<ChatCompletionRequestMessage> {
+///         &mut self.context
+///     }
+/// }
+///
+/// impl FromNaturalLanguageToJSON for MyAgent {
+///     fn get_llm(&self) -> &dyn LLMBackend {
+///         self.llm.as_ref()
+///     }
+///
+///     fn get_retry_policy(&self) -> &RetryPolicy {
+///         &self.retry_policy
+///     }
+/// }
+///
+/// fn main() -> Result<()> {
+///     let configurations = Configurations::load()?;
+///     let llm = create_llm_backend(&configurations, None)?;
+///     let mut agent = MyAgent {
+///         llm,
+///         retry_policy: configurations.retry_policy.clone(),
+///         context: Vec::new(),
+///     };
+///
+///     agent.add(Role::User, "Translate this command to JSON.".to_string())?;
+///     let json_response = agent.from_natural_language_to_json()?;
+///     println!("Generated JSON: {}", json_response);
+///
+///     Ok(())
+/// }
+/// ```
+pub trait FromNaturalLanguageToJSON: Context {
+    /// Returns a reference to the LLM backend used for generating JSON.
+    fn get_llm(&self) -> &dyn LLMBackend;
+
+    /// Returns how failed generations should be retried.
+    fn get_retry_policy(&self) -> &RetryPolicy;
+
+    /// Returns the tools offered to backends with native tool calling.
+    ///
+    /// Agents without tools keep using the JSON-in-prose protocol only.
+    fn get_tools(&self) -> Vec<ToolDefinition> {
+        Vec::new()
+    }
+
+    /// Converts a tool call into the JSON the prose protocol would have
+    /// produced, so both protocols are parsed the same way.
+    fn tool_call_to_json(&self, tool_call: &ToolCall) -> Result<String, Error> {
+        Err(YouError::InvalidToolCall {
+            name: tool_call.name.clone(),
+            reason: "this agent does not offer tools".to_string(),
+        }
+        .into())
+    }
+
+    /// Converts the current context of natural language messages into a JSON representation.
+    ///
+    /// This method sends the current context to the LLM and retrieves a JSON-formatted response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the LLM fails to generate a response or if the response contains no
+    /// JSON. The latter is a `YouError::NoJSONInResponse`.
+    #[allow(clippy::wrong_self_convention)]
+    fn from_natural_language_to_json(&mut self) -> Result<String, Error> {
+        let tools: Vec<ToolDefinition> = self.get_tools();
+        if tools.is_empty() {
+            return self
+                .get_llm()
+                .generate_json_with_context(self.get_context().clone());
+        }
+
+        match self
+            .get_llm()
+            .generate_with_tools(self.get_context().clone(), &tools)?
+        {
+            ToolCallingReply::ToolCall(tool_call) => self.tool_call_to_json(&tool_call),
+            ToolCallingReply::Text(content) => extract_json(content),
+        }
+    }
+
+    /// Same as `from_natural_language_to_json`, but streams the reply to
+    /// `stream_handler` while it is being generated.
+    #[allow(clippy::wrong_self_convention)]
+    fn from_natural_language_to_json_streamed(
+        &mut self,
+        stream_handler: &mut dyn StreamHandler,
+    ) -> Result<String, Error> {
+        // Tool calls are not streamed, so the JSON is handed over in one piece
+        if !self.get_tools().is_empty() {
+            let json: String = self.from_natural_language_to_json()?;
+            stream_handler.on_chunk(&json);
+            return Ok(json);
+        }
+
+        self.get_llm()
+            .generate_json_stream_with_context(self.get_context().clone(), stream_handler)
+    }
+}
@@ -0,0 +1,355 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Error, Result, anyhow};
+use async_openai::types::ChatCompletionRequestMessage;
+use cchain::display_control::Level;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Runtime;
+
+use crate::configurations::LLMConfigurations;
+use crate::output::display_message;
+
+use super::{
+    LLMBackend, ResponseFormat, StreamHandler, ToolCall, ToolCallingReply, ToolDefinition,
+    drain_lines, flatten_message, is_transient_status, request_failed, require_setting,
+    resolve_setting,
+};
+
+const DEFAULT_OLLAMA_API_BASE: &str = "http://localhost:11434";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunction {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parameters: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    arguments: Option<serde_json::Value>,
+}
+
+/// Ollama uses the same shape for offering a tool and for calling it.
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    tool_type: Option<String>,
+    function: OllamaFunction,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OllamaToolCall>,
+    /// JSON schema that constrains the reply
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaEmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<Vec<f32>>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    message: Option<OllamaMessage>,
+    error: Option<String>,
+}
+
+impl OllamaChatResponse {
+    /// The text of the reply, or the error Ollama reported instead.
+    fn into_content(self) -> Result<Option<String>, Error> {
+        if let Some(error) = self.error {
+            return Err(anyhow!("Ollama returned an error: {}", error));
+        }
+
+        Ok(self.message.map(|message| message.content))
+    }
+}
+
+/// Parses one line of a streamed reply, and hands its text to `stream_handler`.
+fn receive_line(
+    line: &str,
+    stream_handler: &mut dyn StreamHandler,
+    content: &mut String,
+) -> Result<(), Error> {
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    let partial_response: OllamaChatResponse = serde_json::from_str(line)?;
+    if let Some(chunk) = partial_response.into_content()? {
+        stream_handler.on_chunk(&chunk);
+        content.push_str(&chunk);
+    }
+
+    Ok(())
+}
+
+/// A backend for Ollama's native `/api/chat` endpoint.
+#[derive(Debug)]
+pub struct OllamaBackend {
+    api_base: String,
+    model: String,
+    embedding_model: Option<String>,
+    client: reqwest::Client,
+    /// Schema sent as `format`, if structured output is enabled
+    response_format: Option<ResponseFormat>,
+    /// Set once Ollama reported that the model does not support tools
+    is_tool_calling_unsupported: AtomicBool,
+}
+
+impl OllamaBackend {
+    pub fn new(
+        llm_configurations: &LLMConfigurations,
+        response_format: Option<ResponseFormat>,
+    ) -> Result<Self, Error> {
+        let api_base: String =
+            resolve_setting(&llm_configurations.api_base, &["YOU_OLLAMA_API_BASE"])
+                .unwrap_or_else(|| DEFAULT_OLLAMA_API_BASE.to_string());
+        let model: String =
+            require_setting(&llm_configurations.model, "model", &["YOU_OLLAMA_MODEL"])?;
+        let embedding_model: Option<String> = resolve_setting(
+            &llm_configurations.embedding_model,
+            &["YOU_OLLAMA_EMBEDDING_MODEL"],
+        );
+
+        Ok(Self {
+            api_base: api_base.trim_end_matches('/').to_string(),
+            model,
+            embedding_model,
+            client: reqwest::Client::new(),
+            response_format,
+            is_tool_calling_unsupported: AtomicBool::new(false),
+        })
+    }
+
+    fn build_request(
+        &self,
+        context: &[ChatCompletionRequestMessage],
+        stream: bool,
+    ) -> OllamaChatRequest<'_> {
+        OllamaChatRequest {
+            model: &self.model,
+            messages: context
+                .iter()
+                .filter_map(flatten_message)
+                .map(|(role, content)| OllamaMessage {
+                    role: role.to_string(),
+                    content,
+                    tool_calls: Vec::new(),
+                })
+                .collect(),
+            stream,
+            tools: Vec::new(),
+            format: self
+                .response_format
+                .as_ref()
+                .map(|response_format| response_format.schema.clone()),
+        }
+    }
+
+    async fn send(&self, request: &OllamaChatRequest<'_>) -> Result<reqwest::Response, Error> {
+        let response: reqwest::Response = self
+            .client
+            .post(format!("{}/api/chat", self.api_base))
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| request_failed(format!("Failed to reach Ollama: {}", e)))?;
+
+        // Other failures carry their error in the body, which is read like a reply
+        let status: reqwest::StatusCode = response.status();
+        if is_transient_status(status) {
+            let body: String = response.text().await.unwrap_or_default();
+            return Err(request_failed(format!(
+                "Ollama returned an error ({}): {}",
+                status, body
+            )));
+        }
+
+        Ok(response)
+    }
+}
+
+impl LLMBackend for OllamaBackend {
+    fn generate_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<String, Error> {
+        let request: OllamaChatRequest = self.build_request(&context, false);
+
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        runtime.block_on(async {
+            let response: OllamaChatResponse = self
+                .send(&request)
+                .await?
+                .json()
+                .await
+                .map_err(|e| anyhow!("Failed to read Ollama's response: {}", e))?;
+
+            response
+                .into_content()?
+                .ok_or_else(|| anyhow!("No response is retrieved from the LLM"))
+        })
+    }
+
+    fn generate_with_tools(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<ToolCallingReply, Error> {
+        if self.is_tool_calling_unsupported.load(Ordering::Relaxed) {
+            return Ok(ToolCallingReply::Text(self.generate_with_context(context)?));
+        }
+
+        let mut request: OllamaChatRequest = self.build_request(&context, false);
+        // The tools carry their own schemas, the reply format would only get in the way
+        request.format = None;
+        request.tools = tools
+            .iter()
+            .map(|tool| OllamaToolCall {
+                tool_type: Some("function".to_string()),
+                function: OllamaFunction {
+                    name: tool.name.clone(),
+                    description: Some(tool.description.clone()),
+                    parameters: Some(tool.parameters.clone()),
+                    arguments: None,
+                },
+            })
+            .collect();
+
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        let response: OllamaChatResponse = runtime.block_on(async {
+            self.send(&request)
+                .await?
+                .json()
+                .await
+                .map_err(|e| anyhow!("Failed to read Ollama's response: {}", e))
+        })?;
+
+        if let Some(error) = &response.error
+            && error.contains("does not support tools")
+        {
+            display_message(
+                Level::Warn,
+                &format!(
+                    "Tool calling is not available ({}). Falling back to JSON in the prompt.",
+                    error
+                ),
+            );
+            self.is_tool_calling_unsupported
+                .store(true, Ordering::Relaxed);
+            return Ok(ToolCallingReply::Text(self.generate_with_context(context)?));
+        }
+
+        let Some(mut message) = response.message else {
+            return response
+                .into_content()?
+                .map(ToolCallingReply::Text)
+                .ok_or_else(|| anyhow!("No response is retrieved from the LLM"));
+        };
+
+        if message.tool_calls.is_empty() {
+            return Ok(ToolCallingReply::Text(message.content));
+        }
+
+        let tool_call: OllamaToolCall = message.tool_calls.remove(0);
+        Ok(ToolCallingReply::ToolCall(ToolCall {
+            name: tool_call.function.name,
+            arguments: tool_call
+                .function
+                .arguments
+                .unwrap_or_else(|| serde_json::json!({})),
+        }))
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        let embedding_model: &str = self.embedding_model.as_deref().ok_or_else(|| {
+            anyhow!(
+                "No embedding model is configured. Set `llm.embedding_model` or `YOU_OLLAMA_EMBEDDING_MODEL`"
+            )
+        })?;
+        let request: OllamaEmbedRequest = OllamaEmbedRequest {
+            model: embedding_model,
+            input: texts,
+        };
+
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        let response: OllamaEmbedResponse = runtime.block_on(async {
+            self.client
+                .post(format!("{}/api/embed", self.api_base))
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| request_failed(format!("Failed to reach Ollama: {}", e)))?
+                .json()
+                .await
+                .map_err(|e| anyhow!("Failed to read Ollama's response: {}", e))
+        })?;
+
+        if let Some(error) = response.error {
+            return Err(anyhow!("Ollama returned an error: {}", error));
+        }
+
+        Ok(response.embeddings)
+    }
+
+    fn generate_stream_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        stream_handler: &mut dyn StreamHandler,
+    ) -> Result<String, Error> {
+        let request: OllamaChatRequest = self.build_request(&context, true);
+
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        runtime.block_on(async {
+            let mut response: reqwest::Response = self.send(&request).await?;
+
+            // Ollama streams one JSON object per line
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut content: String = String::new();
+            while let Some(bytes) = response
+                .chunk()
+                .await
+                .map_err(|e| request_failed(format!("Failed to read Ollama's response: {}", e)))?
+            {
+                buffer.extend_from_slice(&bytes);
+
+                for line in drain_lines(&mut buffer) {
+                    receive_line(&line, stream_handler, &mut content)?;
+                }
+            }
+
+            // The last line may end without a line break
+            let last_line: String = String::from_utf8_lossy(&buffer).trim_end().to_string();
+            receive_line(&last_line, stream_handler, &mut content)?;
+
+            if content.is_empty() {
+                return Err(anyhow!("No response is retrieved from the LLM"));
+            }
+
+            Ok(content)
+        })
+    }
+}
@@ -0,0 +1,307 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Error, Result, anyhow};
+use async_openai::Client;
+use async_openai::config::OpenAIConfig;
+use async_openai::error::{ApiError, OpenAIError};
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionTool, ChatCompletionToolChoiceOption,
+    ChatCompletionToolType, CreateChatCompletionRequest, CreateChatCompletionRequestArgs,
+    CreateChatCompletionResponse, CreateEmbeddingRequest, CreateEmbeddingRequestArgs,
+    CreateEmbeddingResponse, FunctionObject, ResponseFormatJsonSchema,
+};
+use cchain::display_control::Level;
+use futures::StreamExt;
+use tokio::runtime::Runtime;
+
+use crate::configurations::LLMConfigurations;
+use crate::errors::YouError;
+use crate::output::display_message;
+
+use super::{
+    LLMBackend, ResponseFormat, StreamHandler, ToolCall, ToolCallingReply, ToolDefinition,
+    request_failed, require_setting, resolve_setting,
+};
+
+/// A backend for any endpoint that speaks the OpenAI chat completions API.
+#[derive(Debug)]
+pub struct OpenAIBackend {
+    model: String,
+    embedding_model: Option<String>,
+    client: Client<OpenAIConfig>,
+    /// Schema sent as `response_format`, if structured output is enabled
+    response_format: Option<ResponseFormat>,
+    /// Set once the endpoint rejected a request with tools
+    is_tool_calling_unsupported: AtomicBool,
+}
+
+impl OpenAIBackend {
+    pub fn new(
+        llm_configurations: &LLMConfigurations,
+        response_format: Option<ResponseFormat>,
+    ) -> Result<Self, Error> {
+        let api_base: String = require_setting(
+            &llm_configurations.api_base,
+            "api_base",
+            &["DONE_OPENAI_API_BASE", "YOU_OPENAI_API_BASE"],
+        )?;
+        let api_key: String = require_setting(
+            &llm_configurations.api_key,
+            "api_key",
+            &["DONE_OPENAI_API_KEY", "YOU_OPENAI_API_KEY"],
+        )?;
+        let model: String = require_setting(
+            &llm_configurations.model,
+            "model",
+            &["DONE_OPENAI_MODEL", "YOU_OPENAI_MODEL"],
+        )?;
+
+        let embedding_model: Option<String> = resolve_setting(
+            &llm_configurations.embedding_model,
+            &["YOU_OPENAI_EMBEDDING_MODEL"],
+        );
+
+        let llm_configuration: OpenAIConfig = OpenAIConfig::default()
+            .with_api_key(api_key)
+            .with_api_base(api_base);
+        let client: Client<OpenAIConfig> = async_openai::Client::with_config(llm_configuration);
+
+        Ok(Self {
+            model,
+            embedding_model,
+            client,
+            response_format,
+            is_tool_calling_unsupported: AtomicBool::new(false),
+        })
+    }
+
+    /// A plain chat completion request, constrained to the response format if there is one.
+    fn build_request(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<CreateChatCompletionRequest, Error> {
+        let mut request: CreateChatCompletionRequestArgs =
+            CreateChatCompletionRequestArgs::default();
+        request.model(&self.model).messages(context);
+
+        if let Some(response_format) = &self.response_format {
+            request.response_format(async_openai::types::ResponseFormat::JsonSchema {
+                json_schema: ResponseFormatJsonSchema {
+                    description: None,
+                    name: response_format.name.clone(),
+                    schema: Some(response_format.schema.clone()),
+                    strict: None,
+                },
+            });
+        }
+
+        Ok(request.build()?)
+    }
+}
+
+fn to_openai_tool(tool: &ToolDefinition) -> ChatCompletionTool {
+    ChatCompletionTool {
+        r#type: ChatCompletionToolType::Function,
+        function: FunctionObject {
+            name: tool.name.clone(),
+            description: Some(tool.description.clone()),
+            parameters: Some(tool.parameters.clone()),
+            strict: None,
+        },
+    }
+}
+
+/// Whether the endpoint refused a request because its model cannot call tools, e.g.
+/// "registry.ollama.ai/library/gemma:2b does not support tools".
+fn is_tool_calling_refusal(error: &ApiError) -> bool {
+    let is_about_tools = |text: &str| {
+        let text: String = text.to_lowercase();
+        text.contains("tool") || text.contains("function")
+    };
+
+    match error.param.as_deref() {
+        Some(param) if is_about_tools(param) => true,
+        _ => is_about_tools(&error.message) && error.message.to_lowercase().contains("support"),
+    }
+}
+
+/// Describe a failed call, marking the ones that never reached the endpoint as worth retrying.
+fn describe_error(description: &str, error: OpenAIError) -> Error {
+    match error {
+        OpenAIError::Reqwest(_) | OpenAIError::StreamError(_) => {
+            request_failed(format!("{}: {}", description, error))
+        }
+        _ => anyhow!("{}: {}", description, error),
+    }
+}
+
+impl LLMBackend for OpenAIBackend {
+    fn generate_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<String, Error> {
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        runtime.block_on(async {
+            let request: CreateChatCompletionRequest = self.build_request(context)?;
+
+            let response: CreateChatCompletionResponse =
+                match self.client.chat().create(request).await {
+                    std::result::Result::Ok(response) => response,
+                    Err(e) => {
+                        return Err(describe_error("Failed to execute function", e));
+                    }
+                };
+
+            match response.choices.into_iter().next() {
+                Some(choice) => choice
+                    .message
+                    .content
+                    .ok_or_else(|| anyhow!("No response is retrieved from the LLM")),
+                None => Err(anyhow!("No response is retrieved from the LLM")),
+            }
+        })
+    }
+
+    fn generate_with_tools(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<ToolCallingReply, Error> {
+        if self.is_tool_calling_unsupported.load(Ordering::Relaxed) {
+            return Ok(ToolCallingReply::Text(self.generate_with_context(context)?));
+        }
+
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        let reply: Option<ToolCallingReply> = runtime.block_on(async {
+            let request: CreateChatCompletionRequest = CreateChatCompletionRequestArgs::default()
+                .model(&self.model)
+                .messages(context.clone())
+                .tools(tools.iter().map(to_openai_tool).collect::<Vec<_>>())
+                .tool_choice(ChatCompletionToolChoiceOption::Required)
+                .build()?;
+
+            let response: CreateChatCompletionResponse =
+                match self.client.chat().create(request).await {
+                    std::result::Result::Ok(response) => response,
+                    // Models without tool support refuse the request, any other refusal
+                    // such as a rejected key is an error of its own
+                    Err(OpenAIError::ApiError(error)) if is_tool_calling_refusal(&error) => {
+                        display_message(
+                            Level::Warn,
+                            &format!(
+                                "Tool calling is not available ({}). Falling back to JSON in the prompt.",
+                                error
+                            ),
+                        );
+                        self.is_tool_calling_unsupported
+                            .store(true, Ordering::Relaxed);
+                        return Ok(None);
+                    }
+                    Err(e) => {
+                        return Err(describe_error("Failed to execute function", e));
+                    }
+                };
+
+            let Some(choice) = response.choices.into_iter().next() else {
+                return Err(anyhow!("No response is retrieved from the LLM"));
+            };
+
+            if let Some(tool_call) = choice
+                .message
+                .tool_calls
+                .and_then(|tool_calls| tool_calls.into_iter().next())
+            {
+                let arguments: serde_json::Value =
+                    serde_json::from_str(&tool_call.function.arguments).map_err(|e| {
+                        YouError::InvalidToolCall {
+                            name: tool_call.function.name.clone(),
+                            reason: format!("the arguments are not valid JSON: {}", e),
+                        }
+                    })?;
+
+                return Ok(Some(ToolCallingReply::ToolCall(ToolCall {
+                    name: tool_call.function.name,
+                    arguments,
+                })));
+            }
+
+            choice
+                .message
+                .content
+                .map(|content| Some(ToolCallingReply::Text(content)))
+                .ok_or_else(|| anyhow!("No response is retrieved from the LLM"))
+        })?;
+
+        match reply {
+            Some(reply) => Ok(reply),
+            None => Ok(ToolCallingReply::Text(self.generate_with_context(context)?)),
+        }
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        let embedding_model: &str = self.embedding_model.as_deref().ok_or_else(|| {
+            anyhow!(
+                "No embedding model is configured. Set `llm.embedding_model` or `YOU_OPENAI_EMBEDDING_MODEL`"
+            )
+        })?;
+
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        runtime.block_on(async {
+            let request: CreateEmbeddingRequest = CreateEmbeddingRequestArgs::default()
+                .model(embedding_model)
+                .input(texts.to_vec())
+                .build()?;
+
+            let response: CreateEmbeddingResponse = self
+                .client
+                .embeddings()
+                .create(request)
+                .await
+                .map_err(|e| anyhow!("Failed to create embeddings: {}", e))?;
+
+            let mut embeddings = response.data;
+            embeddings.sort_by_key(|embedding| embedding.index);
+            Ok(embeddings
+                .into_iter()
+                .map(|embedding| embedding.embedding)
+                .collect())
+        })
+    }
+
+    fn generate_stream_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        stream_handler: &mut dyn StreamHandler,
+    ) -> Result<String, Error> {
+        let runtime: Runtime = tokio::runtime::Runtime::new()?;
+        runtime.block_on(async {
+            let request: CreateChatCompletionRequest = self.build_request(context)?;
+
+            let mut stream = self
+                .client
+                .chat()
+                .create_stream(request)
+                .await
+                .map_err(|e| describe_error("Failed to execute function", e))?;
+
+            let mut content: String = String::new();
+            while let Some(response) = stream.next().await {
+                let response = response
+                    .map_err(|e| describe_error("Failed to receive the LLM's response", e))?;
+
+                for choice in response.choices {
+                    if let Some(chunk) = choice.delta.content {
+                        stream_handler.on_chunk(&chunk);
+                        content.push_str(&chunk);
+                    }
+                }
+            }
+
+            if content.is_empty() {
+                return Err(anyhow!("No response is retrieved from the LLM"));
+            }
+
+            Ok(content)
+        })
+    }
+}
@@ -0,0 +1,196 @@
+use std::{collections::HashMap, path::Path, sync::Mutex};
+
+use anyhow::{Error, Result, anyhow};
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::Deserialize;
+
+use super::{
+    LLMBackend, StreamHandler, ToolCall, ToolCallingReply, ToolDefinition, flatten_message,
+};
+
+/// Replies are streamed in pieces of this many characters, to mimic a live
+/// endpoint.
+const STREAMED_CHUNK_CHARACTERS: usize = 8;
+
+/// One canned reply in a script file.
+#[derive(Debug, Clone, Deserialize)]
+struct ScriptedResponse {
+    /// The conversation turn this reply answers, counted by user messages
+    /// starting from 1.
+    turn: usize,
+    /// The raw text the model would have replied with
+    #[serde(default)]
+    response: Option<String>,
+    /// A native tool call the model would have made instead
+    #[serde(default)]
+    tool_call: Option<ScriptedToolCall>,
+    /// Text the latest user message must contain, to check what the model is told
+    #[serde(default)]
+    expect: Option<String>,
+    /// Text the system prompt must contain for this reply to be used, so that one
+    /// script can hold the replies of several agents
+    #[serde(default)]
+    system: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ScriptedToolCall {
+    name: String,
+    arguments: serde_json::Value,
+}
+
+/// An offline backend that replays canned replies from a JSONL file.
+///
+/// Each line holds a `turn` and either a `response` or a `tool_call`, and
+/// optionally the text the latest user message is `expect`ed to contain. When
+/// several lines share a turn, they are returned one after another, which is
+/// how retries of the same turn are scripted. Lines with a `system` text are
+/// only returned to agents whose system prompt contains it.
+#[derive(Debug)]
+pub struct ScriptedBackend {
+    responses: Vec<ScriptedResponse>,
+    /// How many replies have been consumed for each turn
+    cursors: Mutex<HashMap<usize, usize>>,
+}
+
+impl ScriptedBackend {
+    pub fn new(script_path: &Path) -> Result<Self, Error> {
+        let script: String = std::fs::read_to_string(script_path).map_err(|e| {
+            anyhow!(
+                "Failed to read the LLM script {}: {}",
+                script_path.display(),
+                e
+            )
+        })?;
+
+        let mut responses: Vec<ScriptedResponse> = Vec::new();
+        for (line_number, line) in script.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            responses.push(serde_json::from_str(line).map_err(|e| {
+                anyhow!(
+                    "Invalid entry at line {} of the LLM script: {}",
+                    line_number + 1,
+                    e
+                )
+            })?);
+        }
+
+        Ok(Self {
+            responses,
+            cursors: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Consume the next canned reply for the turn the context is at.
+    fn next_response(
+        &self,
+        context: &[ChatCompletionRequestMessage],
+    ) -> Result<ScriptedResponse, Error> {
+        let turn: usize = context
+            .iter()
+            .filter(|message| matches!(message, ChatCompletionRequestMessage::User(_)))
+            .count();
+
+        let system_prompt: String = context
+            .iter()
+            .find(|message| matches!(message, ChatCompletionRequestMessage::System(_)))
+            .and_then(flatten_message)
+            .map(|(_, content)| content)
+            .unwrap_or_default();
+
+        let mut cursors = self
+            .cursors
+            .lock()
+            .map_err(|_| anyhow!("The LLM script state is poisoned"))?;
+        let cursor: &mut usize = cursors.entry(turn).or_insert(0);
+
+        let response: &ScriptedResponse = self
+            .responses
+            .iter()
+            .filter(|response| response.turn == turn)
+            .filter(|response| {
+                response
+                    .system
+                    .as_ref()
+                    .is_none_or(|system| system_prompt.contains(system.as_str()))
+            })
+            .nth(*cursor)
+            .ok_or_else(|| anyhow!("The LLM script has no response left for turn {}", turn))?;
+        *cursor += 1;
+
+        if let Some(expected) = &response.expect {
+            let latest_user_message: String = context
+                .iter()
+                .rev()
+                .find(|message| matches!(message, ChatCompletionRequestMessage::User(_)))
+                .and_then(flatten_message)
+                .map(|(_, content)| content)
+                .unwrap_or_default();
+
+            if !latest_user_message.contains(expected.as_str()) {
+                return Err(anyhow!(
+                    "The LLM script expects the user message at turn {} to contain {:?}, but it was {:?}",
+                    turn,
+                    expected,
+                    latest_user_message
+                ));
+            }
+        }
+
+        Ok(response.clone())
+    }
+}
+
+impl LLMBackend for ScriptedBackend {
+    fn generate_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<String, Error> {
+        let response: ScriptedResponse = self.next_response(&context)?;
+
+        response.response.ok_or_else(|| {
+            anyhow!(
+                "The LLM script expects tools to be offered at turn {}",
+                response.turn
+            )
+        })
+    }
+
+    fn generate_with_tools(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        _tools: &[ToolDefinition],
+    ) -> Result<ToolCallingReply, Error> {
+        let response: ScriptedResponse = self.next_response(&context)?;
+
+        match (response.tool_call, response.response) {
+            (Some(tool_call), _) => Ok(ToolCallingReply::ToolCall(ToolCall {
+                name: tool_call.name,
+                arguments: tool_call.arguments,
+            })),
+            (None, Some(text)) => Ok(ToolCallingReply::Text(text)),
+            (None, None) => Err(anyhow!(
+                "The LLM script has neither a response nor a tool call at turn {}",
+                response.turn
+            )),
+        }
+    }
+
+    fn generate_stream_with_context(
+        &self,
+        context: Vec<ChatCompletionRequestMessage>,
+        stream_handler: &mut dyn StreamHandler,
+    ) -> Result<String, Error> {
+        let response: String = self.generate_with_context(context)?;
+
+        let characters: Vec<char> = response.chars().collect();
+        for chunk in characters.chunks(STREAMED_CHUNK_CHARACTERS) {
+            stream_handler.on_chunk(&chunk.iter().collect::<String>());
+        }
+
+        Ok(response)
+    }
+}
@@ -1,59 +1,151 @@
 mod agents;
+mod approval;
 mod arguments;
 mod cache;
 mod configurations;
 mod constants;
+mod dry_run;
+mod errors;
+mod executors;
 mod helpers;
+mod history;
 mod information;
+mod journal;
 mod llm;
-mod shell;
+mod output;
+mod parameters;
+mod risk;
+mod schema;
+mod shell_integration;
 mod styles;
 mod traits;
 
-use std::{fs::File, io::Read};
+use std::process::ExitCode;
 
 use anyhow::{Error, Result};
-use arguments::{Arguments, Commands};
-use cchain::display_control::{Level, display_message};
-use clap::{Parser, crate_authors, crate_description, crate_name, crate_version};
+use arguments::{Arguments, Commands, HistoryCommands};
+use cchain::display_control::Level;
+use clap::{CommandFactory, Parser, crate_authors, crate_description, crate_name, crate_version};
+use clap_complete::CompleteEnv;
 use helpers::{
-    process_explanation_with_one_single_instruction, process_interactive_mode,
-    process_list_cached_scripts, process_remove_cached_script,
-    process_run_with_one_single_instruction,
+    process_cached_script_lookup, process_dry_run_with_one_single_instruction,
+    process_edit_cached_script, process_exec_cached_script,
+    process_explanation_with_one_single_instruction, process_export_cached_scripts,
+    process_history_list, process_history_rerun, process_history_show,
+    process_import_cached_scripts, process_interactive_mode, process_list_cached_scripts,
+    process_remove_cached_script, process_rename_cached_script, process_run_cached_script,
+    process_run_with_one_single_instruction, process_show_cached_script,
+    process_suggestion_with_one_single_instruction, process_undo,
 };
 
 use crate::{
-    cache::Cache, configurations::Configurations, information::ContextualInformation,
-    shell::execute_shell_script, traits::GlobalResourceInitialization,
+    cache::Cache,
+    configurations::Configurations,
+    constants::EXIT_CODE_FAILURE,
+    errors::get_exit_code,
+    executors::set_interpreter,
+    history::History,
+    information::ContextualInformation,
+    journal::Journal,
+    output::{
+        OutputEvent, display_message, emit, is_json_output, reserve_stdout, set_output_format,
+    },
+    shell_integration::get_init_script,
+    traits::GlobalResourceInitialization,
 };
 
-fn main() -> Result<(), Error> {
+fn main() -> ExitCode {
+    // Answers the shell when it asks for completions, e.g. `source <(COMPLETE=bash you)`
+    CompleteEnv::with_factory(Arguments::command).complete();
     let arguments: Arguments = Arguments::parse();
+    set_output_format(arguments.output);
+    // The shell reads the stdout of these, messages must not end up there
+    if matches!(arguments.commands, Commands::Suggest(_) | Commands::Init(_)) {
+        reserve_stdout();
+    }
 
+    let exit_code: u8 = match run(arguments) {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            match is_json_output() {
+                true => emit(&OutputEvent::Error {
+                    message: format!("{:#}", error),
+                }),
+                false => eprintln!("Error: {:?}", error),
+            }
+            get_exit_code(&error)
+        }
+    };
+
+    if is_json_output() {
+        emit(&OutputEvent::Exit { code: exit_code });
+    }
+
+    ExitCode::from(exit_code)
+}
+
+/// Runs the command of `arguments`, and returns the code to exit with when it does not fail.
+///
+/// Failures are mapped to exit codes in `main`, see `YouError::get_exit_code`.
+fn run(arguments: Arguments) -> Result<u8, Error> {
     Configurations::initialize()?;
     Cache::initialize()?;
+    Journal::initialize()?;
+    History::initialize()?;
 
     let mut cache: Cache = Cache::load()?;
     let contextual_information: ContextualInformation = ContextualInformation::new()?;
-    let configurations: Configurations = Configurations::load()?;
+    let mut configurations: Configurations = Configurations::load()?;
+    set_interpreter(configurations.get_interpreter());
+    for directory in configurations.script_search_paths.iter() {
+        if let Err(error) = cache.add_shared_directory(directory) {
+            display_message(
+                Level::Warn,
+                &format!("The shared scripts in {} are skipped: {}", directory, error),
+            );
+        }
+    }
 
     match arguments.commands {
         Commands::Run(subcommand) => {
+            if subcommand.sandbox {
+                configurations.sandbox.enabled = true;
+            }
+            if subcommand.yes {
+                configurations.approval_policy.is_non_interactive = true;
+                configurations.approval_policy.context = subcommand.context;
+            }
+
             if let Some(command_in_natural_language) = subcommand.command_in_natural_language {
+                // Parameters are only given to a cached script, by its name
+                if !subcommand.params.is_empty() {
+                    process_run_cached_script(
+                        &mut cache,
+                        &configurations,
+                        &command_in_natural_language,
+                        &subcommand.params,
+                    )?;
+                    return Ok(0);
+                }
+
+                // A dry run reviews freshly generated commands, cached scripts are skipped
+                if subcommand.dry_run {
+                    process_dry_run_with_one_single_instruction(
+                        &configurations,
+                        &contextual_information,
+                        &command_in_natural_language,
+                    )?;
+                    return Ok(0);
+                }
+
                 if configurations.enable_cache {
                     display_message(Level::Logging, "Cache has been enabled.");
-                    if let Some(script) = cache.search(&command_in_natural_language) {
-                        display_message(
-                            Level::Logging,
-                            &format!(
-                                "Cache hit. Using the generated script {}...",
-                                script.file_name().unwrap().to_str().unwrap()
-                            ),
-                        );
-                        let mut script_content: String = String::new();
-                        File::open(script)?.read_to_string(&mut script_content)?;
-                        execute_shell_script(&script_content)?;
-                        return Ok(());
+                    if process_cached_script_lookup(
+                        &mut cache,
+                        &configurations,
+                        &command_in_natural_language,
+                    )? {
+                        return Ok(0);
                     }
                 }
 
@@ -63,25 +155,85 @@ fn main() -> Result<(), Error> {
                     &contextual_information,
                     &command_in_natural_language,
                 )?;
-                return Ok(());
+                return Ok(0);
             }
 
             process_interactive_mode(&mut cache, &configurations, &contextual_information)?;
         }
         Commands::Explain(subcommand) => {
             process_explanation_with_one_single_instruction(
+                &configurations,
+                &contextual_information,
                 &subcommand.command,
+            )?;
+        }
+        Commands::Suggest(subcommand) => {
+            process_suggestion_with_one_single_instruction(
+                &configurations,
                 &contextual_information,
+                &subcommand.request,
             )?;
         }
-        Commands::List(_) => {
-            process_list_cached_scripts(&cache)?;
+        Commands::Init(subcommand) => {
+            print!("{}", get_init_script(subcommand.shell));
+        }
+        Commands::List(subcommand) => {
+            process_list_cached_scripts(&cache, subcommand.sort, &subcommand.tags)?;
         }
         Commands::Remove(subcommand) => {
             process_remove_cached_script(&mut cache, &subcommand.script_name)?;
         }
+        Commands::Show(subcommand) => {
+            process_show_cached_script(&cache, &subcommand.script_name)?;
+        }
+        Commands::Edit(subcommand) => {
+            process_edit_cached_script(&cache, &subcommand.script_name)?;
+        }
+        Commands::Rename(subcommand) => {
+            process_rename_cached_script(&mut cache, &subcommand.old_name, &subcommand.new_name)?;
+        }
+        Commands::Exec(subcommand) => {
+            configurations.approval_policy.is_non_interactive = subcommand.yes;
+            let exit_code: i32 = process_exec_cached_script(
+                &mut cache,
+                &configurations,
+                &subcommand.script_name,
+                &subcommand.arguments,
+            )?;
+            // Codes out of the range of a process' exit code still fail
+            return Ok(match u8::try_from(exit_code) {
+                Ok(exit_code) => exit_code,
+                Err(_) if exit_code > 0 => u8::MAX,
+                Err(_) => EXIT_CODE_FAILURE,
+            });
+        }
+        Commands::Export(subcommand) => {
+            process_export_cached_scripts(
+                &cache,
+                &subcommand.script_names,
+                &subcommand.tags,
+                &subcommand.output_file,
+            )?;
+        }
+        Commands::Import(subcommand) => {
+            process_import_cached_scripts(&mut cache, &configurations, &subcommand.path)?;
+        }
+        Commands::Undo(_) => {
+            process_undo()?;
+        }
+        Commands::History(subcommand) => match subcommand.commands {
+            None => process_history_list(None, 20)?,
+            Some(HistoryCommands::List(arguments)) => process_history_list(None, arguments.limit)?,
+            Some(HistoryCommands::Search(arguments)) => {
+                process_history_list(Some(&arguments.query), arguments.limit)?
+            }
+            Some(HistoryCommands::Show(arguments)) => process_history_show(&arguments.id)?,
+            Some(HistoryCommands::Rerun(arguments)) => {
+                process_history_rerun(&configurations, &arguments.id)?
+            }
+        },
         Commands::Version(_) => {
-            display_message(Level::Logging, &format!("{}", crate_name!()));
+            display_message(Level::Logging, crate_name!());
             display_message(Level::Logging, &format!("version.{}", crate_version!()));
             display_message(Level::Logging, &format!("Authors: {}", crate_authors!()));
             display_message(
@@ -91,5 +243,5 @@ fn main() -> Result<(), Error> {
         }
     }
 
-    Ok(())
+    Ok(0)
 }
@@ -0,0 +1,181 @@
+use std::{
+    io::Write,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::{Error, Result};
+use cchain::display_control::Level;
+use console::style;
+use serde::Serialize;
+
+use crate::{
+    agents::command_line_explain_agent::CommandExplained, arguments::OutputFormat,
+    cache::ScriptMetadata, dry_run::DryRunReport, history::SessionEventKind,
+};
+
+/// Set once by `--output`, before anything is shown.
+static OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+pub fn set_output_format(output_format: OutputFormat) {
+    let _ = OUTPUT_FORMAT.set(output_format);
+}
+
+/// Whether everything is reported as JSON lines, for `--output json`.
+pub fn is_json_output() -> bool {
+    OUTPUT_FORMAT.get() == Some(&OutputFormat::Json)
+}
+
+/// Set by `you suggest`, whose stdout is read by the shell.
+static IS_STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Keeps stdout for the result alone. Messages and prompts are shown on stderr instead.
+pub fn reserve_stdout() {
+    IS_STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_stdout_reserved() -> bool {
+    IS_STDOUT_RESERVED.load(Ordering::Relaxed)
+}
+
+/// One line of `--output json`, tagged with its `event`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum OutputEvent<'a> {
+    /// A message that is shown as text otherwise
+    Message { level: &'static str, text: &'a str },
+    /// `you` waits for the answer on a line of stdin
+    Prompt { text: &'a str },
+    /// Something that happened in the session, e.g. the action the LLM generated,
+    /// how it was answered, or a command that ran
+    Session(&'a SessionEventKind),
+    /// A piece of the output of a running command
+    Output { stream: &'static str, text: &'a str },
+    /// What a command and each of its tokens do, for `you explain`
+    Explanation {
+        command: &'a str,
+        explanation: &'a CommandExplained,
+    },
+    /// The cached scripts, for `you list`
+    Scripts { scripts: Vec<ScriptEntry> },
+    /// A cached script with its content, e.g. for `you show`
+    Script { script: &'a ScriptEntry },
+    /// The commands `you run --dry-run` would have run
+    DryRun { report: &'a DryRunReport },
+    /// The command `you suggest` leaves to the shell
+    Suggestion { command: &'a str },
+    /// What `you` stopped with
+    Error { message: String },
+    /// The code `you` exits with, always the last line
+    Exit { code: u8 },
+}
+
+/// A cached script in `--output json`. The embedding of its request is left out.
+#[derive(Debug, Serialize)]
+pub struct ScriptEntry {
+    pub name: String,
+    /// `personal`, or the shared directory it comes from
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(flatten)]
+    pub metadata: ScriptMetadata,
+}
+
+impl ScriptEntry {
+    pub fn new(
+        name: &str,
+        source: String,
+        content: Option<String>,
+        metadata: &ScriptMetadata,
+    ) -> Self {
+        let mut metadata: ScriptMetadata = metadata.clone();
+        metadata.embedding = None;
+
+        Self {
+            name: name.to_string(),
+            source,
+            content,
+            metadata,
+        }
+    }
+}
+
+/// Writes `event` as one line of JSON to stdout.
+pub fn emit(event: &OutputEvent) {
+    if let Ok(line) = serde_json::to_string(event) {
+        println!("{}", line);
+    }
+}
+
+/// Shows `message` as `cchain` does, or as a `message` event with `--output json`.
+pub fn display_message(level: Level, message: &str) {
+    if !is_json_output() {
+        if is_stdout_reserved() {
+            return display_message_on_stderr(level, message);
+        }
+        return cchain::display_control::display_message(level, message);
+    }
+
+    let level: &'static str = match level {
+        Level::Logging => "info",
+        Level::Error => "error",
+        Level::Warn => "warn",
+        Level::Selection => "selection",
+        Level::Input => "input",
+    };
+    emit(&OutputEvent::Message {
+        level,
+        text: message,
+    });
+}
+
+/// Shows `message` indented by `indent_level`, as `cchain` does, or as a `message`
+/// event with `--output json`.
+pub fn display_tree_message(indent_level: usize, message: &str) {
+    if is_json_output() || is_stdout_reserved() {
+        return display_message(Level::Logging, message);
+    }
+
+    cchain::display_control::display_tree_message(indent_level, message)
+}
+
+/// Reads a line from stdin after `prompt`, as `cchain` does. With `--output json`,
+/// the prompt is a `prompt` event. An empty answer means stdin is closed.
+pub fn input_message(prompt: &str) -> Result<String, Error> {
+    match (is_json_output(), is_stdout_reserved()) {
+        (true, _) => {
+            emit(&OutputEvent::Prompt { text: prompt });
+            std::io::stdout().flush()?;
+        }
+        (false, true) => {
+            display_message_on_stderr(Level::Input, prompt);
+            std::io::stderr().flush()?;
+        }
+        (false, false) => return cchain::commons::utility::input_message(prompt),
+    }
+
+    let mut input: String = String::new();
+    std::io::stdin().read_line(&mut input)?;
+
+    Ok(input)
+}
+
+/// Shows `message` styled as `cchain` does, on stderr.
+fn display_message_on_stderr(level: Level, message: &str) {
+    let indentation: &str = ">> ";
+
+    match level {
+        Level::Logging => eprintln!("{}{}", indentation, style(message).for_stderr().green()),
+        Level::Error => eprintln!(
+            "{}{}",
+            indentation,
+            style(message).for_stderr().red().bold()
+        ),
+        Level::Warn => eprintln!("{}{}", indentation, style(message).for_stderr().red()),
+        Level::Selection => eprintln!("{}{}", indentation, style(message).for_stderr().blue()),
+        Level::Input => eprint!("{}{} ", indentation, style(message).for_stderr().blue()),
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use anyhow::{Error, Result, anyhow};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A named value that a command is written against, so that it can be reused with another one.
+///
+/// Commands refer to it as `{{name}}`. The value is quoted for the shell when
+/// it is substituted, so the placeholder must not be quoted in the command.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScriptParameter {
+    #[schemars(
+        description = "name of the parameter, made of letters, digits and underscores. Refer to it as {{name}} in the command, without quotes."
+    )]
+    pub name: String,
+    pub kind: ParameterKind,
+    #[schemars(description = "what the parameter is for, in a few words.")]
+    pub description: String,
+    #[schemars(
+        description = "the value for the current request. It is the default when the command is reused."
+    )]
+    pub default: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterKind {
+    /// A file or directory
+    Path,
+    String,
+    /// A whole number
+    Int,
+}
+
+impl std::fmt::Display for ParameterKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Path => f.write_str("path"),
+            Self::String => f.write_str("string"),
+            Self::Int => f.write_str("int"),
+        }
+    }
+}
+
+impl ScriptParameter {
+    /// Checks that `value` is of the kind of the parameter.
+    pub fn validate(&self, value: &str) -> Result<(), Error> {
+        match self.kind {
+            ParameterKind::Int if value.trim().parse::<i64>().is_err() => Err(anyhow!(
+                "`{}` must be a whole number, not `{}`",
+                self.name,
+                value
+            )),
+            ParameterKind::Path if value.is_empty() => {
+                Err(anyhow!("`{}` must be a path, not empty", self.name))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Substitutes the `{{name}}` placeholders of `template` with `values`, quoted for the shell.
+///
+/// Parameters without a value get their default. Values of unknown parameters,
+/// and values of the wrong kind, are rejected.
+pub fn render(
+    template: &str,
+    parameters: &[ScriptParameter],
+    values: &HashMap<String, String>,
+) -> Result<String, Error> {
+    if let Some(unknown) = values
+        .keys()
+        .find(|name| !parameters.iter().any(|parameter| &parameter.name == *name))
+    {
+        return Err(anyhow!(
+            "The script has no parameter `{}`. Its parameters are: {}",
+            unknown,
+            describe_parameters(parameters)
+        ));
+    }
+
+    let mut substitutes: HashMap<&str, String> = HashMap::new();
+    for parameter in parameters {
+        let value: &str = values
+            .get(&parameter.name)
+            .unwrap_or(&parameter.default)
+            .as_str();
+        parameter.validate(value)?;
+
+        let value: String = match parameter.kind {
+            ParameterKind::Int => value.trim().to_string(),
+            ParameterKind::Path | ParameterKind::String => quote(value),
+        };
+        substitutes.insert(&parameter.name, value);
+    }
+
+    // In one pass over the template, so that a value containing `{{other}}` is never
+    // substituted again and always stays within its quotes
+    let mut rendered: String = String::new();
+    let mut rest: &str = template;
+    while let Some(start) = rest.find("{{") {
+        let (before, placeholder) = rest.split_at(start);
+        rendered.push_str(before);
+
+        let substitute: Option<(&String, usize)> = placeholder.find("}}").and_then(|end| {
+            substitutes
+                .get(&placeholder[2..end])
+                .map(|substitute| (substitute, end + 2))
+        });
+        match substitute {
+            Some((substitute, length)) => {
+                rendered.push_str(substitute);
+                rest = &placeholder[length..];
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = &placeholder[2..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+/// Substitutes every placeholder of `template` with the default of its parameter.
+pub fn render_defaults(template: &str, parameters: &[ScriptParameter]) -> Result<String, Error> {
+    render(template, parameters, &HashMap::new())
+}
+
+/// Parses `--param name=value` arguments.
+pub fn parse_assignments(assignments: &[String]) -> Result<HashMap<String, String>, Error> {
+    let mut values: HashMap<String, String> = HashMap::new();
+    for assignment in assignments {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow!("`{}` is not of the form name=value", assignment))?;
+        values.insert(name.trim().to_string(), value.to_string());
+    }
+
+    Ok(values)
+}
+
+/// The parameters on one line, e.g. `dir (path): the directory to compress [./logs]; ...`.
+pub fn describe_parameters(parameters: &[ScriptParameter]) -> String {
+    parameters
+        .iter()
+        .map(|parameter| {
+            format!(
+                "{} ({}): {} [{}]",
+                parameter.name, parameter.kind, parameter.description, parameter.default
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+/// Quotes `value` so that the shell passes it on as one word, as is.
+pub fn quote(value: &str) -> String {
+    if cfg!(target_os = "windows") {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
@@ -0,0 +1,1015 @@
+use std::{
+    fmt::Display,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// How much damage a command can do if it is not what the user wanted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+impl Display for RiskLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        })
+    }
+}
+
+/// The kinds of risky operations the analyzer recognizes. Each of them can be
+/// blocked outright with `risk_policy.blocked_categories` in `configurations.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskCategory {
+    /// `sudo`, `doas`, `su`
+    Privilege,
+    /// `rm`, `shred`, `find -delete` and the like
+    Deletion,
+    /// `dd`, `mkfs`, partitioning tools
+    DiskWrite,
+    /// `chmod -R`, `chown -R`, `chgrp -R`
+    RecursivePermissions,
+    /// Downloads piped into a shell, e.g. `curl ... | sh`
+    RemoteExecution,
+    /// Redirections and copies to paths outside the working directory
+    WriteOutsideWorkingDirectory,
+}
+
+impl Display for RiskCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Privilege => "privilege",
+            Self::Deletion => "deletion",
+            Self::DiskWrite => "disk_write",
+            Self::RecursivePermissions => "recursive_permissions",
+            Self::RemoteExecution => "remote_execution",
+            Self::WriteOutsideWorkingDirectory => "write_outside_working_directory",
+        })
+    }
+}
+
+/// One risky operation found in a command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RiskFinding {
+    pub category: RiskCategory,
+    pub level: RiskLevel,
+    pub reason: String,
+}
+
+/// Everything risky found in a command.
+#[derive(Debug, Clone, Default)]
+pub struct RiskAssessment {
+    pub findings: Vec<RiskFinding>,
+    /// Paths the command is expected to modify or delete, which may contain
+    /// wildcards like `*.log`
+    pub affected_paths: Vec<PathBuf>,
+    /// Whether the command writes files, or runs a program that is not known to be read-only
+    pub may_have_side_effects: bool,
+}
+
+impl RiskAssessment {
+    /// The level of the riskiest finding, `Low` if there is none.
+    pub fn get_level(&self) -> RiskLevel {
+        self.findings
+            .iter()
+            .map(|finding| finding.level)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Whether the command only reads, e.g. `ls` or `git log`. Unknown programs are
+    /// assumed to change something.
+    pub fn is_read_only(&self) -> bool {
+        self.findings.is_empty() && !self.may_have_side_effects
+    }
+
+    /// The findings whose category is in `categories`.
+    pub fn get_findings_in(&self, categories: &[RiskCategory]) -> Vec<&RiskFinding> {
+        self.findings
+            .iter()
+            .filter(|finding| categories.contains(&finding.category))
+            .collect()
+    }
+
+    /// e.g. `high risk: runs with elevated privileges (sudo); deletes files recursively`
+    pub fn get_summary(&self) -> String {
+        let reasons: Vec<&str> = self
+            .findings
+            .iter()
+            .map(|finding| finding.reason.as_str())
+            .collect();
+
+        format!("{} risk: {}", self.get_level(), reasons.join("; "))
+    }
+
+    pub fn merge(&mut self, other: RiskAssessment) {
+        for finding in other.findings {
+            self.add(finding.category, finding.level, finding.reason);
+        }
+
+        for path in other.affected_paths {
+            self.add_affected_path(path);
+        }
+
+        self.may_have_side_effects |= other.may_have_side_effects;
+    }
+
+    fn add(&mut self, category: RiskCategory, level: RiskLevel, reason: String) {
+        let finding: RiskFinding = RiskFinding {
+            category,
+            level,
+            reason,
+        };
+
+        if !self.findings.contains(&finding) {
+            self.findings.push(finding);
+        }
+    }
+
+    fn add_affected_path(&mut self, path: PathBuf) {
+        if !self.affected_paths.contains(&path) {
+            self.affected_paths.push(path);
+        }
+    }
+}
+
+/// Statically analyzes a shell command for risky operations.
+///
+/// This is a best-effort reading of the command, not a full shell parser. It
+/// understands quoting, pipelines, `;`/`&&`/`||` lists, redirections,
+/// command substitutions and wrappers such as `sudo`, `env` or `xargs`, which
+/// covers what LLMs usually generate. Relative paths are resolved against
+/// `working_directory`.
+pub fn assess(command: &str, working_directory: &Path) -> RiskAssessment {
+    let home_directory: Option<PathBuf> = dirs::home_dir();
+    let analyzer: Analyzer = Analyzer {
+        working_directory: normalize(working_directory),
+        home_directory: home_directory.as_deref().map(normalize),
+    };
+
+    let mut assessment: RiskAssessment = RiskAssessment::default();
+    analyzer.assess_script(command, &mut assessment);
+
+    assessment
+}
+
+/// Whether `command` is a single simple command, e.g. `kubectl get pods`, without
+/// lists, pipes, redirections, subshells or command substitutions.
+pub fn is_simple_command(command: &str) -> bool {
+    let (tokens, substitutions): (Vec<Token>, Vec<String>) = tokenize(command.trim());
+
+    substitutions.is_empty() && tokens.iter().all(|token| matches!(token, Token::Word(_)))
+}
+
+/// Programs that only read, whatever their arguments. Programs that read or write
+/// depending on their arguments are told apart in `is_read_only_program`.
+const READ_ONLY_PROGRAMS: &[&str] = &[
+    "ls",
+    "cat",
+    "head",
+    "tail",
+    "less",
+    "more",
+    "grep",
+    "egrep",
+    "fgrep",
+    "rg",
+    "wc",
+    "cut",
+    "tr",
+    "echo",
+    "printf",
+    "pwd",
+    "date",
+    "whoami",
+    "id",
+    "uname",
+    "hostname",
+    "df",
+    "du",
+    "ps",
+    "stat",
+    "file",
+    "which",
+    "type",
+    "basename",
+    "dirname",
+    "realpath",
+    "readlink",
+    "printenv",
+    "uptime",
+    "free",
+    "diff",
+    "cmp",
+    "md5sum",
+    "sha1sum",
+    "sha256sum",
+    "jq",
+    "column",
+    "nl",
+    "tac",
+    "rev",
+    "seq",
+    "true",
+    "false",
+    "test",
+    "[",
+    "tree",
+];
+
+/// `git` subcommands that only read.
+const READ_ONLY_GIT_SUBCOMMANDS: &[&str] = &[
+    "status",
+    "log",
+    "diff",
+    "show",
+    "blame",
+    "ls-files",
+    "rev-parse",
+    "describe",
+    "shortlog",
+];
+
+/// Programs that run the commands they are given as arguments.
+const SHELLS: &[&str] = &[
+    "sh", "bash", "zsh", "fish", "dash", "ksh", "csh", "tcsh", "python", "python3", "perl", "ruby",
+    "node", "pwsh",
+];
+
+const DOWNLOADERS: &[&str] = &["curl", "wget", "fetch", "iwr", "Invoke-WebRequest"];
+
+const DISK_TOOLS: &[&str] = &[
+    "fdisk", "sfdisk", "gdisk", "cfdisk", "parted", "wipefs", "mkswap", "mke2fs", "diskpart",
+];
+
+/// Directories whose contents the system depends on.
+const SYSTEM_DIRECTORIES: &[&str] = &[
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/lib",
+    "/lib64",
+    "/opt",
+    "/proc",
+    "/sbin",
+    "/sys",
+    "/usr",
+    "/var",
+    "/System",
+    "/Library",
+    "/Applications",
+];
+
+/// Write targets that never touch a file.
+const HARMLESS_TARGETS: &[&str] = &["/dev/null", "/dev/stdout", "/dev/stderr", "/dev/tty"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Operator(String),
+}
+
+/// A command with its arguments and redirections, e.g. `grep foo > out.txt`.
+#[derive(Debug, Default)]
+struct SimpleCommand {
+    words: Vec<String>,
+    /// Targets of redirections that write, e.g. `out.txt`
+    written_files: Vec<String>,
+}
+
+struct Analyzer {
+    working_directory: PathBuf,
+    home_directory: Option<PathBuf>,
+}
+
+impl Analyzer {
+    fn assess_script(&self, script: &str, assessment: &mut RiskAssessment) {
+        let (tokens, substitutions): (Vec<Token>, Vec<String>) = tokenize(script);
+
+        // Whatever runs inside `$(...)`, backticks or `<(...)` runs too
+        for substitution in &substitutions {
+            self.assess_script(substitution, assessment);
+        }
+
+        for pipeline in split_pipelines(tokens) {
+            self.assess_pipeline(&pipeline, &substitutions, assessment);
+        }
+    }
+
+    fn assess_pipeline(
+        &self,
+        pipeline: &[SimpleCommand],
+        substitutions: &[String],
+        assessment: &mut RiskAssessment,
+    ) {
+        let mut has_download: bool = false;
+
+        for command in pipeline {
+            let words: Vec<&str> = self.unwrap_command(command, assessment);
+            let Some(program) = words.first().map(|word| program_name(word)) else {
+                continue;
+            };
+
+            if SHELLS.contains(&program) {
+                let runs_download: bool = substitutions.iter().any(|substitution| {
+                    DOWNLOADERS
+                        .iter()
+                        .any(|downloader| substitution.contains(downloader))
+                }) && words.iter().any(|word| is_substituted(word));
+
+                if has_download || runs_download {
+                    assessment.add(
+                        RiskCategory::RemoteExecution,
+                        RiskLevel::High,
+                        format!("runs a downloaded script with `{}`", program),
+                    );
+                }
+            }
+
+            if DOWNLOADERS.contains(&program) {
+                has_download = true;
+            }
+
+            if !is_read_only_program(program, &words[1..]) {
+                assessment.may_have_side_effects = true;
+            }
+
+            self.assess_program(program, &words[1..], assessment);
+
+            // What a shell runs with `-c`, or `eval` runs, is assessed like the command itself
+            if let Some(script) = embedded_script(program, &words[1..]) {
+                self.assess_script(&script, assessment);
+            }
+
+            for written_file in &command.written_files {
+                self.assess_write(written_file, assessment);
+            }
+        }
+    }
+
+    /// Strips environment assignments and wrappers like `sudo` or `xargs` off a
+    /// command, recording the privilege escalations on the way.
+    fn unwrap_command<'a>(
+        &self,
+        command: &'a SimpleCommand,
+        assessment: &mut RiskAssessment,
+    ) -> Vec<&'a str> {
+        let mut words: &[String] = &command.words;
+
+        loop {
+            while words.first().is_some_and(|word| is_assignment(word)) {
+                words = &words[1..];
+            }
+
+            let Some(program) = words.first().map(|word| program_name(word)) else {
+                return Vec::new();
+            };
+
+            // Options of the wrapper that take a value
+            let options_with_values: &[&str] = match program {
+                "sudo" | "doas" => {
+                    assessment.add(
+                        RiskCategory::Privilege,
+                        RiskLevel::High,
+                        format!("runs with elevated privileges (`{}`)", program),
+                    );
+                    &["-u", "-g", "-p", "-C", "-D", "-U", "-r", "-t", "-h"]
+                }
+                "su" => {
+                    assessment.add(
+                        RiskCategory::Privilege,
+                        RiskLevel::High,
+                        "switches to another user (`su`)".to_string(),
+                    );
+                    return Vec::new();
+                }
+                "env" => &["-u", "-C", "-S"],
+                "nice" => &["-n"],
+                "xargs" => &["-I", "-n", "-P", "-L", "-d", "-s", "-E", "-a"],
+                "nohup" | "time" | "exec" | "command" | "builtin" | "stdbuf" => &[],
+                _ => return words.iter().map(String::as_str).collect(),
+            };
+
+            words = &words[1..];
+            while let Some(option) = words.first().filter(|word| word.starts_with('-')) {
+                let takes_value: bool = options_with_values.contains(&option.as_str());
+                words = &words[if takes_value { 2 } else { 1 }.min(words.len())..];
+            }
+        }
+    }
+
+    fn assess_program(&self, program: &str, arguments: &[&str], assessment: &mut RiskAssessment) {
+        let operands: Vec<&str> = arguments
+            .iter()
+            .copied()
+            .filter(|argument| !argument.starts_with('-'))
+            .collect();
+
+        match program {
+            "rm" | "unlink" | "rmdir" | "shred" | "srm" | "trash" | "del" | "rd" => {
+                let is_recursive: bool = arguments.iter().any(|argument| {
+                    *argument == "--recursive" || is_short_flag(argument, &['r', 'R'])
+                });
+
+                let critical_target: Option<&&str> = operands
+                    .iter()
+                    .find(|operand| self.is_critical_path(operand));
+
+                if let Some(critical_target) = critical_target {
+                    assessment.add(
+                        RiskCategory::Deletion,
+                        RiskLevel::High,
+                        format!("deletes `{}`", critical_target),
+                    );
+                } else if is_recursive || program == "shred" {
+                    assessment.add(
+                        RiskCategory::Deletion,
+                        RiskLevel::High,
+                        "deletes files recursively or irrecoverably".to_string(),
+                    );
+                } else {
+                    assessment.add(
+                        RiskCategory::Deletion,
+                        RiskLevel::Medium,
+                        "deletes files".to_string(),
+                    );
+                }
+
+                if operands.iter().any(|operand| self.is_outside(operand)) {
+                    assessment.add(
+                        RiskCategory::Deletion,
+                        RiskLevel::High,
+                        "deletes files outside the working directory".to_string(),
+                    );
+                }
+
+                for operand in &operands {
+                    self.affect(operand, assessment);
+                }
+            }
+            "find" => {
+                let deletes: bool = arguments.iter().enumerate().any(|(index, argument)| {
+                    *argument == "-delete"
+                        || ((*argument == "-exec" || *argument == "-execdir")
+                            && arguments
+                                .get(index + 1)
+                                .is_some_and(|program| program_name(program) == "rm"))
+                });
+
+                if deletes {
+                    assessment.add(
+                        RiskCategory::Deletion,
+                        RiskLevel::High,
+                        "deletes every file that `find` matches".to_string(),
+                    );
+
+                    // The search roots come before the first expression
+                    for root in arguments
+                        .iter()
+                        .take_while(|argument| !argument.starts_with(['-', '(', '!']))
+                    {
+                        self.affect(root, assessment);
+                    }
+                }
+            }
+            "dd" => {
+                if let Some(output) = arguments
+                    .iter()
+                    .find_map(|argument| argument.strip_prefix("of="))
+                {
+                    assessment.add(
+                        RiskCategory::DiskWrite,
+                        RiskLevel::High,
+                        format!("writes raw data to `{}` with `dd`", output),
+                    );
+                    self.affect(output, assessment);
+                }
+            }
+            "chmod" | "chown" | "chgrp" => {
+                let is_recursive: bool = arguments
+                    .iter()
+                    .any(|argument| *argument == "--recursive" || is_short_flag(argument, &['R']));
+
+                if is_recursive {
+                    assessment.add(
+                        RiskCategory::RecursivePermissions,
+                        RiskLevel::High,
+                        format!("changes permissions recursively (`{} -R`)", program),
+                    );
+                }
+
+                // The first operand is the mode or owner
+                for operand in operands.iter().skip(1) {
+                    self.assess_write(operand, assessment);
+                }
+            }
+            "tee" | "touch" | "mkdir" | "truncate" => {
+                for operand in &operands {
+                    self.assess_write(operand, assessment);
+                }
+            }
+            "cp" | "mv" | "install" | "ln" | "rsync" | "scp" => {
+                if let Some(destination) = operands.last().filter(|_| operands.len() > 1) {
+                    self.assess_write(destination, assessment);
+
+                    // The destination may be a directory that the sources end up in
+                    let sources: &[&str] = &operands[..operands.len() - 1];
+                    for source in sources {
+                        if let Some(file_name) = Path::new(source).file_name() {
+                            self.affect(
+                                &Path::new(destination).join(file_name).to_string_lossy(),
+                                assessment,
+                            );
+                        }
+
+                        if program == "mv" {
+                            self.affect(source, assessment);
+                        }
+                    }
+                }
+
+                if program == "mv"
+                    && operands[..operands.len().saturating_sub(1)]
+                        .iter()
+                        .any(|source| self.is_critical_path(source))
+                {
+                    assessment.add(
+                        RiskCategory::Deletion,
+                        RiskLevel::High,
+                        "moves system or home directories away".to_string(),
+                    );
+                }
+            }
+            "sed" | "perl" => {
+                let is_in_place: bool = arguments.iter().any(|argument| {
+                    argument.starts_with("--in-place")
+                        || (argument.starts_with('-')
+                            && !argument.starts_with("--")
+                            && argument.contains('i'))
+                });
+
+                if is_in_place {
+                    for file in edited_files(arguments) {
+                        self.assess_write(file, assessment);
+                    }
+                }
+            }
+            _ if program.starts_with("mkfs") || DISK_TOOLS.contains(&program) => {
+                assessment.add(
+                    RiskCategory::DiskWrite,
+                    RiskLevel::High,
+                    format!("formats or partitions disks (`{}`)", program),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn assess_write(&self, target: &str, assessment: &mut RiskAssessment) {
+        if HARMLESS_TARGETS.contains(&target) || target.starts_with('&') {
+            return;
+        }
+        assessment.may_have_side_effects = true;
+        self.affect(target, assessment);
+
+        if self.is_critical_path(target) {
+            assessment.add(
+                RiskCategory::WriteOutsideWorkingDirectory,
+                RiskLevel::High,
+                format!("writes to `{}`", target),
+            );
+        } else if self.is_outside(target) {
+            assessment.add(
+                RiskCategory::WriteOutsideWorkingDirectory,
+                RiskLevel::Medium,
+                format!("writes to `{}`, outside the working directory", target),
+            );
+        }
+    }
+
+    /// Records `target` as a path the command changes, if it can be resolved.
+    fn affect(&self, target: &str, assessment: &mut RiskAssessment) {
+        if HARMLESS_TARGETS.contains(&target) || is_substituted(target) {
+            return;
+        }
+
+        if let Some(path) = self.resolve(target) {
+            assessment.add_affected_path(path);
+        }
+    }
+
+    /// Resolves a path as the shell would, `None` if it depends on a variable
+    /// other than `$HOME`.
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let home_relative: Option<&str> = ["~", "$HOME", "${HOME}"]
+            .iter()
+            .find_map(|prefix| path.strip_prefix(prefix))
+            .filter(|rest| rest.is_empty() || rest.starts_with('/'));
+
+        if let Some(rest) = home_relative {
+            let home_directory: &PathBuf = self.home_directory.as_ref()?;
+            return Some(normalize(
+                &home_directory.join(rest.trim_start_matches('/')),
+            ));
+        }
+
+        if path.contains('$') || path.starts_with('~') {
+            return None;
+        }
+
+        Some(normalize(&self.working_directory.join(path)))
+    }
+
+    fn is_outside(&self, path: &str) -> bool {
+        match self.resolve(path) {
+            Some(resolved) => !resolved.starts_with(&self.working_directory),
+            // We cannot tell where it goes, so assume the worst
+            None => true,
+        }
+    }
+
+    /// The root and the home directory, and outside the working directory also
+    /// system directories and anything directly inside the root or the home
+    /// directory, like `/*` or `~/Documents`.
+    fn is_critical_path(&self, path: &str) -> bool {
+        let Some(resolved) = self.resolve(path) else {
+            return false;
+        };
+
+        let is_root_or_home =
+            |path: &Path| path.parent().is_none() || self.home_directory.as_deref() == Some(path);
+
+        if is_root_or_home(&resolved) {
+            return true;
+        }
+
+        // Working in `/var/www` or in the home directory is fine
+        if resolved.starts_with(&self.working_directory) {
+            return false;
+        }
+
+        resolved.parent().is_some_and(is_root_or_home)
+            || (SYSTEM_DIRECTORIES
+                .iter()
+                .any(|directory| resolved.starts_with(directory))
+                && !HARMLESS_TARGETS
+                    .iter()
+                    .any(|target| resolved == Path::new(target)))
+    }
+}
+
+/// Whether `program` only reads when it is run with `arguments`.
+fn is_read_only_program(program: &str, arguments: &[&str]) -> bool {
+    match program {
+        "find" => !arguments.iter().any(|argument| {
+            matches!(
+                *argument,
+                "-delete"
+                    | "-exec"
+                    | "-execdir"
+                    | "-ok"
+                    | "-okdir"
+                    | "-fprint"
+                    | "-fprintf"
+                    | "-fls"
+            )
+        }),
+        "sed" => !arguments.iter().any(|argument| {
+            argument.starts_with("--in-place")
+                || (argument.starts_with('-')
+                    && !argument.starts_with("--")
+                    && argument.contains('i'))
+        }),
+        "sort" => !arguments
+            .iter()
+            .any(|argument| argument.starts_with("--output") || is_short_flag(argument, &['o'])),
+        "git" => arguments
+            .iter()
+            .find(|argument| !argument.starts_with('-'))
+            .is_some_and(|subcommand| READ_ONLY_GIT_SUBCOMMANDS.contains(subcommand)),
+        _ => READ_ONLY_PROGRAMS.contains(&program),
+    }
+}
+
+fn program_name(word: &str) -> &str {
+    word.rsplit(['/', '\\']).next().unwrap_or(word)
+}
+
+/// `NAME=value`, as in `FOO=bar make`.
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|character| character.is_ascii_alphanumeric() || character == '_')
+                && !name.starts_with(|character: char| character.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// The script that `program` runs from its arguments, e.g. `rm -rf ~` in `sh -c 'rm -rf ~'`.
+fn embedded_script(program: &str, arguments: &[&str]) -> Option<String> {
+    if program == "eval" {
+        return Some(arguments.join(" "));
+    }
+    if !SHELLS.contains(&program) {
+        return None;
+    }
+
+    // `-c script`, also inside a cluster as in `bash -lc script`
+    let position: usize = arguments
+        .iter()
+        .position(|argument| is_short_flag(argument, &['c']))?;
+
+    arguments.get(position + 1).map(|script| script.to_string())
+}
+
+/// Whether a short option cluster like `-rf` contains any of `flags`.
+fn is_short_flag(argument: &str, flags: &[char]) -> bool {
+    argument.starts_with('-')
+        && !argument.starts_with("--")
+        && argument.chars().skip(1).any(|flag| flags.contains(&flag))
+}
+
+/// The files that `sed -i` or `perl -i` edit: every operand, except the script
+/// when it is not given with `-e` or `-f`.
+fn edited_files<'a>(arguments: &[&'a str]) -> Vec<&'a str> {
+    let mut operands: Vec<&str> = Vec::new();
+    let mut has_script_option: bool = false;
+    let mut arguments = arguments.iter();
+
+    while let Some(argument) = arguments.next() {
+        if matches!(*argument, "--expression" | "--file") {
+            has_script_option = true;
+            arguments.next();
+        } else if argument.starts_with("--") {
+            continue;
+        } else if argument.starts_with('-') {
+            // `-e script`, also at the end of a cluster as in `perl -pe script`
+            if argument.ends_with(['e', 'f']) {
+                has_script_option = true;
+                arguments.next();
+            }
+        } else {
+            operands.push(argument);
+        }
+    }
+
+    if !has_script_option && !operands.is_empty() {
+        operands.remove(0);
+    }
+
+    operands
+}
+
+fn is_substituted(word: &str) -> bool {
+    word.contains(SUBSTITUTION_PLACEHOLDER)
+}
+
+/// Stands in for a command substitution inside a word.
+const SUBSTITUTION_PLACEHOLDER: &str = "\u{0}substitution\u{0}";
+
+/// Splits a script into words and operators, the way `sh` would, minus
+/// expansions. Command substitutions are replaced by a placeholder and
+/// returned separately.
+fn tokenize(script: &str) -> (Vec<Token>, Vec<String>) {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut substitutions: Vec<String> = Vec::new();
+    let mut word: String = String::new();
+    // Distinguishes an empty quoted word, `''`, from no word at all
+    let mut has_word: bool = false;
+    let mut characters = script.chars().peekable();
+
+    let finish_word = |tokens: &mut Vec<Token>, word: &mut String, has_word: &mut bool| {
+        if *has_word {
+            tokens.push(Token::Word(std::mem::take(word)));
+            *has_word = false;
+        }
+    };
+
+    while let Some(character) = characters.next() {
+        match character {
+            '\n' | ';' => {
+                finish_word(&mut tokens, &mut word, &mut has_word);
+                tokens.push(Token::Operator(";".to_string()));
+            }
+            ' ' | '\t' | '\r' => finish_word(&mut tokens, &mut word, &mut has_word),
+            '#' if !has_word => {
+                while characters.peek().is_some_and(|next| *next != '\n') {
+                    characters.next();
+                }
+            }
+            '\\' => {
+                if let Some(escaped) = characters.next()
+                    && escaped != '\n'
+                {
+                    word.push(escaped);
+                    has_word = true;
+                }
+            }
+            '\'' => {
+                has_word = true;
+                for quoted in characters.by_ref() {
+                    if quoted == '\'' {
+                        break;
+                    }
+                    word.push(quoted);
+                }
+            }
+            '"' => {
+                has_word = true;
+                while let Some(quoted) = characters.next() {
+                    match quoted {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(escaped) = characters.next() {
+                                word.push(escaped);
+                            }
+                        }
+                        '$' if characters.peek() == Some(&'(') => {
+                            characters.next();
+                            substitutions.push(read_until_closing(&mut characters, ')'));
+                            word.push_str(SUBSTITUTION_PLACEHOLDER);
+                        }
+                        '`' => {
+                            substitutions.push(read_until_closing(&mut characters, '`'));
+                            word.push_str(SUBSTITUTION_PLACEHOLDER);
+                        }
+                        _ => word.push(quoted),
+                    }
+                }
+            }
+            '$' if characters.peek() == Some(&'(') => {
+                characters.next();
+                substitutions.push(read_until_closing(&mut characters, ')'));
+                word.push_str(SUBSTITUTION_PLACEHOLDER);
+                has_word = true;
+            }
+            '`' => {
+                substitutions.push(read_until_closing(&mut characters, '`'));
+                word.push_str(SUBSTITUTION_PLACEHOLDER);
+                has_word = true;
+            }
+            // Process substitution, `<(...)` or `>(...)`
+            '<' | '>' if characters.peek() == Some(&'(') => {
+                characters.next();
+                finish_word(&mut tokens, &mut word, &mut has_word);
+                substitutions.push(read_until_closing(&mut characters, ')'));
+                tokens.push(Token::Word(SUBSTITUTION_PLACEHOLDER.to_string()));
+            }
+            '|' | '&' => {
+                finish_word(&mut tokens, &mut word, &mut has_word);
+                let mut operator: String = character.to_string();
+                if characters.peek() == Some(&character) {
+                    characters.next();
+                    operator.push(character);
+                } else if character == '&' && characters.peek() == Some(&'>') {
+                    // `&>` and `&>>` redirect both streams
+                    characters.next();
+                    operator.push('>');
+                    if characters.peek() == Some(&'>') {
+                        characters.next();
+                        operator.push('>');
+                    }
+                } else if character == '|' && characters.peek() == Some(&'&') {
+                    characters.next();
+                    operator = "|".to_string();
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            '<' | '>' => {
+                // A file descriptor number right before belongs to the operator, as in `2>`
+                let mut operator: String = String::new();
+                if has_word && word.chars().all(|digit| digit.is_ascii_digit()) {
+                    operator = std::mem::take(&mut word);
+                    has_word = false;
+                }
+                finish_word(&mut tokens, &mut word, &mut has_word);
+
+                operator.push(character);
+                while let Some(next) = characters.peek().copied() {
+                    if next == '>' || next == '<' || next == '&' || next == '|' {
+                        operator.push(next);
+                        characters.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            '(' | ')' | '{' | '}' if !has_word => {
+                tokens.push(Token::Operator(";".to_string()));
+            }
+            _ => {
+                word.push(character);
+                has_word = true;
+            }
+        }
+    }
+    finish_word(&mut tokens, &mut word, &mut has_word);
+
+    (tokens, substitutions)
+}
+
+/// Reads up to the matching `closing` character, keeping nested parentheses
+/// and quotes intact.
+fn read_until_closing(
+    characters: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    closing: char,
+) -> String {
+    let mut content: String = String::new();
+    let mut depth: usize = 0;
+    let mut quote: Option<char> = None;
+
+    while let Some(character) = characters.next() {
+        match (quote, character) {
+            (Some(open), _) if character == open => quote = None,
+            (Some(_), '\\') => {
+                content.push(character);
+                if let Some(escaped) = characters.next() {
+                    content.push(escaped);
+                }
+                continue;
+            }
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(character),
+            (None, '(') if closing == ')' => depth += 1,
+            (None, _) if character == closing => {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+        content.push(character);
+    }
+
+    content
+}
+
+/// Groups tokens into pipelines of simple commands.
+fn split_pipelines(tokens: Vec<Token>) -> Vec<Vec<SimpleCommand>> {
+    let mut pipelines: Vec<Vec<SimpleCommand>> = Vec::new();
+    let mut pipeline: Vec<SimpleCommand> = Vec::new();
+    let mut command: SimpleCommand = SimpleCommand::default();
+    let mut tokens = tokens.into_iter();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Word(word) => command.words.push(word),
+            Token::Operator(operator) if operator == "|" => {
+                pipeline.push(std::mem::take(&mut command));
+            }
+            Token::Operator(operator) if operator.contains('>') || operator.contains('<') => {
+                let target: Option<String> = match tokens.next() {
+                    Some(Token::Word(target)) => Some(target),
+                    _ => None,
+                };
+
+                // `>&2` duplicates a descriptor and `<` only reads
+                let is_write: bool = operator.contains('>') && !operator.ends_with('&');
+                if let Some(target) = target.filter(|_| is_write) {
+                    command.written_files.push(target);
+                }
+            }
+            Token::Operator(_) => {
+                pipeline.push(std::mem::take(&mut command));
+                pipelines.push(std::mem::take(&mut pipeline));
+            }
+        }
+    }
+    pipeline.push(command);
+    pipelines.push(pipeline);
+
+    pipelines
+}
+
+/// Resolves `.` and `..` without touching the file system, as the paths may
+/// not exist yet.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized: PathBuf = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
@@ -0,0 +1,303 @@
+use anyhow::{Error, Result};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::errors::YouError;
+
+/// Generates the JSON schema of `T`, as sent to the LLM.
+///
+/// Every reply is a JSON object. Tagged enums produce a bare `oneOf` at the
+/// root, so the root type is spelled out for endpoints that insist on it.
+pub fn schema_of<T: JsonSchema>() -> Value {
+    let mut schema: Value = schemars::schema_for!(T).to_value();
+    if let Value::Object(schema) = &mut schema {
+        schema
+            .entry("type")
+            .or_insert_with(|| Value::String("object".to_string()));
+    }
+
+    schema
+}
+
+/// Parses the LLM's reply into `T`, checking it against the schema of `T` first.
+///
+/// Replies that are valid JSON but do not follow the schema are reported as
+/// `YouError::SchemaViolation`, listing every mismatch with its JSON path, so the
+/// model can be told exactly what to fix.
+pub fn parse_validated<T: JsonSchema + DeserializeOwned>(response: &str) -> Result<T, Error> {
+    let value: Value = serde_json::from_str(response)?;
+
+    let violations: Vec<String> = validate(&schema_of::<T>(), &value);
+    if !violations.is_empty() {
+        return Err(YouError::SchemaViolation { violations }.into());
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Validates `instance` against `schema`, returning one message per violation.
+///
+/// Only the subset of JSON Schema that `schemars` emits for our types is supported:
+/// `type`, `properties`, `required`, `additionalProperties`, `items`, `enum`,
+/// `const`, `$ref` into `$defs`, `oneOf` and `anyOf`. A `oneOf` whose branches are
+/// told apart by a `const` property, like the `action` tag of `LLMActionType`, is
+/// validated against the matching branch only.
+pub fn validate(schema: &Value, instance: &Value) -> Vec<String> {
+    let mut violations: Vec<String> = Vec::new();
+    validate_at(schema, schema, instance, "$", &mut violations);
+
+    violations
+}
+
+fn validate_at(
+    root: &Value,
+    schema: &Value,
+    instance: &Value,
+    path: &str,
+    violations: &mut Vec<String>,
+) {
+    let schema: &Map<String, Value> = match schema {
+        Value::Object(schema) => schema,
+        // `false` rejects everything, `true` accepts everything
+        Value::Bool(false) => {
+            violations.push(format!("{}: no value is allowed here", path));
+            return;
+        }
+        _ => return,
+    };
+
+    if let Some(Value::String(reference)) = schema.get("$ref") {
+        match resolve_reference(root, reference) {
+            Some(referenced) => validate_at(root, referenced, instance, path, violations),
+            None => violations.push(format!("{}: unknown schema reference {}", path, reference)),
+        }
+    }
+
+    if let Some(expected) = schema.get("type")
+        && !matches_type(expected, instance)
+    {
+        violations.push(format!(
+            "{}: expected {}, found {}",
+            path,
+            describe_type(expected),
+            type_name(instance)
+        ));
+        // Nothing below makes sense for a value of the wrong type
+        return;
+    }
+
+    if let Some(expected) = schema.get("const")
+        && expected != instance
+    {
+        violations.push(format!(
+            "{}: expected {}, found {}",
+            path, expected, instance
+        ));
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum")
+        && !allowed.contains(instance)
+    {
+        violations.push(format!(
+            "{}: expected one of {}, found {}",
+            path,
+            join_values(allowed),
+            instance
+        ));
+    }
+
+    if let Value::Object(object) = instance {
+        validate_object(root, schema, object, path, violations);
+    }
+
+    if let (Some(items), Value::Array(elements)) = (schema.get("items"), instance) {
+        for (index, element) in elements.iter().enumerate() {
+            validate_at(
+                root,
+                items,
+                element,
+                &format!("{}[{}]", path, index),
+                violations,
+            );
+        }
+    }
+
+    if let Some(Value::Array(branches)) = schema.get("oneOf") {
+        validate_branches(root, branches, instance, path, violations);
+    }
+
+    if let Some(Value::Array(branches)) = schema.get("anyOf") {
+        validate_branches(root, branches, instance, path, violations);
+    }
+}
+
+fn validate_object(
+    root: &Value,
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    violations: &mut Vec<String>,
+) {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                violations.push(format!("{}: missing required field `{}`", path, name));
+            }
+        }
+    }
+
+    let properties: Option<&Map<String, Value>> =
+        schema.get("properties").and_then(Value::as_object);
+
+    for (name, value) in object {
+        let property_path: String = format!("{}.{}", path, name);
+        match properties.and_then(|properties| properties.get(name)) {
+            Some(property) => validate_at(root, property, value, &property_path, violations),
+            None => {
+                if let Some(additional) = schema.get("additionalProperties") {
+                    validate_at(root, additional, value, &property_path, violations);
+                }
+            }
+        }
+    }
+}
+
+fn validate_branches(
+    root: &Value,
+    branches: &[Value],
+    instance: &Value,
+    path: &str,
+    violations: &mut Vec<String>,
+) {
+    if let Some(tag) = find_discriminator(branches)
+        && let Value::Object(object) = instance
+    {
+        let allowed: Vec<Value> = branches
+            .iter()
+            .filter_map(|branch| discriminator_value(branch, &tag).cloned())
+            .collect();
+
+        match object.get(&tag) {
+            None => violations.push(format!(
+                "{}: missing required field `{}`, expected one of {}",
+                path,
+                tag,
+                join_values(&allowed)
+            )),
+            Some(value) => match branches
+                .iter()
+                .find(|branch| discriminator_value(branch, &tag) == Some(value))
+            {
+                Some(branch) => validate_at(root, branch, instance, path, violations),
+                None => violations.push(format!(
+                    "{}.{}: expected one of {}, found {}",
+                    path,
+                    tag,
+                    join_values(&allowed),
+                    value
+                )),
+            },
+        }
+
+        return;
+    }
+
+    // Without a discriminator, report the mismatches of the closest branch
+    let mut closest: Option<Vec<String>> = None;
+    for branch in branches {
+        let mut branch_violations: Vec<String> = Vec::new();
+        validate_at(root, branch, instance, path, &mut branch_violations);
+        if branch_violations.is_empty() {
+            return;
+        }
+
+        if closest
+            .as_ref()
+            .is_none_or(|closest| branch_violations.len() < closest.len())
+        {
+            closest = Some(branch_violations);
+        }
+    }
+
+    if let Some(closest) = closest {
+        violations.extend(closest);
+    }
+}
+
+/// The property whose `const` value tells every branch apart, if there is one.
+fn find_discriminator(branches: &[Value]) -> Option<String> {
+    let first: &Map<String, Value> = branches.first()?.get("properties")?.as_object()?;
+
+    first
+        .iter()
+        .filter(|(_, property)| property.get("const").is_some())
+        .map(|(name, _)| name.clone())
+        .find(|name| {
+            branches
+                .iter()
+                .all(|branch| discriminator_value(branch, name).is_some())
+        })
+}
+
+fn discriminator_value<'a>(branch: &'a Value, tag: &str) -> Option<&'a Value> {
+    branch.get("properties")?.get(tag)?.get("const")
+}
+
+fn resolve_reference<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    if reference == "#" {
+        return Some(root);
+    }
+
+    root.pointer(reference.strip_prefix('#')?)
+}
+
+fn matches_type(expected: &Value, instance: &Value) -> bool {
+    match expected {
+        Value::String(expected) => matches_type_name(expected, instance),
+        Value::Array(expected) => expected
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|expected| matches_type_name(expected, instance)),
+        _ => true,
+    }
+}
+
+fn matches_type_name(expected: &str, instance: &Value) -> bool {
+    match expected {
+        "integer" => instance.is_i64() || instance.is_u64(),
+        "number" => instance.is_number(),
+        _ => expected == type_name(instance),
+    }
+}
+
+fn type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn describe_type(expected: &Value) -> String {
+    match expected {
+        Value::Array(expected) => expected
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<&str>>()
+            .join(" or "),
+        Value::String(expected) => expected.clone(),
+        _ => expected.to_string(),
+    }
+}
+
+fn join_values(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
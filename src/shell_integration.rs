@@ -0,0 +1,48 @@
+use crate::arguments::Shell;
+
+// Each function sends the command line to `you suggest` as the request, and replaces it
+// with the suggested command. Questions of the LLM are asked on the terminal, and an
+// aborted suggestion leaves the command line as it was.
+const BASH_INIT_SCRIPT: &str = r#"_you_suggest() {
+    [[ -z "$READLINE_LINE" ]] && return
+    local suggestion
+    suggestion="$(command you suggest -- "$READLINE_LINE" </dev/tty)" || return
+    READLINE_LINE="$suggestion"
+    READLINE_POINT=${#READLINE_LINE}
+}
+bind -x '"\C-g": _you_suggest'
+"#;
+
+const ZSH_INIT_SCRIPT: &str = r#"_you_suggest() {
+    [[ -z "$BUFFER" ]] && return
+    zle -I
+    local suggestion
+    if suggestion="$(command you suggest -- "$BUFFER" </dev/tty)"; then
+        BUFFER="$suggestion"
+        CURSOR=${#BUFFER}
+    fi
+    zle reset-prompt
+}
+zle -N _you_suggest
+bindkey '^G' _you_suggest
+"#;
+
+const FISH_INIT_SCRIPT: &str = r#"function _you_suggest
+    set -l request (commandline)
+    if test -n "$request"
+        set -l suggestion (command you suggest -- "$request" </dev/tty | string collect)
+        and commandline --replace -- $suggestion
+    end
+    commandline -f repaint
+end
+bind \cg _you_suggest
+"#;
+
+/// The shell code printed by `you init`, which binds Ctrl-G to `you suggest`.
+pub fn get_init_script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => BASH_INIT_SCRIPT,
+        Shell::Zsh => ZSH_INIT_SCRIPT,
+        Shell::Fish => FISH_INIT_SCRIPT,
+    }
+}
@@ -1,7 +1,10 @@
-use std::time::Duration;
+use std::{io::Write, time::Duration};
 
+use console::style;
 use indicatif::ProgressBar;
 
+use crate::llm::StreamHandler;
+
 pub fn start_spinner(message: String) -> ProgressBar {
     let spinner = ProgressBar::new_spinner();
     spinner.set_message(message);
@@ -9,3 +12,268 @@ pub fn start_spinner(message: String) -> ProgressBar {
 
     spinner
 }
+
+/// Renders the `explanation` field of a JSON reply while it streams in.
+///
+/// The spinner keeps spinning until the first character of the explanation
+/// arrives. Everything else in the reply is left for the caller to parse once
+/// it is complete.
+pub struct ExplanationStreamRenderer {
+    spinner: ProgressBar,
+    /// The reply received so far
+    received: String,
+    /// Byte offset in `received` where the explanation's text begins
+    explanation_start: Option<usize>,
+    /// Byte offset in `received` up to which the explanation is rendered
+    rendered_until: usize,
+    /// Whether the closing quote of the explanation has been seen
+    is_explanation_complete: bool,
+    /// Whether a rendered line is waiting for its line break
+    is_line_open: bool,
+    has_rendered: bool,
+}
+
+impl ExplanationStreamRenderer {
+    pub fn new(spinner: ProgressBar) -> Self {
+        Self {
+            spinner,
+            received: String::new(),
+            explanation_start: None,
+            rendered_until: 0,
+            is_explanation_complete: false,
+            is_line_open: false,
+            has_rendered: false,
+        }
+    }
+
+    /// Whether any part of an explanation was shown to the user.
+    pub fn has_rendered(&self) -> bool {
+        self.has_rendered
+    }
+
+    /// Close the rendered line and clear the spinner.
+    pub fn finish(&mut self) {
+        self.close_line();
+        self.spinner.finish_and_clear();
+    }
+
+    fn close_line(&mut self) {
+        if self.is_line_open {
+            println!();
+            self.is_line_open = false;
+        }
+    }
+
+    /// Find where the text of the `explanation` string begins, if it has
+    /// arrived yet.
+    fn locate_explanation(&self) -> Option<usize> {
+        const KEY: &str = "\"explanation\"";
+
+        let key_end: usize = self.received.find(KEY)? + KEY.len();
+        let rest: &str = self.received[key_end..].trim_start();
+        let rest: &str = rest.strip_prefix(':')?.trim_start();
+        rest.strip_prefix('"')?;
+
+        Some(self.received.len() - rest.len() + 1)
+    }
+
+    /// Decode as much of the explanation as has fully arrived. Escape
+    /// sequences split across chunks are left for the next call.
+    fn decode_pending(&mut self) -> String {
+        let mut decoded: String = String::new();
+        let pending: &str = &self.received[self.rendered_until..];
+        let mut characters = pending.char_indices();
+
+        while let Some((offset, character)) = characters.next() {
+            match character {
+                '"' => {
+                    self.is_explanation_complete = true;
+                    self.rendered_until += offset + 1;
+                    return decoded;
+                }
+                '\\' => {
+                    let Some((_, escaped)) = characters.next() else {
+                        self.rendered_until += offset;
+                        return decoded;
+                    };
+
+                    match escaped {
+                        'n' => decoded.push('\n'),
+                        't' => decoded.push('\t'),
+                        'r' => {}
+                        'u' => {
+                            let hex: &str = pending.get(offset + 2..offset + 6).unwrap_or("");
+                            if hex.len() < 4 {
+                                self.rendered_until += offset;
+                                return decoded;
+                            }
+
+                            decoded.push(
+                                u32::from_str_radix(hex, 16)
+                                    .ok()
+                                    .and_then(char::from_u32)
+                                    .unwrap_or(char::REPLACEMENT_CHARACTER),
+                            );
+                            for _ in 0..4 {
+                                characters.next();
+                            }
+                        }
+                        other => decoded.push(other),
+                    }
+                }
+                other => decoded.push(other),
+            }
+        }
+
+        self.rendered_until = self.received.len();
+        decoded
+    }
+}
+
+impl StreamHandler for ExplanationStreamRenderer {
+    fn on_chunk(&mut self, chunk: &str) {
+        self.received.push_str(chunk);
+
+        if self.is_explanation_complete {
+            return;
+        }
+
+        if self.explanation_start.is_none() {
+            self.explanation_start = self.locate_explanation();
+            match self.explanation_start {
+                Some(start) => self.rendered_until = start,
+                None => return,
+            }
+        }
+
+        let decoded: String = self.decode_pending();
+        if decoded.is_empty() {
+            return;
+        }
+
+        if !self.is_line_open {
+            self.spinner.finish_and_clear();
+            print!("    * ");
+            self.is_line_open = true;
+            self.has_rendered = true;
+        }
+
+        print!("{}", decoded);
+        let _ = std::io::stdout().flush();
+    }
+
+    fn on_retry(&mut self) {
+        self.close_line();
+        self.received.clear();
+        self.explanation_start = None;
+        self.rendered_until = 0;
+        self.is_explanation_complete = false;
+    }
+}
+
+/// Colors a shell script for display: commands in bold, strings, variables,
+/// operators and comments each in their own color, and `{{name}}` parameters
+/// in yellow.
+///
+/// Colors are left out when the output is not a terminal.
+pub fn highlight_shell(script: &str) -> String {
+    script
+        .lines()
+        .map(highlight_shell_line)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn highlight_shell_line(line: &str) -> String {
+    let characters: Vec<char> = line.chars().collect();
+    let mut highlighted: String = String::new();
+    let mut word: String = String::new();
+    // Whether the next word is a command rather than an argument
+    let mut is_command_position: bool = true;
+    let mut index: usize = 0;
+
+    let flush_word = |word: &mut String, highlighted: &mut String, is_command: &mut bool| {
+        if word.is_empty() {
+            return;
+        }
+        // Assignments like `NAME=value` come before the command
+        let is_assignment: bool = word.contains('=') && !word.starts_with('=');
+        if *is_command && !is_assignment {
+            highlighted.push_str(&style(word.as_str()).bold().to_string());
+            *is_command = false;
+        } else {
+            highlighted.push_str(word);
+        }
+        word.clear();
+    };
+
+    while index < characters.len() {
+        let character: char = characters[index];
+        let rest: String = characters[index..].iter().collect();
+
+        if character == '#' && word.is_empty() {
+            flush_word(&mut word, &mut highlighted, &mut is_command_position);
+            highlighted.push_str(&style(rest).dim().to_string());
+            break;
+        }
+
+        if rest.starts_with("{{")
+            && let Some(end) = rest.find("}}")
+        {
+            flush_word(&mut word, &mut highlighted, &mut is_command_position);
+            highlighted.push_str(&style(&rest[..end + 2]).yellow().to_string());
+            index += rest[..end + 2].chars().count();
+            continue;
+        }
+
+        match character {
+            '\'' | '"' => {
+                flush_word(&mut word, &mut highlighted, &mut is_command_position);
+                let length: usize = characters[index + 1..]
+                    .iter()
+                    .position(|other| *other == character)
+                    .map_or(characters.len() - index, |end| end + 2);
+                let quoted: String = characters[index..index + length].iter().collect();
+                highlighted.push_str(&style(quoted).green().to_string());
+                index += length;
+            }
+            '$' => {
+                flush_word(&mut word, &mut highlighted, &mut is_command_position);
+                let length: usize = 1 + characters[index + 1..]
+                    .iter()
+                    .take_while(|other| {
+                        other.is_alphanumeric()
+                            || matches!(other, '_' | '{' | '}' | '?' | '@' | '#')
+                    })
+                    .count();
+                let variable: String = characters[index..index + length].iter().collect();
+                highlighted.push_str(&style(variable).cyan().to_string());
+                index += length;
+            }
+            '|' | '&' | ';' | '>' | '<' | '(' | ')' => {
+                flush_word(&mut word, &mut highlighted, &mut is_command_position);
+                let length: usize = characters[index..]
+                    .iter()
+                    .take_while(|other| matches!(other, '|' | '&' | ';' | '>' | '<' | '(' | ')'))
+                    .count();
+                let operator: String = characters[index..index + length].iter().collect();
+                // What follows a redirection is a file, not a command
+                is_command_position = !operator.contains(['>', '<']);
+                highlighted.push_str(&style(operator).magenta().to_string());
+                index += length;
+            }
+            character if character.is_whitespace() => {
+                flush_word(&mut word, &mut highlighted, &mut is_command_position);
+                highlighted.push(character);
+                index += 1;
+            }
+            character => {
+                word.push(character);
+                index += 1;
+            }
+        }
+    }
+    flush_word(&mut word, &mut highlighted, &mut is_command_position);
+
+    highlighted
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+const EXECUTE_ECHO: &str =
+    r#"{"action": "execute", "command": "echo hello-from-you", "explanation": "Print a greeting"}"#;
+
+#[test]
+fn run_executes_the_approved_command() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(&["run", "say hello"], &[(1, EXECUTE_ECHO)], "y\nn\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("echo hello-from-you"));
+    assert!(run.stdout.contains("hello-from-you"));
+    assert!(
+        run.stdout
+            .contains("Commands had been executed successfully.")
+    );
+}
+
+#[test]
+fn run_saves_the_command_to_a_shell_script() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(&["run", "say hello"], &[(1, EXECUTE_ECHO)], "y\ngreet\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    let script: String =
+        std::fs::read_to_string(sandbox.working_directory().join("greet.sh")).unwrap();
+    assert!(script.contains("echo hello-from-you"));
+}
+
+#[test]
+fn run_retries_when_the_llm_returns_malformed_json() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "say hello"],
+        // The repair message counts as a user turn
+        &[(1, r#"{"unexpected": "shape"}"#), (2, EXECUTE_ECHO)],
+        "y\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("LLM returned a wrong JSON, retrying...")
+    );
+    assert!(run.stdout.contains("hello-from-you"));
+}
+
+#[test]
+fn run_retries_when_the_llm_returns_no_json() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "say hello"],
+        &[(1, "Sure! I would echo a greeting."), (2, EXECUTE_ECHO)],
+        "y\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("LLM returned no JSON, retrying..."));
+    assert!(run.stdout.contains("hello-from-you"));
+}
+
+#[test]
+fn run_gives_up_once_retries_are_exhausted() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "say hello"],
+        &[
+            (1, r#"{"unexpected": "shape"}"#),
+            (2, r#"{"unexpected": "shape"}"#),
+            (3, r#"{"unexpected": "shape"}"#),
+            (4, EXECUTE_ECHO),
+        ],
+        "y\nn\n",
+    );
+
+    assert_eq!(run.code(), Some(123));
+    assert!(run.stderr.contains("Gave up after 3 attempts"));
+    assert!(!run.stdout.contains("hello-from-you"));
+}
+
+#[test]
+fn run_relays_requests_to_install_clis() {
+    let sandbox = Sandbox::new();
+    let request_clis: &str = r#"{"action": "request_clis_to_install", "request_clis_to_install": [{"cli_name": "fd", "suggested_installation_command": "brew install fd", "additional_notices": null}]}"#;
+    let run = sandbox.run(
+        &["run", "find rust files"],
+        &[(1, request_clis), (2, EXECUTE_ECHO)],
+        "installed\ny\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Tool: fd"));
+    assert!(run.stdout.contains("Installation Command: brew install fd"));
+    assert!(run.stdout.contains("hello-from-you"));
+}
+
+#[test]
+fn installing_clis_does_not_count_as_a_repair_round() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "repair_policy": { "max_rounds": 0 },
+        "preferred_clis": []
+    }));
+    let request_clis: &str = r#"{"action": "request_clis_to_install", "request_clis_to_install": [{"cli_name": "sh", "suggested_installation_command": "apt install dash", "additional_notices": null}, {"cli_name": "not-installed-anywhere", "suggested_installation_command": "brew install not-installed-anywhere", "additional_notices": null}]}"#;
+    let run = sandbox.run_with_llm_script(
+        &["run", "find rust files"],
+        &[
+            json!({ "turn": 1, "response": request_clis }),
+            json!({
+                "turn": 2,
+                "expect": "`sh` is installed now.\n`not-installed-anywhere` is still not found in PATH.",
+                "response": EXECUTE_ECHO
+            }),
+        ],
+        "y\ny\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(!run.stdout.contains("Asking the LLM to fix the command"));
+    assert!(run.stdout.contains("hello-from-you"));
+}
+
+#[test]
+fn run_relays_requests_for_information() {
+    let sandbox = Sandbox::new();
+    let request_information: &str = r#"{"action": "request_information", "request_additional_information": "Which directory should I look in?"}"#;
+    let run = sandbox.run(
+        &["run", "count files"],
+        &[(1, request_information), (2, EXECUTE_ECHO)],
+        "the current one\ny\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Which directory should I look in?"));
+    assert!(run.stdout.contains("hello-from-you"));
+}
+
+#[test]
+fn explain_displays_the_explanation() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["explain", "ls -la"],
+        &[(1, r#"{"explanation": "Lists every file with details"}"#)],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Lists every file with details"));
+}
+
+#[test]
+fn explain_displays_the_breakdown() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["explain", "ls -la"],
+        &[(
+            1,
+            r#"{"explanation": "Lists every file with details", "breakdown": [{"token": "ls", "explanation": "lists directory contents"}, {"token": "-la", "explanation": "long format, including hidden files"}]}"#,
+        )],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("ls: lists directory contents"));
+    assert!(
+        run.stdout
+            .contains("-la: long format, including hidden files")
+    );
+}
+
+#[test]
+fn interactive_mode_executes_until_the_user_exits() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(&["run"], &[(1, EXECUTE_ECHO)], "say hello\ny\ne\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("What can I do for you"));
+    assert!(run.stdout.contains("hello-from-you"));
+}
+
+#[test]
+fn running_out_of_scripted_responses_fails() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(&["run", "say hello"], &[], "");
+
+    assert!(!run.success());
+    assert!(run.stderr.contains("no response left for turn 1"));
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::{Sandbox, serve_http};
+use serde_json::json;
+
+/// A sandbox whose LLM is `provider` on `api_base`, streaming its replies and never retrying.
+fn sandbox_with_backend(provider: &str, api_base: &str) -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "llm": {
+            "provider": provider,
+            "api_base": api_base,
+            "api_key": "test-key",
+            "model": "test-model"
+        },
+        "enable_streaming": true,
+        "retry_policy": {
+            "max_attempts": 1,
+            "initial_backoff_milliseconds": 0,
+            "max_backoff_milliseconds": 0
+        },
+        "preferred_clis": []
+    }));
+
+    sandbox
+}
+
+#[test]
+fn anthropic_errors_are_shown_for_streamed_replies() {
+    let api_base: String = serve_http(
+        "401 Unauthorized",
+        r#"{"type": "error", "error": {"type": "authentication_error", "message": "invalid x-api-key"}}"#,
+    );
+    let sandbox = sandbox_with_backend("anthropic", &api_base);
+
+    let run = sandbox.run_with_configured_backend(&["explain", "ls"], "");
+
+    assert!(!run.success());
+    assert!(
+        run.stderr
+            .contains("Anthropic returned an error (401 Unauthorized): invalid x-api-key"),
+        "{}{}",
+        run.stdout,
+        run.stderr
+    );
+}
+
+#[test]
+fn the_last_line_of_an_ollama_stream_is_read_without_a_line_break() {
+    let first_line: String =
+        json!({ "message": { "role": "assistant", "content": "{\"explanation\": " } }).to_string();
+    let last_line: String = json!({
+        "message": { "role": "assistant", "content": "\"Lists the files\"}" },
+        "done": true
+    })
+    .to_string();
+    let api_base: String = serve_http("200 OK", &format!("{}\n{}", first_line, last_line));
+    let sandbox = sandbox_with_backend("ollama", &api_base);
+
+    let run = sandbox.run_with_configured_backend(&["explain", "ls"], "");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Lists the files"));
+}
+
+/// A sandbox whose LLM is the OpenAI compatible endpoint at `api_base`, calling tools.
+fn sandbox_with_openai_tools(api_base: &str) -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "llm": {
+            "provider": "openai",
+            "api_base": api_base,
+            "api_key": "test-key",
+            "model": "test-model",
+            "enable_tool_calling": true
+        },
+        "retry_policy": {
+            "max_attempts": 1,
+            "initial_backoff_milliseconds": 0,
+            "max_backoff_milliseconds": 0
+        },
+        "preferred_clis": []
+    }));
+
+    sandbox
+}
+
+#[test]
+fn only_models_without_tool_support_fall_back_to_json_in_the_prompt() {
+    let api_base: String = serve_http(
+        "400 Bad Request",
+        r#"{"error": {"message": "registry.ollama.ai/library/gemma:2b does not support tools", "type": "api_error", "param": null, "code": null}}"#,
+    );
+    let sandbox = sandbox_with_openai_tools(&api_base);
+
+    let run = sandbox.run_with_configured_backend(&["run", "say hello"], "");
+    assert!(
+        format!("{}{}", run.stdout, run.stderr).contains("Tool calling is not available"),
+        "{}{}",
+        run.stdout,
+        run.stderr
+    );
+
+    let api_base: String = serve_http(
+        "401 Unauthorized",
+        r#"{"error": {"message": "Incorrect API key provided", "type": "invalid_request_error", "param": null, "code": "invalid_api_key"}}"#,
+    );
+    let sandbox = sandbox_with_openai_tools(&api_base);
+
+    let run = sandbox.run_with_configured_backend(&["run", "say hello"], "");
+    assert!(!run.success());
+    assert!(run.stderr.contains("Incorrect API key provided"));
+    assert!(!format!("{}{}", run.stdout, run.stderr).contains("Tool calling is not available"));
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+/// A sandbox with the cache enabled.
+fn sandbox_with_cache() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "enable_cache": true,
+        "preferred_clis": []
+    }));
+
+    sandbox
+}
+
+/// Runs `request` through the agent, approving `command` and saving it as `name` with `tags`.
+fn save_script(sandbox: &Sandbox, name: &str, request: &str, command: &str, tags: &str) {
+    let reply: String =
+        json!({ "action": "execute", "command": command, "explanation": "Scripted command" })
+            .to_string();
+    let run = sandbox.run(
+        &["run", request],
+        &[(1, &reply)],
+        &format!("y\n{}\n{}\n", name, tags),
+    );
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+}
+
+/// Exports the scripts of `sandbox` with `arguments` to a bundle, and returns its path.
+fn export(sandbox: &Sandbox, arguments: &[&str]) -> std::path::PathBuf {
+    let bundle_path = sandbox.working_directory().join("bundle.json");
+    let mut export_arguments: Vec<&str> = vec!["export", "-o", bundle_path.to_str().unwrap()];
+    export_arguments.extend(arguments);
+
+    let run = sandbox.run(&export_arguments, &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+
+    bundle_path
+}
+
+fn cached_script(sandbox: &Sandbox, name: &str) -> Option<String> {
+    std::fs::read_to_string(sandbox.home().join(format!(".you/cache/{}.sh", name))).ok()
+}
+
+#[test]
+fn exported_scripts_are_imported_with_their_metadata() {
+    let sandbox = sandbox_with_cache();
+    save_script(
+        &sandbox,
+        "show-date",
+        "print the current date",
+        "date",
+        "time",
+    );
+    save_script(&sandbox, "say-hello", "greet the world", "echo hello", "");
+    let run = sandbox.run(&["run", "print the current date"], &[], "1\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+
+    let bundle_path = export(&sandbox, &["show-date"]);
+    let bundle: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&bundle_path).unwrap()).unwrap();
+    assert_eq!(bundle["scripts"].as_array().unwrap().len(), 1);
+    assert_eq!(bundle["scripts"][0]["metadata"]["run_count"], 0);
+
+    let other_sandbox = sandbox_with_cache();
+    let run = other_sandbox.run(&["import", bundle_path.to_str().unwrap()], &[], "y\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("Script show-date, saved for: print the current date")
+    );
+    assert!(run.stdout.contains("1 of 1 script(s) have been imported."));
+    assert!(
+        cached_script(&other_sandbox, "show-date")
+            .unwrap()
+            .contains("date")
+    );
+
+    let run = other_sandbox.run(&["list", "--tag", "time"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("show-date"));
+}
+
+#[test]
+fn declined_scripts_are_not_imported() {
+    let sandbox = sandbox_with_cache();
+    save_script(&sandbox, "show-date", "print the current date", "date", "");
+    save_script(&sandbox, "say-hello", "greet the world", "echo hello", "");
+    let bundle_path = export(&sandbox, &[]);
+
+    let other_sandbox = sandbox_with_cache();
+    let run = other_sandbox.run(&["import", bundle_path.to_str().unwrap()], &[], "y\nn\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("show-date has been skipped."));
+    assert!(run.stdout.contains("1 of 2 script(s) have been imported."));
+    assert!(cached_script(&other_sandbox, "say-hello").is_some());
+    assert!(cached_script(&other_sandbox, "show-date").is_none());
+}
+
+#[test]
+fn name_collisions_are_skipped_renamed_or_overwritten() {
+    let sandbox = sandbox_with_cache();
+    save_script(&sandbox, "greet", "greet the world", "echo shared", "");
+    let bundle_path = export(&sandbox, &[]);
+    let bundle_path: &str = bundle_path.to_str().unwrap();
+
+    let other_sandbox = sandbox_with_cache();
+    save_script(&other_sandbox, "greet", "greet the world", "echo local", "");
+
+    let run = other_sandbox.run(&["import", bundle_path], &[], "y\ns\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("A script named greet is already cached.")
+    );
+    assert!(
+        cached_script(&other_sandbox, "greet")
+            .unwrap()
+            .contains("echo local")
+    );
+
+    let run = other_sandbox.run(&["import", bundle_path], &[], "y\nr\ngreet\ngreet-shared\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("A script named greet is already cached too.")
+    );
+    assert!(
+        cached_script(&other_sandbox, "greet")
+            .unwrap()
+            .contains("echo local")
+    );
+    assert!(
+        cached_script(&other_sandbox, "greet-shared")
+            .unwrap()
+            .contains("echo shared")
+    );
+
+    let run = other_sandbox.run(&["import", bundle_path], &[], "y\no\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        cached_script(&other_sandbox, "greet")
+            .unwrap()
+            .contains("echo shared")
+    );
+}
+
+#[test]
+fn exports_can_be_selected_by_tag() {
+    let sandbox = sandbox_with_cache();
+    save_script(
+        &sandbox,
+        "show-date",
+        "print the current date",
+        "date",
+        "time",
+    );
+    save_script(&sandbox, "say-hello", "greet the world", "echo hello", "");
+
+    let bundle_path = export(&sandbox, &["--tag", "time"]);
+    let bundle: String = std::fs::read_to_string(bundle_path).unwrap();
+    assert!(bundle.contains("show-date"));
+    assert!(!bundle.contains("say-hello"));
+
+    let run = sandbox.run(&["export", "missing"], &[], "");
+    assert!(!run.success());
+    assert!(run.stderr.contains("Script 'missing' not found"));
+}
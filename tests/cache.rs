@@ -0,0 +1,294 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+fn execute(command: &str, explanation: &str) -> String {
+    json!({ "action": "execute", "command": command, "explanation": explanation }).to_string()
+}
+
+/// A sandbox with the cache enabled and a script saved as `name` for `request`.
+fn sandbox_with_cached_script(name: &str, request: &str, command: &str) -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "enable_cache": true,
+        "preferred_clis": []
+    }));
+    save_script(&sandbox, name, request, command, "");
+
+    sandbox
+}
+
+/// Runs `request` through the agent, approving `command` and saving it as `name` with `tags`.
+fn save_script(sandbox: &Sandbox, name: &str, request: &str, command: &str, tags: &str) {
+    let run = sandbox.run(
+        &["run", request],
+        &[(1, &execute(command, "Write the marker file"))],
+        &format!("y\n{}\n{}\n", name, tags),
+    );
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Shell had been saved to the cache."));
+}
+
+#[test]
+fn reworded_requests_find_the_cached_script() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+
+    // No LLM reply is scripted, the cached script has to be used
+    let run = sandbox.run(&["run", "please compress the log directory"], &[], "1\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("1. compress-logs"));
+    assert!(
+        run.stdout
+            .contains("saved for: compress the logs directory")
+    );
+    assert!(run.stdout.contains("* Write the marker file"));
+    assert!(run.stdout.contains("> echo cached >> marker.txt"));
+    assert_eq!(
+        std::fs::read_to_string(sandbox.working_directory().join("marker.txt")).unwrap(),
+        "cached\ncached\n"
+    );
+}
+
+#[test]
+fn declining_the_candidates_asks_the_llm() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+
+    let run = sandbox.run(
+        &["run", "compress the logs directory"],
+        &[(1, &execute("echo fresh > fresh.txt", "Fresh command"))],
+        "n\ny\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("1. compress-logs (100% similar)"));
+    assert!(sandbox.working_directory().join("fresh.txt").exists());
+    assert_eq!(
+        std::fs::read_to_string(sandbox.working_directory().join("marker.txt")).unwrap(),
+        "cached\n"
+    );
+}
+
+#[test]
+fn unrelated_requests_are_not_offered_cached_scripts() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+
+    let run = sandbox.run(
+        &["run", "show the disk usage"],
+        &[(1, &execute("echo fresh > fresh.txt", "Fresh command"))],
+        "y\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        !run.stdout
+            .contains("Cached scripts similar to your request")
+    );
+    assert!(sandbox.working_directory().join("fresh.txt").exists());
+}
+
+#[test]
+fn lookups_fall_back_to_words_without_embeddings() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+    sandbox.write_configurations(json!({
+        "enable_cache": true,
+        "cache_lookup": { "use_embeddings": true },
+        "preferred_clis": []
+    }));
+
+    let run = sandbox.run(&["run", "compress the logs directory"], &[], "1\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Embeddings are not available"));
+    assert!(run.stdout.contains("1. compress-logs"));
+}
+
+#[test]
+fn list_shows_the_metadata_and_filters_by_tag() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+    save_script(
+        &sandbox,
+        "show-date",
+        "print the current date",
+        "date",
+        "time, ops",
+    );
+
+    let run = sandbox.run(&["list"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("NAME"));
+    assert!(run.stdout.contains("LAST USED"));
+    assert!(run.stdout.contains("compress the logs directory"));
+    assert!(run.stdout.contains("time,ops"));
+    assert!(run.stdout.contains("never"));
+
+    let run = sandbox.run(&["list", "--tag", "ops"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("show-date"));
+    assert!(!run.stdout.contains("compress-logs"));
+
+    let run = sandbox.run(&["list", "--tag", "missing"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("No cached scripts are tagged missing."));
+}
+
+#[test]
+fn runs_from_the_cache_are_counted() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+    save_script(&sandbox, "show-date", "print the current date", "date", "");
+
+    let run = sandbox.run(&["list", "--sort", "runs"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.find("compress-logs") < run.stdout.find("show-date"));
+
+    let run = sandbox.run(&["run", "print the current date"], &[], "1\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+
+    let run = sandbox.run(&["list", "--sort", "runs"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.find("show-date") < run.stdout.find("compress-logs"));
+    let show_date_row: &str = run
+        .stdout
+        .lines()
+        .find(|line| line.contains("show-date"))
+        .unwrap();
+    assert!(show_date_row.contains("  1  "));
+    assert!(!show_date_row.contains("never"));
+}
+
+#[test]
+fn show_displays_the_script_and_what_it_was_saved_for() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+
+    let run = sandbox.run(&["show", "compress-logs"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("Script compress-logs, saved for: compress the logs directory")
+    );
+    assert!(run.stdout.contains("* Write the marker file"));
+    assert!(run.stdout.contains("echo cached >> marker.txt"));
+
+    let run = sandbox.run(&["show", "missing"], &[], "");
+    assert!(!run.success());
+    assert!(run.stderr.contains("Script 'missing' not found"));
+}
+
+#[test]
+fn rename_keeps_the_metadata_of_the_script() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+    save_script(&sandbox, "show-date", "print the current date", "date", "");
+
+    let run = sandbox.run(&["rename", "compress-logs", "show-date"], &[], "");
+    assert!(!run.success());
+    assert!(run.stderr.contains("Script 'show-date' already exists"));
+
+    let run = sandbox.run(&["rename", "compress-logs", "pack-logs"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("Script 'compress-logs' has been renamed to 'pack-logs'.")
+    );
+
+    let run = sandbox.run(&["show", "pack-logs"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("saved for: compress the logs directory")
+    );
+    assert!(!sandbox.home().join(".you/cache/compress-logs.sh").exists());
+}
+
+/// An `$EDITOR` that replaces the edited file with `content`.
+fn editor_writing(sandbox: &Sandbox, content: &str) -> String {
+    let editor_path = sandbox.home().join("editor.sh");
+    std::fs::write(&editor_path, format!("printf '{}' > \"$1\"\n", content)).unwrap();
+
+    format!("sh {}", editor_path.display())
+}
+
+#[test]
+fn edited_scripts_are_kept_once_the_shell_parses_them() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+    let editor: String = editor_writing(&sandbox, "echo edited > edited.txt\\n");
+
+    let run =
+        sandbox.run_with_environment(&["edit", "compress-logs"], &[], &[("EDITOR", &editor)], "");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("Script 'compress-logs' has been updated.")
+    );
+    assert_eq!(
+        std::fs::read_to_string(sandbox.home().join(".you/cache/compress-logs.sh")).unwrap(),
+        "echo edited > edited.txt\n"
+    );
+}
+
+#[test]
+fn edited_scripts_the_shell_cannot_parse_are_restored() {
+    let sandbox = sandbox_with_cached_script(
+        "compress-logs",
+        "compress the logs directory",
+        "echo cached >> marker.txt",
+    );
+    let script_path = sandbox.home().join(".you/cache/compress-logs.sh");
+    let original_script: String = std::fs::read_to_string(&script_path).unwrap();
+    let editor: String = editor_writing(&sandbox, "if then (\\n");
+
+    let run = sandbox.run_with_environment(
+        &["edit", "compress-logs"],
+        &[],
+        &[("EDITOR", &editor)],
+        "n\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("The shell cannot parse the script"));
+    assert!(
+        run.stdout
+            .contains("The previous version has been restored.")
+    );
+    assert_eq!(
+        std::fs::read_to_string(&script_path).unwrap(),
+        original_script
+    );
+}
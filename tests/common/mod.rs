@@ -0,0 +1,203 @@
+#![allow(dead_code)]
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+use tempfile::TempDir;
+
+/// An isolated home and working directory for running the `you` binary
+/// against a scripted LLM.
+pub struct Sandbox {
+    home: TempDir,
+    working_directory: TempDir,
+}
+
+/// The output of one `you` invocation.
+pub struct Run {
+    pub stdout: String,
+    pub stderr: String,
+    pub output: Output,
+}
+
+impl Run {
+    pub fn success(&self) -> bool {
+        self.output.status.success()
+    }
+
+    pub fn code(&self) -> Option<i32> {
+        self.output.status.code()
+    }
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self {
+            home: TempDir::new().unwrap(),
+            working_directory: TempDir::new().unwrap(),
+        }
+    }
+
+    pub fn home(&self) -> &Path {
+        self.home.path()
+    }
+
+    pub fn working_directory(&self) -> &Path {
+        self.working_directory.path()
+    }
+
+    /// Write `~/.you/configurations.json` before `you` creates a default one.
+    pub fn write_configurations(&self, configurations: serde_json::Value) {
+        let you_home_directory: PathBuf = self.home.path().join(".you");
+        std::fs::create_dir_all(&you_home_directory).unwrap();
+        std::fs::write(
+            you_home_directory.join("configurations.json"),
+            serde_json::to_string_pretty(&configurations).unwrap(),
+        )
+        .unwrap();
+    }
+
+    /// Write the lines of an LLM script.
+    pub fn write_llm_script(&self, script_lines: &[serde_json::Value]) -> PathBuf {
+        let script_path: PathBuf = self.home.path().join("llm_script.jsonl");
+        let mut script: String = String::new();
+        for line in script_lines {
+            script.push_str(&line.to_string());
+            script.push('\n');
+        }
+        std::fs::write(&script_path, script).unwrap();
+
+        script_path
+    }
+
+    /// Run `you` with the given arguments, canned `(turn, response)` replies
+    /// and stdin.
+    pub fn run(&self, arguments: &[&str], responses: &[(usize, &str)], stdin: &str) -> Run {
+        let script_lines: Vec<serde_json::Value> = responses
+            .iter()
+            .map(|(turn, response)| serde_json::json!({ "turn": turn, "response": response }))
+            .collect();
+
+        self.run_with_llm_script(arguments, &script_lines, stdin)
+    }
+
+    /// Run `you` with the given arguments, raw LLM script lines and stdin.
+    pub fn run_with_llm_script(
+        &self,
+        arguments: &[&str],
+        script_lines: &[serde_json::Value],
+        stdin: &str,
+    ) -> Run {
+        self.run_with_environment(arguments, script_lines, &[], stdin)
+    }
+
+    /// Run `you` with the given arguments, raw LLM script lines, additional
+    /// environment variables and stdin.
+    pub fn run_with_environment(
+        &self,
+        arguments: &[&str],
+        script_lines: &[serde_json::Value],
+        environment: &[(&str, &str)],
+        stdin: &str,
+    ) -> Run {
+        let script_path: PathBuf = self.write_llm_script(script_lines);
+
+        let mut command: Command = self.command(arguments);
+        command
+            .env("YOU_LLM_SCRIPT", &script_path)
+            .envs(environment.iter().copied());
+
+        finish(command, stdin)
+    }
+
+    /// Run `you` against the LLM backend of the configurations, e.g. one on
+    /// `serve_http`, instead of a script.
+    pub fn run_with_configured_backend(&self, arguments: &[&str], stdin: &str) -> Run {
+        finish(self.command(arguments), stdin)
+    }
+
+    fn command(&self, arguments: &[&str]) -> Command {
+        let mut command: Command = Command::new(env!("CARGO_BIN_EXE_you"));
+        command
+            .args(arguments)
+            .current_dir(self.working_directory.path())
+            .env("HOME", self.home.path())
+            .env_remove("YOU_LLM_SCRIPT")
+            .env("NO_COLOR", "1");
+
+        command
+    }
+}
+
+/// Run `command` with `stdin`, and collect what it printed.
+fn finish(mut command: Command, stdin: &str) -> Run {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    let output: Output = child.wait_with_output().unwrap();
+
+    Run {
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        output,
+    }
+}
+
+/// Answers every HTTP request on a local port with `status` and `body`, for
+/// as long as the test runs. Returns the base URL.
+pub fn serve_http(status: &str, body: &str) -> String {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url: String = format!("http://{}", listener.local_addr().unwrap());
+    let response: String = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            read_request(&mut stream);
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    base_url
+}
+
+/// Reads a whole request, so that the client is done sending before it is answered.
+fn read_request(stream: &mut TcpStream) {
+    let mut reader: BufReader<&mut TcpStream> = BufReader::new(stream);
+    let mut content_length: usize = 0;
+    loop {
+        let mut line: String = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+
+    let mut body: Vec<u8> = vec![0; content_length];
+    let _ = reader.read_exact(&mut body);
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use common::{Run, Sandbox};
+use serde_json::{Value, json};
+
+/// The explain agent's reply. It comes first in the script, so that the explain
+/// agent does not pick up the replies meant for the run agent.
+fn explained(breakdown: Value) -> Value {
+    json!({
+        "turn": 1,
+        "system": "explains shell commands",
+        "response": json!({ "explanation": "Scripted explanation", "breakdown": breakdown }).to_string()
+    })
+}
+
+fn execute(turn: usize, command: &str) -> Value {
+    json!({
+        "turn": turn,
+        "response": json!({ "action": "execute", "command": command, "explanation": "Scripted command" }).to_string()
+    })
+}
+
+/// The JSON summary on the last line of stdout.
+fn summary(run: &Run) -> Value {
+    serde_json::from_str(run.stdout.lines().last().unwrap()).unwrap()
+}
+
+#[test]
+fn dry_run_never_executes() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run_with_llm_script(
+        &["run", "--dry-run", "create a file"],
+        &[
+            explained(json!([
+                { "token": "touch", "explanation": "creates empty files" },
+                { "token": "created.txt", "explanation": "the file to create" }
+            ])),
+            execute(1, "touch created.txt"),
+        ],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(!sandbox.working_directory().join("created.txt").exists());
+    assert!(run.stdout.contains("Dry run, nothing has been executed"));
+    assert!(run.stdout.contains("touch: creates empty files"));
+
+    let summary: Value = summary(&run);
+    assert_eq!(summary["request"], "create a file");
+    assert_eq!(summary["risk_level"], "low");
+    assert_eq!(summary["commands"][0]["command"], "touch created.txt");
+    assert_eq!(
+        summary["commands"][0]["breakdown"][1]["token"],
+        "created.txt"
+    );
+}
+
+#[test]
+fn dry_run_answers_questions_and_reports_the_risk() {
+    let sandbox = Sandbox::new();
+    std::fs::create_dir(sandbox.working_directory().join("logs")).unwrap();
+
+    let run = sandbox.run_with_llm_script(
+        &["run", "--dry-run", "clean up"],
+        &[
+            explained(json!([])),
+            json!({
+                "turn": 1,
+                "response": json!({
+                    "action": "request_information",
+                    "request_additional_information": "Which directory should I clean?"
+                }).to_string()
+            }),
+            json!({
+                "turn": 2,
+                "expect": "logs",
+                "response": json!({ "action": "execute", "command": "rm -rf logs", "explanation": "Delete the logs" }).to_string()
+            }),
+        ],
+        "logs\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Which directory should I clean?"));
+    assert!(sandbox.working_directory().join("logs").exists());
+    assert!(
+        run.stdout
+            .contains("! high risk: deletes files recursively")
+    );
+
+    let summary: Value = summary(&run);
+    assert_eq!(summary["risk_level"], "high");
+    assert_eq!(
+        summary["commands"][0]["risk_findings"][0]["category"],
+        "deletion"
+    );
+}
+
+#[test]
+fn dry_run_lists_every_step_of_a_plan() {
+    let sandbox = Sandbox::new();
+    let plan: String = json!({
+        "action": "plan",
+        "steps": [
+            { "command": "mkdir build", "explanation": "Create the build directory", "is_destructive": false },
+            { "command": "rm build/old.o", "explanation": "Remove stale objects", "is_destructive": true }
+        ]
+    })
+    .to_string();
+
+    let run = sandbox.run_with_llm_script(
+        &["run", "--dry-run", "prepare the build"],
+        &[explained(json!([])), json!({ "turn": 1, "response": plan })],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(!sandbox.working_directory().join("build").exists());
+    assert!(run.stdout.contains("2. > rm build/old.o"));
+    assert!(run.stdout.contains("! destructive"));
+
+    let summary: Value = summary(&run);
+    assert_eq!(summary["commands"].as_array().unwrap().len(), 2);
+    assert_eq!(summary["commands"][1]["is_destructive"], true);
+    assert_eq!(summary["risk_level"], "medium");
+}
+
+#[test]
+fn dry_run_needs_an_instruction() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(&["run", "--dry-run"], &[], "");
+
+    assert!(!run.success());
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+const FAILING_COMMAND: &str = r#"{"action": "execute", "command": "echo partial-output; echo broken-pipe >&2; exit 4", "explanation": "Fail on purpose"}"#;
+
+#[test]
+fn a_failed_command_is_fixed_by_the_agent() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run_with_llm_script(
+        &["run", "do something"],
+        &[
+            json!({ "turn": 1, "response": FAILING_COMMAND }),
+            json!({
+                "turn": 2,
+                "expect": "exited with code 4",
+                "response": r#"{"action": "execute", "command": "echo fixed", "explanation": "The corrected command"}"#
+            }),
+        ],
+        "y\ny\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("Asking the LLM to fix the command (1/3)...")
+    );
+    assert!(run.stdout.contains("The corrected command"));
+}
+
+#[test]
+fn the_agent_sees_stdout_and_stderr_separately() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run_with_llm_script(
+        &["run", "do something"],
+        &[
+            json!({ "turn": 1, "response": FAILING_COMMAND }),
+            json!({
+                "turn": 2,
+                "expect": "stdout:\npartial-output\n\nstderr:\nbroken-pipe\n",
+                "response": r#"{"action": "execute", "command": "echo fixed", "explanation": "The corrected command"}"#
+            }),
+        ],
+        "y\ny\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+}
+
+#[test]
+fn repairs_stop_at_the_configured_cap() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "repair_policy": { "max_rounds": 1 },
+        "preferred_clis": []
+    }));
+    let run = sandbox.run(
+        &["run", "do something"],
+        &[(1, FAILING_COMMAND), (2, FAILING_COMMAND)],
+        "y\ny\n",
+    );
+
+    // The exit code of the failed command is passed on
+    assert_eq!(run.code(), Some(4));
+    assert!(
+        run.stderr
+            .contains("The command still fails after 1 repair round(s)"),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn repairs_can_be_turned_off() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "repair_policy": { "max_rounds": 0 },
+        "preferred_clis": []
+    }));
+    let run = sandbox.run(&["run", "do something"], &[(1, FAILING_COMMAND)], "y\n");
+
+    assert!(!run.success());
+    assert!(!run.stdout.contains("Asking the LLM to fix the command"));
+}
+
+#[test]
+fn interactive_mode_feeds_failures_back_to_the_agent() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run_with_llm_script(
+        &["run"],
+        &[
+            json!({ "turn": 1, "response": FAILING_COMMAND }),
+            json!({
+                "turn": 2,
+                "expect": "broken-pipe",
+                "response": r#"{"action": "execute", "command": "echo fixed-interactively", "explanation": "The corrected command"}"#
+            }),
+        ],
+        "do something\ny\ny\ne\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("fixed-interactively"));
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::Sandbox;
+
+/// A sandbox with `script` cached as `name`.
+fn sandbox_with_script(name: &str, script: &str) -> Sandbox {
+    let sandbox = Sandbox::new();
+    let cache_directory = sandbox.home().join(".you/cache");
+    std::fs::create_dir_all(&cache_directory).unwrap();
+    std::fs::write(
+        cache_directory.join(format!("{}.sh", name)),
+        format!("#!/usr/bin/env sh\n{}", script),
+    )
+    .unwrap();
+
+    sandbox
+}
+
+#[test]
+fn arguments_are_passed_on_to_the_script() {
+    let sandbox = sandbox_with_script(
+        "log-arguments",
+        "for argument in \"$@\"; do echo \"$argument\" >> arguments.txt; done",
+    );
+
+    let run = sandbox.run(
+        &[
+            "exec",
+            "log-arguments",
+            "one",
+            "two words",
+            "--flag",
+            "it's",
+        ],
+        &[],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(
+        std::fs::read_to_string(sandbox.working_directory().join("arguments.txt")).unwrap(),
+        "one\ntwo words\n--flag\nit's\n"
+    );
+}
+
+#[test]
+fn the_exit_code_of_the_script_is_the_exit_code_of_you() {
+    let sandbox = sandbox_with_script("fail", "echo \"failing with $1\"; exit \"$1\"");
+
+    let run = sandbox.run(&["exec", "fail", "7"], &[], "");
+    assert_eq!(run.code(), Some(7));
+    assert!(run.stdout.contains("failing with 7"));
+
+    let run = sandbox.run(&["exec", "missing"], &[], "");
+    assert!(!run.success());
+    assert!(run.stderr.contains("Script 'missing' not found"));
+}
+
+#[test]
+fn cached_script_names_are_completed() {
+    let sandbox = sandbox_with_script("compress-logs", "echo compress");
+    // Completion only reads the configurations, the first run writes them
+    sandbox.run(&["list"], &[], "");
+
+    let run = sandbox.run_with_environment(
+        &["--", "you", "exec", "comp"],
+        &[],
+        &[("COMPLETE", "fish")],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("compress-logs"));
+}
+
+#[test]
+fn scripts_in_blocked_categories_are_refused() {
+    let outside_directory = tempfile::TempDir::new().unwrap();
+    let log_path = outside_directory.path().join("log.txt");
+    let sandbox = sandbox_with_script(
+        "log-outside",
+        &format!("echo line >> '{}'", log_path.display()),
+    );
+    sandbox.write_configurations(serde_json::json!({
+        "risk_policy": { "blocked_categories": ["write_outside_working_directory"] },
+        "preferred_clis": []
+    }));
+
+    let run = sandbox.run(&["exec", "log-outside"], &[], "");
+
+    assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stderr
+            .contains("The command is blocked by your configurations")
+    );
+    assert!(!log_path.exists());
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+/// A sandbox that does not wait between retries.
+fn sandbox_without_backoff() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "enable_cache": true,
+        "retry_policy": {
+            "max_attempts": 2,
+            "initial_backoff_milliseconds": 0,
+            "max_backoff_milliseconds": 0
+        },
+        "preferred_clis": []
+    }));
+
+    sandbox
+}
+
+#[test]
+fn the_exit_codes_are_documented_in_the_help() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(&["--help"], &[], "");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Exit codes:"));
+    assert!(
+        run.stdout
+            .contains("120  The user declined to run the command")
+    );
+}
+
+#[test]
+fn an_unreachable_llm_has_its_own_exit_code() {
+    let sandbox = Sandbox::new();
+    // Nothing listens on port 1, so every connection is refused
+    sandbox.write_configurations(json!({
+        "llm": {
+            "provider": "ollama",
+            "api_base": "http://127.0.0.1:1",
+            "model": "test-model"
+        },
+        "retry_policy": {
+            "max_attempts": 2,
+            "initial_backoff_milliseconds": 0,
+            "max_backoff_milliseconds": 0
+        },
+        "preferred_clis": []
+    }));
+    let run = sandbox.run_with_configured_backend(&["run", "say hello"], "");
+
+    assert_eq!(run.code(), Some(121), "{}{}", run.stdout, run.stderr);
+    assert!(run.stderr.contains("Cannot reach the LLM after 2 attempts"));
+}
+
+#[test]
+fn a_rejected_request_fails_with_its_own_error() {
+    let api_base: String = common::serve_http(
+        "401 Unauthorized",
+        r#"{"type": "error", "error": {"type": "authentication_error", "message": "invalid x-api-key"}}"#,
+    );
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "llm": {
+            "provider": "anthropic",
+            "api_base": api_base,
+            "api_key": "test-key",
+            "model": "test-model"
+        },
+        "retry_policy": {
+            "max_attempts": 2,
+            "initial_backoff_milliseconds": 0,
+            "max_backoff_milliseconds": 0
+        },
+        "preferred_clis": []
+    }));
+    let run = sandbox.run_with_configured_backend(&["run", "say hello"], "");
+
+    assert_eq!(run.code(), Some(1), "{}{}", run.stdout, run.stderr);
+    assert!(run.stderr.contains("invalid x-api-key"));
+    assert!(!run.stderr.contains("Cannot reach the LLM"));
+}
+
+#[test]
+fn invalid_configurations_have_their_own_exit_code() {
+    let sandbox = Sandbox::new();
+    std::fs::create_dir_all(sandbox.home().join(".you")).unwrap();
+    std::fs::write(
+        sandbox.home().join(".you/configurations.json"),
+        "{ \"enable_cache\": ",
+    )
+    .unwrap();
+
+    let run = sandbox.run(&["list"], &[], "");
+
+    assert_eq!(run.code(), Some(122), "{}{}", run.stdout, run.stderr);
+    assert!(run.stderr.contains("configurations.json cannot be parsed"));
+}
+
+#[test]
+fn declining_a_cached_script_has_its_own_exit_code() {
+    let sandbox = sandbox_without_backoff();
+    let cache_directory = sandbox.home().join(".you/cache");
+    std::fs::create_dir_all(&cache_directory).unwrap();
+    std::fs::write(cache_directory.join("wipe.sh"), "rm -rf build").unwrap();
+
+    let run = sandbox.run(&["exec", "wipe"], &[], "no\n");
+
+    assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Nothing has been run."));
+}
+
+#[test]
+fn declining_a_proposed_command_has_its_own_exit_code() {
+    let sandbox = sandbox_without_backoff();
+    let reply: String = json!({
+        "action": "execute",
+        "command": "echo hello > marker.txt",
+        "explanation": "Says hello"
+    })
+    .to_string();
+
+    // Answered with `n`, and with stdin closed before any answer
+    for stdin in ["n\n", ""] {
+        let run = sandbox.run(&["run", "say hi"], &[(1, &reply)], stdin);
+
+        assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+        assert!(run.stderr.contains("Declined by the user"));
+        assert!(!sandbox.working_directory().join("marker.txt").exists());
+    }
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+fn execute(command: &str) -> String {
+    json!({ "action": "execute", "command": command, "explanation": "Scripted command" })
+        .to_string()
+}
+
+/// Runs `prompt` through the agent, approving `command` and declining to save it.
+fn run_approved(sandbox: &Sandbox, prompt: &str, command: &str) {
+    let run = sandbox.run(&["run", prompt], &[(1, &execute(command))], "y\nn\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+}
+
+/// Ids of the recorded sessions, oldest first.
+fn session_ids(sandbox: &Sandbox) -> Vec<String> {
+    let mut ids: Vec<String> = std::fs::read_dir(sandbox.home().join(".you/history"))
+        .unwrap()
+        .map(|file| {
+            file.unwrap()
+                .path()
+                .file_stem()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    ids.sort();
+
+    ids
+}
+
+#[test]
+fn runs_are_listed_with_their_outcome() {
+    let sandbox = Sandbox::new();
+
+    run_approved(&sandbox, "say hello", "echo hello");
+    let run = sandbox.run(&["history"], &[], "");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains(&session_ids(&sandbox)[0]));
+    assert!(run.stdout.contains("say hello"));
+    assert!(
+        run.stdout
+            .contains("1 command(s), the last exited with code 0")
+    );
+}
+
+#[test]
+fn show_displays_proposals_decisions_and_executions() {
+    let sandbox = Sandbox::new();
+
+    let run = sandbox.run(
+        &["run", "fail then succeed"],
+        &[(1, &execute("exit 3")), (2, &execute("echo fixed"))],
+        "y\ny\nn\n",
+    );
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+
+    let id: String = session_ids(&sandbox).remove(0);
+    let run = sandbox.run(&["history", "show", &id], &[], "");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Prompt: fail then succeed"));
+    assert!(run.stdout.contains("proposed: `exit 3` (Scripted command)"));
+    assert!(run.stdout.contains("answered: y"));
+    assert!(
+        run.stdout
+            .contains("ran `exit 3`, which exited with code 3")
+    );
+    assert!(
+        run.stdout
+            .contains("ran `echo fixed`, which exited with code 0")
+    );
+    assert!(
+        run.stdout
+            .contains(&sandbox.working_directory().display().to_string())
+    );
+}
+
+#[test]
+fn search_matches_prompts_and_commands() {
+    let sandbox = Sandbox::new();
+
+    run_approved(&sandbox, "greet the world", "echo hello");
+    run_approved(&sandbox, "show the date", "date");
+
+    let run = sandbox.run(&["history", "search", "HELLO"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("greet the world"));
+    assert!(!run.stdout.contains("show the date"));
+
+    let run = sandbox.run(&["history", "search", "nowhere"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("No sessions match `nowhere`."));
+}
+
+#[test]
+fn rerun_executes_the_succeeded_commands_again() {
+    let sandbox = Sandbox::new();
+    let log_path = sandbox.working_directory().join("log.txt");
+
+    run_approved(&sandbox, "log a line", "echo line >> log.txt");
+    let id: String = session_ids(&sandbox).remove(0);
+
+    let run = sandbox.run(&["history", "rerun", &id], &[], "n\n");
+    assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Nothing has been run."));
+    assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "line\n");
+
+    let run = sandbox.run(&["history", "rerun", &id], &[], "y\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "line\nline\n");
+
+    // The re-run is a session of its own
+    assert_eq!(session_ids(&sandbox).len(), 2);
+}
+
+#[test]
+fn rerun_refuses_commands_in_blocked_categories() {
+    let sandbox = Sandbox::new();
+    let outside_directory = tempfile::TempDir::new().unwrap();
+    let log_path = outside_directory.path().join("log.txt");
+
+    run_approved(
+        &sandbox,
+        "log a line",
+        &format!("echo line >> '{}'", log_path.display()),
+    );
+    let id: String = session_ids(&sandbox).remove(0);
+    sandbox.write_configurations(json!({
+        "risk_policy": { "blocked_categories": ["write_outside_working_directory"] },
+        "preferred_clis": []
+    }));
+
+    let run = sandbox.run(&["history", "rerun", &id], &[], "y\n");
+    assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stderr
+            .contains("The command is blocked by your configurations")
+    );
+    assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "line\n");
+}
+
+#[test]
+fn only_the_most_recent_sessions_are_kept() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "history": { "max_sessions": 2 },
+        "preferred_clis": []
+    }));
+
+    run_approved(&sandbox, "first session", "echo first");
+    run_approved(&sandbox, "second session", "echo second");
+    run_approved(&sandbox, "third session", "echo third");
+
+    let run = sandbox.run(&["history"], &[], "");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(session_ids(&sandbox).len(), 2);
+    assert!(!run.stdout.contains("first session"));
+    assert!(run.stdout.contains("second session"));
+    assert!(run.stdout.contains("third session"));
+}
+
+#[test]
+fn nothing_is_recorded_when_the_history_is_disabled() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "history": { "enabled": false },
+        "preferred_clis": []
+    }));
+
+    run_approved(&sandbox, "say hello", "echo hello");
+    let run = sandbox.run(&["history"], &[], "");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("No sessions found."));
+}
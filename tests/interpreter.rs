@@ -0,0 +1,142 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+/// A reply that writes the name of the shell it runs in to marker.txt, only used when the
+/// system prompt tells the LLM about `shell`.
+fn write_shell_name_for(shell: &str) -> serde_json::Value {
+    json!({
+        "turn": 1,
+        "system": format!("Shell: {}\n", shell),
+        "response": json!({
+            "action": "execute",
+            "command": "echo \"$0\" > marker.txt",
+            "explanation": "Writes the name of the shell"
+        })
+        .to_string()
+    })
+}
+
+fn read_marker(sandbox: &Sandbox) -> String {
+    std::fs::read_to_string(sandbox.working_directory().join("marker.txt")).unwrap()
+}
+
+#[test]
+fn commands_run_in_the_login_shell() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run_with_environment(
+        &["run", "which shell is this"],
+        &[write_shell_name_for("/bin/bash")],
+        &[("SHELL", "/bin/bash")],
+        "y\nn\n",
+    );
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(read_marker(&sandbox), "/bin/bash\n");
+
+    // Without a login shell, commands run in `sh`
+    let run = sandbox.run_with_environment(
+        &["run", "which shell is this"],
+        &[write_shell_name_for("sh")],
+        &[("SHELL", "")],
+        "y\nn\n",
+    );
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(read_marker(&sandbox), "sh\n");
+}
+
+#[test]
+fn the_configured_interpreter_runs_and_saves_commands() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "interpreter": "bash",
+        "preferred_clis": []
+    }));
+    let reply: String = json!({
+        "action": "execute",
+        "command": "[[ -n \"$BASH_VERSION\" ]] && echo bash-only > marker.txt",
+        "explanation": "Writes a marker with a bash-ism"
+    })
+    .to_string();
+
+    let run = sandbox.run_with_environment(
+        &["run", "write a marker"],
+        &[json!({ "turn": 1, "system": "Shell: bash\n", "response": reply })],
+        &[("SHELL", "/bin/sh")],
+        "y\nmark\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(read_marker(&sandbox), "bash-only\n");
+    let saved_script: String =
+        std::fs::read_to_string(sandbox.working_directory().join("mark.sh")).unwrap();
+    assert!(saved_script.starts_with("#!/usr/bin/env bash\n"));
+}
+
+#[test]
+fn cached_scripts_run_in_the_interpreter_of_their_shebang() {
+    let sandbox = Sandbox::new();
+    let cache_directory = sandbox.home().join(".you/cache");
+    std::fs::create_dir_all(&cache_directory).unwrap();
+    // Saved without any metadata, as before scripts were indexed
+    std::fs::write(
+        cache_directory.join("bash-only.sh"),
+        "#!/usr/bin/env bash\n[[ -n \"$BASH_VERSION\" ]] && echo bash-only > marker.txt\n",
+    )
+    .unwrap();
+    std::fs::write(
+        cache_directory.join("no-shebang.sh"),
+        "echo \"$0\" > marker.txt\n",
+    )
+    .unwrap();
+
+    let run =
+        sandbox.run_with_environment(&["exec", "bash-only"], &[], &[("SHELL", "/bin/sh")], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(read_marker(&sandbox), "bash-only\n");
+
+    // Without a shebang, the script runs in `sh` rather than the login shell
+    let run =
+        sandbox.run_with_environment(&["exec", "no-shebang"], &[], &[("SHELL", "/bin/bash")], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(read_marker(&sandbox), "no-shebang\n");
+}
+
+#[test]
+fn cached_scripts_run_by_name_or_lookup_use_their_shebang() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "enable_cache": true,
+        "preferred_clis": []
+    }));
+    let reply: String = json!({
+        "action": "execute",
+        "command": "echo {{word}} > marker.txt",
+        "explanation": "Writes a word",
+        "parameters": [
+            { "name": "word", "kind": "string", "description": "word to write", "default": "default" }
+        ]
+    })
+    .to_string();
+    let run = sandbox.run(
+        &["run", "write a word"],
+        &[(1, &reply)],
+        "y\nwrite-word\n\n",
+    );
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+
+    // Rewritten in Python, which the shell cannot run
+    std::fs::write(
+        sandbox.home().join(".you/cache/write-word.sh"),
+        "#!/usr/bin/env python3\nopen('marker.txt', 'w').write({{word}} + '\\n')\n",
+    )
+    .unwrap();
+
+    let run = sandbox.run(&["run", "write-word", "--param", "word=python"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(read_marker(&sandbox), "python\n");
+
+    let run = sandbox.run(&["run", "write a word"], &[], "1\n\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(read_marker(&sandbox), "default\n");
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::{Run, Sandbox};
+use serde_json::{Value, json};
+
+/// Every line of stdout, which must each be a JSON event.
+fn parse_events(run: &Run) -> Vec<Value> {
+    run.stdout
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|error| panic!("`{}` is not JSON: {}", line, error))
+        })
+        .collect()
+}
+
+fn find_event<'a>(events: &'a [Value], event: &str) -> Vec<&'a Value> {
+    events
+        .iter()
+        .filter(|value| value["event"] == event)
+        .collect()
+}
+
+#[test]
+fn runs_are_reported_as_json_lines() {
+    let sandbox = Sandbox::new();
+    let reply: String = json!({
+        "action": "execute",
+        "command": "echo hello-from-you",
+        "explanation": "Print a greeting"
+    })
+    .to_string();
+    let run = sandbox.run(
+        &["--output", "json", "run", "say hello"],
+        &[(1, &reply)],
+        "y\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    let events: Vec<Value> = parse_events(&run);
+
+    let sessions: Vec<&Value> = find_event(&events, "session");
+    assert_eq!(sessions[0]["kind"], "proposal");
+    assert_eq!(sessions[0]["action"]["command"], "echo hello-from-you");
+    assert_eq!(sessions[1]["kind"], "decision");
+    assert_eq!(sessions[1]["input"], "y\n");
+    assert_eq!(sessions[2]["kind"], "execution");
+    assert_eq!(sessions[2]["exit_code"], 0);
+
+    let outputs: Vec<&Value> = find_event(&events, "output");
+    assert_eq!(outputs[0]["stream"], "stdout");
+    assert_eq!(outputs[0]["text"], "hello-from-you\n");
+
+    assert!(!find_event(&events, "prompt").is_empty());
+    assert_eq!(
+        events.last().unwrap(),
+        &json!({ "event": "exit", "code": 0 })
+    );
+}
+
+#[test]
+fn explanations_and_scripts_are_reported_as_json() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["explain", "ls -la", "--output", "json"],
+        &[(
+            1,
+            r#"{"explanation": "Lists every file with details", "breakdown": [{"token": "ls", "explanation": "lists directory contents"}]}"#,
+        )],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    let events: Vec<Value> = parse_events(&run);
+    let explanation: &Value = find_event(&events, "explanation")[0];
+    assert_eq!(explanation["command"], "ls -la");
+    assert_eq!(
+        explanation["explanation"]["breakdown"][0]["token"],
+        json!("ls")
+    );
+
+    let cache_directory = sandbox.home().join(".you/cache");
+    std::fs::write(cache_directory.join("show-date.sh"), "date").unwrap();
+    let run = sandbox.run(&["list", "--output", "json"], &[], "");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    let events: Vec<Value> = parse_events(&run);
+    let scripts: &Value = &find_event(&events, "scripts")[0]["scripts"];
+    assert_eq!(scripts[0]["name"], "show-date");
+    assert_eq!(scripts[0]["source"], "personal");
+}
+
+#[test]
+fn errors_are_reported_with_the_exit_code() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(&["--output", "json", "show", "missing"], &[], "");
+
+    assert_eq!(run.code(), Some(1));
+    let events: Vec<Value> = parse_events(&run);
+    assert!(
+        find_event(&events, "error")[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Script 'missing' not found")
+    );
+    assert_eq!(
+        events.last().unwrap(),
+        &json!({ "event": "exit", "code": 1 })
+    );
+}
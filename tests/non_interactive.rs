@@ -0,0 +1,141 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+fn execute(command: &str) -> String {
+    json!({ "action": "execute", "command": command, "explanation": "Scripted command" })
+        .to_string()
+}
+
+const ASK_FOR_DIRECTORY: &str = r#"{"action": "request_information", "request_additional_information": "Which directory should I look in?"}"#;
+
+#[test]
+fn read_only_commands_run_without_prompting() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "--yes", "say hello"],
+        &[(1, &execute("echo hello-from-you | tr a-z A-Z"))],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("HELLO-FROM-YOU"));
+    assert!(
+        run.stdout
+            .contains(r#"prompt="approve_command" answer="y" reason="read-only""#)
+    );
+    assert!(run.stdout.contains(r#"prompt="save_script" answer="n""#));
+}
+
+#[test]
+fn other_commands_only_run_when_allowed_by_the_policy() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "--yes", "create a file"],
+        &[(1, &execute("touch created.txt"))],
+        "",
+    );
+
+    assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stderr
+            .contains("`touch created.txt` is neither read-only nor allowed")
+    );
+    assert!(!sandbox.working_directory().join("created.txt").exists());
+
+    sandbox.write_configurations(json!({
+        "approval_policy": { "allowed_commands": ["^touch [a-z]+\\.txt$"] },
+        "preferred_clis": []
+    }));
+    let run = sandbox.run(
+        &["run", "--yes", "create a file"],
+        &[(1, &execute("touch created.txt"))],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains(r#"reason="matches `^touch [a-z]+\\.txt$`""#)
+    );
+    assert!(sandbox.working_directory().join("created.txt").exists());
+}
+
+#[test]
+fn allowed_commands_do_not_approve_chained_commands() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "approval_policy": { "allowed_commands": ["^touch "] },
+        "preferred_clis": []
+    }));
+
+    for command in [
+        "touch created.txt; touch chained.txt",
+        "touch created.txt && touch chained.txt",
+        "touch created.txt | tee chained.txt",
+        "touch created.txt > chained.txt",
+        "touch created.txt $(touch chained.txt)",
+    ] {
+        let run = sandbox.run(
+            &["run", "--yes", "create a file"],
+            &[(1, &execute(command))],
+            "",
+        );
+
+        assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+        assert!(!sandbox.working_directory().join("created.txt").exists());
+        assert!(!sandbox.working_directory().join("chained.txt").exists());
+    }
+}
+
+#[test]
+fn questions_are_answered_with_the_context_or_stop_you() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run_with_llm_script(
+        &[
+            "run",
+            "--yes",
+            "--context",
+            "look in ./logs",
+            "count the logs",
+        ],
+        &[
+            json!({ "turn": 1, "response": ASK_FOR_DIRECTORY }),
+            json!({ "turn": 2, "expect": "look in ./logs", "response": execute("echo counted") }),
+        ],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains(r#"prompt="answer_question" answer="look in ./logs""#)
+    );
+    assert!(run.stdout.contains("counted"));
+
+    let run = sandbox.run(
+        &["run", "--yes", "count the logs"],
+        &[(1, ASK_FOR_DIRECTORY)],
+        "",
+    );
+
+    assert_eq!(run.code(), Some(1), "{}{}", run.stdout, run.stderr);
+    assert!(run.stderr.contains("Which directory should I look in?"));
+}
+
+#[test]
+fn cached_scripts_are_approved_by_the_policy_too() {
+    let sandbox = Sandbox::new();
+    let cache_directory = sandbox.home().join(".you/cache");
+    std::fs::create_dir_all(&cache_directory).unwrap();
+    std::fs::write(cache_directory.join("show-files.sh"), "ls").unwrap();
+    std::fs::write(cache_directory.join("wipe.sh"), "rm -rf build").unwrap();
+
+    let run = sandbox.run(&["exec", "--yes", "show-files"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+
+    let run = sandbox.run(&["exec", "--yes", "wipe"], &[], "yes\n");
+    assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+    assert!(!run.stdout.contains("Using the cached script wipe"));
+}
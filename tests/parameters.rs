@@ -0,0 +1,152 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+/// Writes `count` to a file in `dir`, both parameters of the script.
+fn execute_with_parameters() -> String {
+    json!({
+        "action": "execute",
+        "command": "mkdir -p {{dir}} && echo {{count}} > {{dir}}/count.txt",
+        "explanation": "Write the count to a file in the directory",
+        "parameters": [
+            { "name": "dir", "kind": "path", "description": "directory to write to", "default": "first" },
+            { "name": "count", "kind": "int", "description": "number to write", "default": "1" }
+        ]
+    })
+    .to_string()
+}
+
+/// A sandbox with the cache enabled and the parameterized script saved as `write-count`.
+fn sandbox_with_parameterized_script() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "enable_cache": true,
+        "preferred_clis": []
+    }));
+
+    let run = sandbox.run(
+        &["run", "write a count to a file"],
+        &[(1, &execute_with_parameters())],
+        "y\nwrite-count\n\n",
+    );
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Shell had been saved to the cache."));
+
+    sandbox
+}
+
+fn read_count(sandbox: &Sandbox, dir: &str) -> String {
+    std::fs::read_to_string(sandbox.working_directory().join(dir).join("count.txt")).unwrap()
+}
+
+#[test]
+fn proposals_run_with_the_defaults_and_are_cached_as_templates() {
+    let sandbox = sandbox_with_parameterized_script();
+
+    assert_eq!(read_count(&sandbox, "first"), "1\n");
+
+    let script: String =
+        std::fs::read_to_string(sandbox.home().join(".you/cache/write-count.sh")).unwrap();
+    assert!(script.contains("mkdir -p {{dir}} && echo {{count}} > {{dir}}/count.txt"));
+    let index: String =
+        std::fs::read_to_string(sandbox.home().join(".you/cache/index.json")).unwrap();
+    assert!(index.contains("\"kind\": \"path\""));
+    assert!(index.contains("\"default\": \"first\""));
+}
+
+#[test]
+fn params_fill_in_the_cached_script_and_are_quoted() {
+    let sandbox = sandbox_with_parameterized_script();
+
+    let run = sandbox.run(
+        &[
+            "run",
+            "write-count",
+            "--param",
+            "dir=it's here; touch injected",
+            "--param",
+            "count=7",
+        ],
+        &[],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(read_count(&sandbox, "it's here; touch injected"), "7\n");
+    assert!(!sandbox.working_directory().join("injected").exists());
+}
+
+#[test]
+fn picked_cached_scripts_ask_for_their_parameters() {
+    let sandbox = sandbox_with_parameterized_script();
+
+    // The directory is given, the count keeps its default
+    let run = sandbox.run(&["run", "write a count to a file"], &[], "1\nsecond\n\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("$ dir (path): directory to write to [first]")
+    );
+    assert!(
+        run.stdout
+            .contains("dir (path, directory to write to) [first]:")
+    );
+    assert_eq!(read_count(&sandbox, "second"), "1\n");
+}
+
+#[test]
+fn invalid_params_are_rejected_before_running() {
+    let sandbox = sandbox_with_parameterized_script();
+
+    let run = sandbox.run(
+        &[
+            "run",
+            "write-count",
+            "--param",
+            "count=many",
+            "--param",
+            "dir=third",
+        ],
+        &[],
+        "",
+    );
+    assert!(!run.success());
+    assert!(
+        run.stderr
+            .contains("`count` must be a whole number, not `many`")
+    );
+
+    let run = sandbox.run(&["run", "write-count", "--param", "size=3"], &[], "");
+    assert!(!run.success());
+    assert!(run.stderr.contains("The script has no parameter `size`"));
+
+    let run = sandbox.run(&["run", "missing-script", "--param", "dir=third"], &[], "");
+    assert!(!run.success());
+    assert!(run.stderr.contains("Script 'missing-script' not found"));
+
+    assert!(!sandbox.working_directory().join("third").exists());
+}
+
+#[test]
+fn values_are_not_substituted_again() {
+    let sandbox = sandbox_with_parameterized_script();
+
+    let run = sandbox.run(
+        &[
+            "run",
+            "write-count",
+            "--param",
+            "dir={{count}}",
+            "--param",
+            "count=5",
+        ],
+        &[],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(read_count(&sandbox, "{{count}}"), "5\n");
+    assert!(!sandbox.working_directory().join("5").exists());
+}
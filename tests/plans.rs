@@ -0,0 +1,127 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::{Value, json};
+
+fn plan(steps: Value) -> String {
+    json!({ "action": "plan", "steps": steps }).to_string()
+}
+
+fn two_step_plan() -> String {
+    plan(json!([
+        { "command": "echo first-step", "explanation": "Print the first step", "is_destructive": false },
+        { "command": "echo second-step", "explanation": "Print the second step", "is_destructive": true }
+    ]))
+}
+
+#[test]
+fn each_step_is_approved_separately() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "print two steps"],
+        &[(1, &two_step_plan())],
+        // The destructive step needs the full word
+        "y\ny\nyes\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Step 1/2"));
+    assert!(run.stdout.contains("Step 2 is destructive"));
+    assert!(run.stdout.contains("first-step"));
+    assert!(run.stdout.contains("second-step"));
+    assert!(
+        run.stdout
+            .contains("Commands had been executed successfully.")
+    );
+}
+
+#[test]
+fn skipped_steps_are_neither_run_nor_saved() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "print two steps"],
+        &[(1, &two_step_plan())],
+        "y\ns\nyes\nsteps\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        !run.stdout
+            .contains("Start executing command: echo first-step")
+    );
+    assert!(
+        run.stdout
+            .contains("Start executing command: echo second-step")
+    );
+
+    let script: String =
+        std::fs::read_to_string(sandbox.working_directory().join("steps.sh")).unwrap();
+    assert!(!script.contains("first-step"));
+    assert!(script.contains("echo second-step"));
+}
+
+#[test]
+fn edited_steps_run_the_edited_command() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "print two steps"],
+        &[(1, &two_step_plan())],
+        "y\ne\necho edited-step\ny\ns\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("edited-step"));
+    assert!(
+        !run.stdout
+            .contains("Start executing command: echo first-step")
+    );
+}
+
+#[test]
+fn aborting_stops_the_plan() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "print two steps"],
+        &[(1, &two_step_plan())],
+        "y\ny\na\n",
+    );
+
+    assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("Start executing command: echo first-step")
+    );
+    assert!(
+        !run.stdout
+            .contains("Start executing command: echo second-step")
+    );
+    assert!(run.stdout.contains("The plan has been aborted."));
+}
+
+#[test]
+fn a_failing_step_is_reported_back_to_the_agent() {
+    let sandbox = Sandbox::new();
+    let failing_plan: String = plan(json!([
+        { "command": "exit 3", "explanation": "Fail on purpose", "is_destructive": false },
+        { "command": "echo never-run", "explanation": "Never reached", "is_destructive": false }
+    ]));
+    let run = sandbox.run(
+        &["run", "try something"],
+        &[
+            (1, &failing_plan),
+            // The report of the failed plan is the second user turn
+            (
+                2,
+                r#"{"action": "execute", "command": "echo recovered", "explanation": "Recover"}"#,
+            ),
+        ],
+        "y\ny\ny\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        !run.stdout
+            .contains("Start executing command: echo never-run")
+    );
+    assert!(run.stdout.contains("recovered"));
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use std::process::{Command, Stdio};
+
+use common::Sandbox;
+use serde_json::json;
+
+fn execute(command: &str) -> String {
+    json!({ "action": "execute", "command": command, "explanation": "Scripted command" })
+        .to_string()
+}
+
+/// Whether this machine can set up the sandbox at all, e.g. not on macOS or in
+/// containers without user namespaces.
+fn is_sandbox_available() -> bool {
+    let probe = |program: &str, arguments: &[&str]| {
+        Command::new(program)
+            .args(arguments)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+
+    cfg!(target_os = "linux")
+        && (probe("bwrap", &["--ro-bind", "/", "/", "true"])
+            || probe("unshare", &["--user", "--map-root-user", "--mount", "true"]))
+}
+
+/// A working directory with one file to modify, one to delete and one to keep.
+fn populated_sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    std::fs::write(sandbox.working_directory().join("notes.txt"), "old\n").unwrap();
+    std::fs::write(sandbox.working_directory().join("obsolete.txt"), "bye\n").unwrap();
+    std::fs::write(sandbox.working_directory().join("kept.txt"), "kept\n").unwrap();
+
+    sandbox
+}
+
+const CHANGING_COMMAND: &str = "echo new > notes.txt && rm obsolete.txt && mkdir -p reports/2024 && echo done > reports/2024/summary.txt";
+
+#[test]
+fn committed_changes_reach_the_working_directory() {
+    if !is_sandbox_available() {
+        eprintln!("Skipped, no sandbox can be set up here");
+        return;
+    }
+
+    let sandbox = populated_sandbox();
+    let run = sandbox.run(
+        &["run", "--sandbox", "tidy up"],
+        &[(1, &execute(CHANGING_COMMAND))],
+        "y\nc\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("~ notes.txt"));
+    assert!(run.stdout.contains("- obsolete.txt"));
+    assert!(run.stdout.contains("+ reports/2024/summary.txt"));
+    assert!(!run.stdout.contains("kept.txt"));
+
+    let working_directory = sandbox.working_directory();
+    assert_eq!(
+        std::fs::read_to_string(working_directory.join("notes.txt")).unwrap(),
+        "new\n"
+    );
+    assert!(!working_directory.join("obsolete.txt").exists());
+    assert_eq!(
+        std::fs::read_to_string(working_directory.join("reports/2024/summary.txt")).unwrap(),
+        "done\n"
+    );
+}
+
+#[test]
+fn discarded_changes_leave_the_working_directory_untouched() {
+    if !is_sandbox_available() {
+        eprintln!("Skipped, no sandbox can be set up here");
+        return;
+    }
+
+    let sandbox = populated_sandbox();
+    let run = sandbox.run(
+        &["run", "--sandbox", "tidy up"],
+        &[(1, &execute(CHANGING_COMMAND))],
+        "y\nd\n",
+    );
+
+    assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("The changes have been discarded."));
+
+    let working_directory = sandbox.working_directory();
+    assert_eq!(
+        std::fs::read_to_string(working_directory.join("notes.txt")).unwrap(),
+        "old\n"
+    );
+    assert!(working_directory.join("obsolete.txt").exists());
+    assert!(!working_directory.join("reports").exists());
+}
+
+#[test]
+fn the_rest_of_the_file_system_is_read_only() {
+    if !is_sandbox_available() {
+        eprintln!("Skipped, no sandbox can be set up here");
+        return;
+    }
+
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "sandbox": { "enabled": true },
+        "repair_policy": { "max_rounds": 0 },
+        "preferred_clis": []
+    }));
+    let run = sandbox.run(
+        &["run", "write next to the working directory"],
+        &[(1, &execute("touch ../escaped.txt"))],
+        "y\n",
+    );
+
+    assert!(!run.success());
+    assert!(
+        run.stdout.contains("Read-only file system"),
+        "{}",
+        run.stdout
+    );
+    assert!(
+        !sandbox
+            .working_directory()
+            .parent()
+            .unwrap()
+            .join("escaped.txt")
+            .exists()
+    );
+}
+
+#[test]
+fn a_cached_script_whose_changes_are_discarded_is_declined() {
+    if !is_sandbox_available() {
+        eprintln!("Skipped, no sandbox can be set up here");
+        return;
+    }
+
+    let sandbox = populated_sandbox();
+    sandbox.write_configurations(json!({
+        "sandbox": { "enabled": true },
+        "preferred_clis": []
+    }));
+    let cache_directory = sandbox.home().join(".you/cache");
+    std::fs::create_dir_all(&cache_directory).unwrap();
+    std::fs::write(
+        cache_directory.join("tidy-up.sh"),
+        format!("#!/usr/bin/env sh\n{}\n", CHANGING_COMMAND),
+    )
+    .unwrap();
+
+    let run = sandbox.run(&["exec", "tidy-up"], &[], "d\n");
+
+    assert_eq!(run.code(), Some(120), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("The changes have been discarded."));
+    assert_eq!(
+        std::fs::read_to_string(sandbox.working_directory().join("notes.txt")).unwrap(),
+        "old\n"
+    );
+}
+
+#[test]
+fn working_directories_beyond_the_limits_are_not_copied() {
+    if !is_sandbox_available() {
+        eprintln!("Skipped, no sandbox can be set up here");
+        return;
+    }
+
+    let sandbox = populated_sandbox();
+    sandbox.write_configurations(json!({
+        "sandbox": { "enabled": true, "max_files": 2 },
+        "repair_policy": { "max_rounds": 0 },
+        "preferred_clis": []
+    }));
+    let run = sandbox.run(
+        &["run", "tidy up"],
+        &[(1, &execute(CHANGING_COMMAND))],
+        "y\n",
+    );
+
+    assert!(!run.success());
+    assert!(
+        run.stdout
+            .contains("has more than 2 files or 1024 MB to copy"),
+        "{}",
+        run.stdout
+    );
+    assert!(sandbox.working_directory().join("obsolete.txt").exists());
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+const EXECUTE_ECHO: &str =
+    r#"{"action": "execute", "command": "echo hello-from-you", "explanation": "Print a greeting"}"#;
+
+#[test]
+fn untagged_replies_are_rejected_with_the_allowed_actions() {
+    let sandbox = Sandbox::new();
+    let untagged: &str = r#"{"command": "echo hello-from-you", "explanation": "Print a greeting"}"#;
+    let run = sandbox.run(
+        &["run", "say hello"],
+        &[(1, untagged), (2, untagged), (3, untagged)],
+        "y\nn\n",
+    );
+
+    assert!(!run.success());
+    assert!(
+        run.stderr.contains(
+            r#"$: missing required field `action`, expected one of "execute", "request_information", "request_clis_to_install""#
+        ),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn unknown_actions_are_reported_by_path() {
+    let sandbox = Sandbox::new();
+    let unknown: &str = r#"{"action": "launch_rockets"}"#;
+    let run = sandbox.run(
+        &["run", "say hello"],
+        &[(1, unknown), (2, unknown), (3, unknown)],
+        "y\nn\n",
+    );
+
+    assert!(!run.success());
+    assert!(
+        run.stderr
+            .contains(r#"$.action: expected one of "execute""#),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn fields_of_the_wrong_type_are_reported_by_path() {
+    let sandbox = Sandbox::new();
+    let wrong_type: &str = r#"{"action": "request_clis_to_install", "request_clis_to_install": [{"cli_name": 7, "suggested_installation_command": "brew install fd"}]}"#;
+    let run = sandbox.run(
+        &["run", "find files"],
+        &[(1, wrong_type), (2, wrong_type), (3, wrong_type)],
+        "",
+    );
+
+    assert!(!run.success());
+    assert!(
+        run.stderr
+            .contains("$.request_clis_to_install[0].cli_name: expected string, found number"),
+        "{}",
+        run.stderr
+    );
+}
+
+#[test]
+fn a_reply_that_follows_the_schema_after_a_repair_is_executed() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["run", "say hello"],
+        &[
+            (
+                1,
+                r#"{"action": "execute", "command": "echo hello-from-you"}"#,
+            ),
+            (2, EXECUTE_ECHO),
+        ],
+        "y\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("LLM returned a wrong JSON, retrying...")
+    );
+    assert!(run.stdout.contains("hello-from-you"));
+}
+
+#[test]
+fn tool_calls_are_validated_against_the_schema() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "llm": { "enable_tool_calling": true },
+        "preferred_clis": []
+    }));
+    let invalid_call = json!({
+        "turn": 1,
+        "tool_call": { "name": "execute", "arguments": { "command": ["echo", "hi"] } }
+    });
+    let run = sandbox.run_with_llm_script(
+        &["run", "say hello"],
+        &[
+            invalid_call.clone(),
+            json!({ "turn": 2, "tool_call": invalid_call["tool_call"] }),
+            json!({ "turn": 3, "tool_call": invalid_call["tool_call"] }),
+        ],
+        "",
+    );
+
+    assert!(!run.success());
+    assert!(
+        run.stderr
+            .contains("$.command: expected string, found array"),
+        "{}",
+        run.stderr
+    );
+    assert!(
+        run.stderr
+            .contains("$: missing required field `explanation`"),
+        "{}",
+        run.stderr
+    );
+}
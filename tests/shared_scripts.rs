@@ -0,0 +1,121 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+/// A sandbox with the cache enabled and a shared directory of scripts, with `compress-logs`
+/// described in its index and `show-date` without any metadata.
+fn sandbox_with_shared_directory() -> (Sandbox, std::path::PathBuf) {
+    let sandbox = Sandbox::new();
+    let shared_directory = sandbox.home().join("team-scripts");
+    std::fs::create_dir_all(&shared_directory).unwrap();
+    std::fs::write(
+        shared_directory.join("compress-logs.sh"),
+        "#!/usr/bin/env sh\necho shared >> marker.txt",
+    )
+    .unwrap();
+    std::fs::write(shared_directory.join("show-date.sh"), "date").unwrap();
+    std::fs::write(
+        shared_directory.join("index.json"),
+        json!({
+            "scripts": {
+                "compress-logs": {
+                    "request": "compress the logs directory",
+                    "description": "Compress the logs the team way",
+                    "tags": ["ops"]
+                }
+            }
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    sandbox.write_configurations(json!({
+        "enable_cache": true,
+        "script_search_paths": ["~/team-scripts", "~/missing-scripts"],
+        "preferred_clis": []
+    }));
+
+    (sandbox, shared_directory)
+}
+
+fn read_marker(sandbox: &Sandbox) -> String {
+    std::fs::read_to_string(sandbox.working_directory().join("marker.txt")).unwrap()
+}
+
+#[test]
+fn shared_scripts_are_listed_and_found_with_their_source() {
+    let (sandbox, shared_directory) = sandbox_with_shared_directory();
+
+    let run = sandbox.run(&["list"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("SOURCE"));
+    assert!(run.stdout.contains(&shared_directory.display().to_string()));
+    assert!(run.stdout.contains("show-date"));
+    assert!(
+        run.stdout
+            .contains("The shared scripts in ~/missing-scripts are skipped")
+    );
+
+    let run = sandbox.run(&["run", "compress the log directory"], &[], "1\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("* Compress the logs the team way"));
+    assert_eq!(read_marker(&sandbox), "shared\n");
+}
+
+#[test]
+fn personal_scripts_hide_shared_ones_of_the_same_name() {
+    let (sandbox, _) = sandbox_with_shared_directory();
+    let reply: String = json!({
+        "action": "execute",
+        "command": "echo personal >> marker.txt",
+        "explanation": "Compress the logs my way"
+    })
+    .to_string();
+    let run = sandbox.run(
+        &["run", "pack the logs"],
+        &[(1, &reply)],
+        "y\ncompress-logs\n\n",
+    );
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+
+    let run = sandbox.run(&["show", "compress-logs"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("echo personal >> marker.txt"));
+    assert!(!run.stdout.contains("Shared from"));
+
+    // Once the personal script is removed, the shared one is back
+    let run = sandbox.run(&["remove", "compress-logs"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    let run = sandbox.run(&["show", "compress-logs"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Shared from"));
+    assert!(run.stdout.contains("echo shared >> marker.txt"));
+}
+
+#[test]
+fn shared_scripts_are_read_only() {
+    let (sandbox, shared_directory) = sandbox_with_shared_directory();
+
+    let run = sandbox.run(&["rename", "compress-logs", "pack-logs"], &[], "");
+    assert!(!run.success());
+    assert!(run.stderr.contains("which is read-only"));
+
+    let run = sandbox.run(&["edit", "compress-logs"], &[], "");
+    assert!(!run.success());
+    assert!(run.stderr.contains("which is read-only"));
+
+    let run = sandbox.run(&["remove", "compress-logs"], &[], "");
+    assert!(run.stdout.contains("which is read-only"));
+    assert!(shared_directory.join("compress-logs.sh").exists());
+
+    // Running a shared script leaves its directory as it is
+    let index_before: String =
+        std::fs::read_to_string(shared_directory.join("index.json")).unwrap();
+    let run = sandbox.run(&["run", "compress the logs directory"], &[], "1\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(
+        std::fs::read_to_string(shared_directory.join("index.json")).unwrap(),
+        index_before
+    );
+}
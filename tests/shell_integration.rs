@@ -0,0 +1,57 @@
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn suggest_prints_only_the_command_without_running_it() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.run(
+        &["suggest", "write a marker"],
+        &[(
+            1,
+            r#"{"action": "execute", "command": "echo suggested > marker.txt", "explanation": "Writes a marker"}"#,
+        )],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(run.stdout, "echo suggested > marker.txt\n");
+    assert!(run.stderr.contains("Writes a marker"));
+    assert!(!sandbox.working_directory().join("marker.txt").exists());
+}
+
+#[test]
+fn suggest_asks_questions_on_stderr_and_chains_plans() {
+    let sandbox = Sandbox::new();
+    let request_information: &str = r#"{"action": "request_information", "request_additional_information": "Which directory should I clean?"}"#;
+    let plan: &str = r#"{"action": "plan", "steps": [{"command": "cd build", "explanation": "Enters the directory", "is_destructive": false}, {"command": "rm -rf ./cache", "explanation": "Removes the cache", "is_destructive": true}]}"#;
+    let run = sandbox.run(
+        &["suggest", "clean the cache"],
+        &[(1, request_information), (2, plan)],
+        "build\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(run.stdout, "cd build && rm -rf ./cache\n");
+    assert!(run.stderr.contains("Which directory should I clean?"));
+    assert!(run.stderr.contains("deletes files recursively"));
+}
+
+#[test]
+fn init_prints_a_function_bound_to_suggest() {
+    let sandbox = Sandbox::new();
+
+    for (shell, binding) in [
+        ("bash", r#"bind -x '"\C-g": _you_suggest'"#),
+        ("zsh", "bindkey '^G' _you_suggest"),
+        ("fish", r"bind \cg _you_suggest"),
+    ] {
+        let run = sandbox.run(&["init", shell], &[], "");
+        assert!(run.success(), "{}{}", run.stdout, run.stderr);
+        assert!(run.stdout.contains("command you suggest --"));
+        assert!(run.stdout.contains(binding));
+    }
+
+    let run = sandbox.run(&["init", "powershell"], &[], "");
+    assert_eq!(run.code(), Some(2));
+}
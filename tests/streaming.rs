@@ -0,0 +1,72 @@
+mod common;
+
+use common::Sandbox;
+
+fn streaming_sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(serde_json::json!({
+        "enable_streaming": true,
+        "preferred_clis": []
+    }));
+
+    sandbox
+}
+
+#[test]
+fn explain_streams_the_explanation_once() {
+    let sandbox = streaming_sandbox();
+    let run = sandbox.run(
+        &["explain", "ls -la"],
+        &[(
+            1,
+            r#"{"explanation": "Lists \"every\" file! with details"}"#,
+        )],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("    * Lists \"every\" file! with details\n")
+    );
+    assert_eq!(run.stdout.matches("with details").count(), 1);
+}
+
+#[test]
+fn run_streams_the_explanation_before_the_prompt() {
+    let sandbox = streaming_sandbox();
+    let run = sandbox.run(
+        &["run", "say hello"],
+        &[(
+            1,
+            r#"{"action": "execute", "command": "echo hello-from-you", "explanation": "Print a greeting"}"#,
+        )],
+        "y\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    let streamed: usize = run.stdout.find("    * Print a greeting\n").unwrap();
+    let prompt: usize = run.stdout.find("Your input:").unwrap();
+    assert!(streamed < prompt);
+    assert!(run.stdout.contains("hello-from-you"));
+}
+
+#[test]
+fn streaming_still_retries_malformed_json() {
+    let sandbox = streaming_sandbox();
+    let run = sandbox.run(
+        &["explain", "ls -la"],
+        &[
+            (1, r#"{"explanation": 42}"#),
+            (2, r#"{"explanation": "Lists files"}"#),
+        ],
+        "",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("LLM returned a wrong JSON, retrying...")
+    );
+    assert!(run.stdout.contains("    * Lists files\n"));
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+fn tool_calling_sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "llm": { "enable_tool_calling": true },
+        "preferred_clis": []
+    }));
+
+    sandbox
+}
+
+#[test]
+fn run_executes_a_tool_call() {
+    let sandbox = tool_calling_sandbox();
+    let run = sandbox.run_with_llm_script(
+        &["run", "say hello"],
+        &[json!({
+            "turn": 1,
+            "tool_call": {
+                "name": "execute",
+                "arguments": { "command": "echo hello-from-a-tool", "explanation": "Print a greeting" }
+            }
+        })],
+        "y\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Print a greeting"));
+    assert!(run.stdout.contains("hello-from-a-tool"));
+}
+
+#[test]
+fn run_relays_a_request_for_information_tool_call() {
+    let sandbox = tool_calling_sandbox();
+    let run = sandbox.run_with_llm_script(
+        &["run", "count files"],
+        &[
+            json!({
+                "turn": 1,
+                "tool_call": {
+                    "name": "request_information",
+                    "arguments": { "request_additional_information": "Which directory?" }
+                }
+            }),
+            json!({
+                "turn": 2,
+                "tool_call": {
+                    "name": "execute",
+                    "arguments": { "command": "echo counted", "explanation": "Count files" }
+                }
+            }),
+        ],
+        "this one\ny\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("Which directory?"));
+    assert!(run.stdout.contains("counted"));
+}
+
+#[test]
+fn run_repairs_an_invalid_tool_call() {
+    let sandbox = tool_calling_sandbox();
+    let run = sandbox.run_with_llm_script(
+        &["run", "say hello"],
+        &[
+            json!({
+                "turn": 1,
+                "tool_call": { "name": "launch_rockets", "arguments": {} }
+            }),
+            json!({
+                "turn": 2,
+                "tool_call": {
+                    "name": "execute",
+                    "arguments": { "command": "echo hello-from-a-tool", "explanation": "Print a greeting" }
+                }
+            }),
+        ],
+        "y\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(
+        run.stdout
+            .contains("LLM made an invalid tool call, retrying...")
+    );
+    assert!(run.stdout.contains("hello-from-a-tool"));
+}
+
+#[test]
+fn run_accepts_prose_json_when_tools_are_offered() {
+    let sandbox = tool_calling_sandbox();
+    let run = sandbox.run(
+        &["run", "say hello"],
+        &[(
+            1,
+            r#"Here you go: {"action": "execute", "command": "echo hello-in-prose", "explanation": "Print a greeting"}"#,
+        )],
+        "y\nn\n",
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("hello-in-prose"));
+}
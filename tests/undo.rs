@@ -0,0 +1,127 @@
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+
+fn execute(command: &str) -> String {
+    json!({ "action": "execute", "command": command, "explanation": "Scripted command" })
+        .to_string()
+}
+
+/// Runs `command` through the agent, approving it with `approval` and declining to save it.
+fn run_approved(sandbox: &Sandbox, command: &str, approval: &str) {
+    let run = sandbox.run(
+        &["run", "do something"],
+        &[(1, &execute(command))],
+        &format!("{}\nn\n", approval),
+    );
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+}
+
+#[test]
+fn undo_restores_edited_and_moved_files() {
+    let sandbox = Sandbox::new();
+    let working_directory = sandbox.working_directory();
+    std::fs::write(working_directory.join("notes.txt"), "old line\n").unwrap();
+
+    run_approved(
+        &sandbox,
+        "sed -i 's/old/new/' notes.txt && mv notes.txt renamed.txt",
+        "y",
+    );
+    assert!(!working_directory.join("notes.txt").exists());
+
+    let run = sandbox.run(&["undo"], &[], "y\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("has been undone"));
+    assert_eq!(
+        std::fs::read_to_string(working_directory.join("notes.txt")).unwrap(),
+        "old line\n"
+    );
+    assert!(!working_directory.join("renamed.txt").exists());
+}
+
+#[test]
+fn undo_restores_deleted_directories_and_removes_created_files() {
+    let sandbox = Sandbox::new();
+    let working_directory = sandbox.working_directory();
+    std::fs::create_dir_all(working_directory.join("data/nested")).unwrap();
+    std::fs::write(working_directory.join("data/nested/values.csv"), "1,2\n").unwrap();
+    std::fs::write(working_directory.join("a.log"), "a\n").unwrap();
+    std::fs::write(working_directory.join("b.log"), "b\n").unwrap();
+
+    run_approved(
+        &sandbox,
+        "rm -r data *.log; echo summary > report.txt",
+        "yes",
+    );
+    assert!(!working_directory.join("data").exists());
+    assert!(working_directory.join("report.txt").exists());
+
+    let run = sandbox.run(&["undo"], &[], "y\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert_eq!(
+        std::fs::read_to_string(working_directory.join("data/nested/values.csv")).unwrap(),
+        "1,2\n"
+    );
+    assert!(working_directory.join("a.log").exists());
+    assert!(working_directory.join("b.log").exists());
+    assert!(!working_directory.join("report.txt").exists());
+}
+
+#[test]
+fn undo_goes_back_one_command_at_a_time() {
+    let sandbox = Sandbox::new();
+    let working_directory = sandbox.working_directory();
+
+    run_approved(&sandbox, "echo first > first.txt", "y");
+    run_approved(&sandbox, "echo second > second.txt", "y");
+
+    let run = sandbox.run(&["undo"], &[], "y\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("echo second > second.txt"));
+    assert!(working_directory.join("first.txt").exists());
+    assert!(!working_directory.join("second.txt").exists());
+
+    let run = sandbox.run(&["undo"], &[], "y\n");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(!working_directory.join("first.txt").exists());
+
+    let run = sandbox.run(&["undo"], &[], "");
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("There is nothing to undo."));
+}
+
+#[test]
+fn undo_asks_before_restoring() {
+    let sandbox = Sandbox::new();
+    let working_directory = sandbox.working_directory();
+
+    run_approved(&sandbox, "echo kept > kept.txt", "y");
+
+    let run = sandbox.run(&["undo"], &[], "n\n");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("remove"));
+    assert!(run.stdout.contains("Nothing has been changed."));
+    assert!(working_directory.join("kept.txt").exists());
+}
+
+#[test]
+fn nothing_is_journaled_when_the_journal_is_disabled() {
+    let sandbox = Sandbox::new();
+    sandbox.write_configurations(json!({
+        "journal": { "enabled": false },
+        "preferred_clis": []
+    }));
+
+    run_approved(&sandbox, "echo untracked > untracked.txt", "y");
+    let run = sandbox.run(&["undo"], &[], "");
+
+    assert!(run.success(), "{}{}", run.stdout, run.stderr);
+    assert!(run.stdout.contains("There is nothing to undo."));
+    assert!(sandbox.working_directory().join("untracked.txt").exists());
+}